@@ -58,7 +58,7 @@ const init = async () => {
         codeId,
         {
             owner: ownerAddress,
-            recipient: receipientAddress,
+            recipients: [[receipientAddress, 10000]],
             cliff_amount: "1",
             vesting_amount: "1000",
             start_time: moment().unix().toString(),
@@ -47,7 +47,7 @@ const init = async () => {
             contractAddres,
             {
                 "withdraw_cliff_vested_funds": {
-                    "denom": "uluna",
+                    "asset_info": { "native": "uluna" },
                 }
             },
         )],
@@ -64,7 +64,7 @@ const init = async () => {
             contractAddres,
             {
                 "withdraw_vested_funds": {
-                    "denom": "uluna",
+                    "asset_info": { "native": "uluna" },
                 }
             },
         )],
@@ -1,78 +1,166 @@
-use crate::state::{CONFIG, STATE};
+use crate::events::{admin_event, join_addresses, join_recipients, stake_event, withdraw_event};
+use crate::feegrant::{grant_allowance_msg, query_remaining_allowance, revoke_allowance_msg};
+use crate::state::{
+    withdrawal_history, APPROVED_DESTINATIONS, CONFIG, FEE_BUDGET, FEE_GRANTS, FUNDED_AMOUNT,
+    IBC_TRANSFERS, IBC_TRANSFER_REPLY, LEGACY_CONFIG, OPERATORS, OWNER_WHITELISTED, PAUSED,
+    PENDING_WITHDRAWALS, PENDING_WITHDRAWAL_ID, POSITIONS, POSITION_ID, STATE, TOKEN_APPROVALS,
+    UNBONDING, WITHDRAWAL_HISTORY_ID,
+};
+use crate::transfer::transfer_msg;
+use crate::vesting::{self, Schedule};
+use crate::{validate_schedule, ContractError, State};
 use crate::{
-    AddToWhitelistMsg, Config, DelegateFundsMsg, ExecuteMsg, InstantiateMsg, QueryMsg,
-    RedelegateFundsMsg, RemoveFromWhitelistMsg, UndelegateFundsMsg, UpdateOwnerMsg,
-    UpdateRecipientMsg, WithdrawDelegatorRewardMsg, WithdrawVestedFundsMsg,
+    AddToWhitelistMsg, AmendScheduleMsg, ApproveAllMsg, ApproveMsg, ApprovedDestinationsResponse,
+    ClawbackMsg, Config, ConfigResponse, Cw20HookMsg, DelegateFundsMsg, DestinationMsg, ExecuteMsg,
+    FeeAllowanceMsg, FeeGrant, FeeGrantsResponse, FundingResponse, IbcLifecycleComplete, IbcPayout,
+    IbcTransfer, InstantiateMsg, MigrateMsg, PausedResponse, PayoutDestination, PendingWithdrawal,
+    PendingWithdrawalMsg, PendingWithdrawalsResponse, PrepareLiquidityMsg, QueryMsg,
+    RedelegateFundsMsg, RemoveFromWhitelistMsg, RevokeAllMsg, RevokeFeeAllowanceMsg, RevokeMsg,
+    SendNftMsg, SolvencyResponse, StateResponse, SudoMsg, SummaryResponse, Tranche, TransferNftMsg,
+    UndelegateFundsMsg, UpdateGuardianMsg, UpdateIbcPayoutMsg, UpdateLiquidityGuardMsg,
+    UpdateOwnerMsg, UpdateRecipientMsg, UpdateWithdrawalDelayMsg, VestingPosition,
+    WithdrawDelegatorRewardMsg, WithdrawPeriodLimit, WithdrawVestedFundsMsg, WithdrawableResponse,
+    WithdrawalHistoryEntry, WithdrawalHistoryResponse, WithdrawalRecord,
 };
-use crate::{ContractError, State};
+#[cfg(not(any(feature = "library", feature = "factory")))]
+use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    entry_point, to_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Deps, DepsMut, DistributionMsg,
-    Env, MessageInfo, Response, StakingMsg, StdResult, Uint128, Uint64,
+    from_binary, to_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Decimal, Deps, DepsMut,
+    DistributionMsg, Env, Event, MessageInfo, Order, QuerierWrapper, Reply, Response, StakingMsg,
+    StdError, StdResult, Storage, SubMsg, Timestamp, Uint128, Uint64, WasmMsg,
+};
+use cw20::{Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg, TokenInfoResponse};
+use cw721::{
+    AllNftInfoResponse, Approval, ApprovalResponse, ApprovalsResponse, ContractInfoResponse,
+    Cw721ReceiveMsg, Expiration, NftInfoResponse, NumTokensResponse, OperatorsResponse,
+    OwnerOfResponse, TokensResponse,
 };
+use cw_asset::{Asset, AssetInfo};
+use cw_storage_plus::Bound;
+
+// unbonding period of the staking module on phoenix-1
+const UNBONDING_PERIOD: u64 = 21 * 24 * 60 * 60;
+// recipient shares are expressed in basis points
+const TOTAL_BPS: u16 = 10_000;
+
+const IBC_TRANSFER_REPLY_ID: u64 = 1;
+
+// cw721 contract info of the recipient positions
+const POSITION_NAME: &str = "Community Pool Vesting Position";
+const POSITION_SYMBOL: &str = "VEST";
+
+// module account of x/gov on phoenix-1, the first 20 bytes of sha256("gov")
+pub const GOVERNANCE_MODULE: &str = "terra10d07y265gmmuvt4z0w9aw880jnsr700juxf95n";
+
+// longest schedule accepted at instantiation, in seconds
+pub const MAX_VESTING_DURATION: u64 = 100 * 365 * 24 * 60 * 60;
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
 
-#[cfg_attr(not(feature = "library"), entry_point)]
+#[cfg_attr(not(any(feature = "library", feature = "factory")), entry_point)]
 pub fn instantiate(
     deps: DepsMut,
     env: Env,
-    _info: MessageInfo,
+    info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
-    if let Some(start_time) = msg.start_time {
-        if start_time.u64() < env.block.time.seconds() {
+    msg.validate(env.block.time.seconds())?;
+    let start_time = msg
+        .start_time
+        .unwrap_or_else(|| Uint64::new(env.block.time.seconds()));
+
+    let recipients = validate_recipients(deps.as_ref(), &msg.recipients)?;
+    let governance_owner = msg.governance_owner.unwrap_or(false);
+
+    let vesting_asset = match msg.vesting_asset {
+        Some(vesting_asset) => vesting_asset.check(deps.api, None)?,
+        None => AssetInfo::native("uluna"),
+    };
+
+    validate_ibc_payout(&vesting_asset, &recipients, &msg.ibc_payout)?;
+
+    let funded_amount = vesting_funds_sent(&vesting_asset, &info.funds)?;
+    let total_amount = msg
+        .unlocked_amount
+        .checked_add(msg.cliff_amount)?
+        .checked_add(msg.vesting_amount)?;
+    if msg.require_full_funding.unwrap_or(false) {
+        // cw20 tokens cannot be attached to the instantiation, they are funded through the hook
+        if !matches!(vesting_asset, AssetInfo::Native(_)) {
             return Err(ContractError::ValidationError(
-                "Start time cannot be in the past".to_string(),
+                "Full funding can only be required for a native vesting asset".to_string(),
             ));
         }
-        if msg.end_time < start_time {
+        if funded_amount < total_amount {
             return Err(ContractError::ValidationError(
-                "End time cannot be before start time".to_string(),
+                "Funds sent do not cover the vesting schedule".to_string(),
             ));
         }
     }
+    if total_amount > query_asset_supply(deps.as_ref(), &vesting_asset)? {
+        return Err(ContractError::ValidationError(
+            "Total amount exceeds the supply of the vesting asset".to_string(),
+        ));
+    }
+    FUNDED_AMOUNT.save(deps.storage, &funded_amount)?;
 
     CONFIG.save(
         deps.storage,
         &Config {
             owner: deps.api.addr_validate(&msg.owner)?,
-            recipient: deps.api.addr_validate(&msg.recipient)?,
+            guardian: msg
+                .guardian
+                .map(|guardian| deps.api.addr_validate(&guardian))
+                .transpose()?,
+            recipients: recipients.clone(),
+            vesting_asset: vesting_asset.clone(),
+            withdrawal_delay: msg.withdrawal_delay,
+            max_withdraw_per_period: msg.max_withdraw_per_period.clone(),
+            ibc_payout: msg.ibc_payout.clone(),
             unlocked_amount: msg.unlocked_amount,
             cliff_amount: msg.cliff_amount,
             vesting_amount: msg.vesting_amount,
-            start_time: msg
-                .start_time
-                .unwrap_or(Uint64::new(env.block.time.seconds())),
+            start_time,
             end_time: msg.end_time,
             //this whitelist is to designate users who can call the withdraw vested funds message. they cannot perform any other action
-            whitelisted_addresses: vec![
-                deps.api.addr_validate(&msg.owner)?,
-                deps.api.addr_validate(&msg.recipient)?,
-            ],
+            whitelisted_addresses: [deps.api.addr_validate(&msg.owner)?]
+                .into_iter()
+                .chain(recipients.iter().map(|(addr, _)| addr.clone()))
+                .collect(),
+            governance_owner,
+            liquidity_guard: msg.liquidity_guard.unwrap_or(false),
         },
     )?;
 
+    sync_positions(deps.storage, &recipients)?;
+
     STATE.save(
         deps.storage,
         &State {
-            last_withdrawn_time: msg
-                .start_time
-                .unwrap_or(Uint64::new(env.block.time.seconds())),
+            last_withdrawn_time: start_time,
             cliff_amount_withdrawn: Uint128::zero(),
             unlocked_amount_withdrawn: Uint128::zero(),
+            recent_withdrawals: vec![],
         },
     )?;
 
-    Ok(Response::new()
-        .add_attribute("action", "instantiate")
-        .add_attribute("recipient", msg.recipient)
-        .add_attribute(
-            "start_time",
-            msg.start_time
-                .unwrap_or(Uint64::new(env.block.time.seconds())),
-        )
-        .add_attribute("end_time", msg.end_time))
+    Ok(Response::new().add_event(
+        admin_event("instantiate")
+            .add_attribute("owner", msg.owner)
+            .add_attribute("recipients", join_recipients(&recipients))
+            .add_attribute("vesting_asset", vesting_asset.to_string())
+            .add_attribute("funded_amount", funded_amount)
+            .add_attribute(
+                "start_time",
+                msg.start_time
+                    .unwrap_or(Uint64::new(env.block.time.seconds())),
+            )
+            .add_attribute("end_time", msg.end_time),
+    ))
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
+#[cfg_attr(not(any(feature = "library", feature = "factory")), entry_point)]
 pub fn execute(
     deps: DepsMut,
     env: Env,
@@ -90,504 +178,2829 @@ pub fn execute(
         ExecuteMsg::UndelegateFunds(data) => undelegate_funds(deps, env, info, data),
         ExecuteMsg::RedelegateFunds(data) => redelegate_funds(deps, env, info, data),
         ExecuteMsg::AddToWhitelist(data) => add_to_whitelist(deps, info, data),
-        ExecuteMsg::RemoveFromWhitelist(data) => remove_from_whitelist(deps, info, data),
+        ExecuteMsg::RemoveFromWhitelist(data) => remove_from_whitelist(deps, env, info, data),
         ExecuteMsg::UpdateOwner(data) => update_owner(deps, info, data),
         ExecuteMsg::UpdateRecipient(data) => update_recipient(deps, info, data),
+        ExecuteMsg::Receive(data) => receive_cw20(deps, info, data),
+        ExecuteMsg::Fund {} => fund(deps, info),
+        ExecuteMsg::AmendSchedule(data) => amend_schedule(deps, env, info, data),
+        ExecuteMsg::Pause {} => set_paused(deps, info, true),
+        ExecuteMsg::Unpause {} => set_paused(deps, info, false),
+        ExecuteMsg::UpdateGuardian(data) => update_guardian(deps, info, data),
+        ExecuteMsg::FinalizeWithdrawal(data) => finalize_withdrawal(deps, env, info, data),
+        ExecuteMsg::CancelWithdrawal(data) => cancel_withdrawal(deps, info, data),
+        ExecuteMsg::UpdateWithdrawalDelay(data) => update_withdrawal_delay(deps, info, data),
+        ExecuteMsg::UpdateLiquidityGuard(data) => update_liquidity_guard(deps, info, data),
+        ExecuteMsg::PrepareLiquidity(data) => prepare_liquidity(deps, env, info, data),
+        ExecuteMsg::ApproveDestination(data) => update_destination(deps, info, data, true),
+        ExecuteMsg::RevokeDestination(data) => update_destination(deps, info, data, false),
+        ExecuteMsg::UpdateIbcPayout(data) => update_ibc_payout(deps, info, data),
+        ExecuteMsg::TransferNft(data) => transfer_nft(deps, env, info, data),
+        ExecuteMsg::SendNft(data) => send_nft(deps, env, info, data),
+        ExecuteMsg::Approve(data) => approve(deps, env, info, data),
+        ExecuteMsg::Revoke(data) => revoke(deps, env, info, data),
+        ExecuteMsg::ApproveAll(data) => approve_all(deps, env, info, data),
+        ExecuteMsg::RevokeAll(data) => revoke_all(deps, info, data),
+        ExecuteMsg::TopUpFeeBudget {} => top_up_fee_budget(deps, info),
+        ExecuteMsg::GrantFeeAllowance(data) => grant_fee_allowance(deps, env, info, data),
+        ExecuteMsg::RevokeFeeAllowance(data) => revoke_fee_allowance(deps, env, info, data),
     }
 }
 
-fn update_recipient(
-    deps: DepsMut,
-    info: MessageInfo,
-    data: UpdateRecipientMsg,
-) -> Result<Response, ContractError> {
+#[cfg_attr(not(any(feature = "library", feature = "factory")), entry_point)]
+pub fn migrate(deps: DepsMut, _env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    // configs stored with a single recipient give it the full share
+    if CONFIG.load(deps.storage).is_err() {
+        let legacy = LEGACY_CONFIG.load(deps.storage)?;
+        CONFIG.save(
+            deps.storage,
+            &Config {
+                owner: legacy.owner,
+                guardian: legacy.guardian,
+                recipients: vec![(legacy.recipient, TOTAL_BPS)],
+                vesting_asset: legacy.vesting_asset,
+                withdrawal_delay: legacy.withdrawal_delay,
+                max_withdraw_per_period: legacy.max_withdraw_per_period,
+                ibc_payout: None,
+                unlocked_amount: legacy.unlocked_amount,
+                cliff_amount: legacy.cliff_amount,
+                vesting_amount: legacy.vesting_amount,
+                start_time: legacy.start_time,
+                end_time: legacy.end_time,
+                whitelisted_addresses: legacy.whitelisted_addresses,
+                governance_owner: false,
+                liquidity_guard: false,
+            },
+        )?;
+    }
+
+    // contracts from before stable token ids used the recipient index, which the first mint keeps
+    if POSITION_ID.may_load(deps.storage)?.is_none() {
+        let config = CONFIG.load(deps.storage)?;
+        sync_positions(deps.storage, &config.recipients)?;
+        for addr in &config.whitelisted_addresses {
+            if *addr != config.owner && !config.recipients.iter().any(|(r, _)| r == addr) {
+                OWNER_WHITELISTED.save(deps.storage, addr, &true)?;
+            }
+        }
+    }
+
+    if let Some(governance_owner) = msg.governance_owner {
+        let config = CONFIG.load(deps.storage)?;
+        let config = if governance_owner {
+            let owner = deps.api.addr_validate(GOVERNANCE_MODULE)?;
+            with_owner(config, owner)
+        } else {
+            config
+        };
+        CONFIG.save(
+            deps.storage,
+            &Config {
+                governance_owner,
+                ..config
+            },
+        )?;
+    }
+
+    Ok(Response::new().add_event(admin_event("migrate")))
+}
+
+fn set_paused(deps: DepsMut, info: MessageInfo, paused: bool) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
-    if config.owner != info.sender {
+    if config.owner != info.sender && config.guardian.as_ref() != Some(&info.sender) {
         return Err(ContractError::Unauthorized {});
     }
-    CONFIG.save(
-        deps.storage,
-        &Config {
-            owner: config.owner,
-            recipient: deps.api.addr_validate(&data.recipient)?,
-            unlocked_amount: config.unlocked_amount,
-            cliff_amount: config.cliff_amount,
-            vesting_amount: config.vesting_amount,
-            start_time: config.start_time,
-            end_time: config.end_time,
-            whitelisted_addresses: config.whitelisted_addresses,
-        },
-    )?;
-    Ok(Response::new()
-        .add_attribute("action", "update_recipient")
-        .add_attribute("owner", format!("{:?}", data.recipient)))
+    save_paused(deps.storage, paused, info.sender.as_str())
 }
 
-fn update_owner(
+// also reached through sudo, where the sender is `sudo`
+fn save_paused(
+    storage: &mut dyn Storage,
+    paused: bool,
+    sender: &str,
+) -> Result<Response, ContractError> {
+    PAUSED.save(storage, &paused)?;
+
+    Ok(Response::new().add_event(
+        admin_event(if paused { "pause" } else { "unpause" }).add_attribute("sender", sender),
+    ))
+}
+
+fn is_paused(storage: &dyn Storage) -> StdResult<bool> {
+    Ok(PAUSED.may_load(storage)?.unwrap_or_default())
+}
+
+fn assert_not_paused(storage: &dyn Storage) -> Result<(), ContractError> {
+    if is_paused(storage)? {
+        return Err(ContractError::Paused {});
+    }
+    Ok(())
+}
+
+fn update_guardian(
     deps: DepsMut,
     info: MessageInfo,
-    data: UpdateOwnerMsg,
+    data: UpdateGuardianMsg,
 ) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
     if config.owner != info.sender {
         return Err(ContractError::Unauthorized {});
     }
-    let old_owner = config.owner;
-    let new_owner = deps.api.addr_validate(&data.owner)?;
-
-    // remove the old owner and add the new owner
-    let mut new_addresses = vec![new_owner.clone(), config.recipient.clone()];
-    for addr in config.whitelisted_addresses {
-        if addr != old_owner && addr != config.recipient && addr != new_owner {
-            new_addresses.push(addr);
-        }
-    }
-
+    let guardian = data
+        .guardian
+        .map(|guardian| deps.api.addr_validate(&guardian))
+        .transpose()?;
     CONFIG.save(
         deps.storage,
         &Config {
-            owner: new_owner,
-            recipient: config.recipient,
-            unlocked_amount: config.unlocked_amount,
-            cliff_amount: config.cliff_amount,
-            vesting_amount: config.vesting_amount,
-            start_time: config.start_time,
-            end_time: config.end_time,
-            whitelisted_addresses: new_addresses,
+            guardian: guardian.clone(),
+            ..config
         },
     )?;
 
-    Ok(Response::new()
-        .add_attribute("action", "update_owner")
-        .add_attribute("owner", format!("{:?}", data.owner)))
+    Ok(Response::new().add_event(
+        admin_event("update_guardian").add_attribute(
+            "guardian",
+            guardian
+                .map(|guardian| guardian.to_string())
+                .unwrap_or_default(),
+        ),
+    ))
 }
 
-fn remove_from_whitelist(
-    deps: DepsMut,
-    info: MessageInfo,
-    data: RemoveFromWhitelistMsg,
-) -> Result<Response, ContractError> {
+fn fund(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
-    if config.owner != info.sender {
-        return Err(ContractError::Unauthorized {});
+    if info.funds.is_empty() {
+        return Err(ContractError::NoFunds {});
     }
-    //always keep recipient and owner address on the whitelist
-    let mut new_addresses = vec![config.owner.clone(), config.recipient.clone()];
-    for addr in config.whitelisted_addresses {
-        if !data.addresses.contains(&addr) && addr != config.recipient && addr != config.owner {
-            new_addresses.push(addr);
+    // cw20 vesting assets are funded through the receive hook
+    if info
+        .funds
+        .iter()
+        .any(|coin| AssetInfo::native(&coin.denom) != config.vesting_asset)
+    {
+        return Err(ContractError::UnsupportedAsset {});
+    }
+
+    let amount = vesting_funds_sent(&config.vesting_asset, &info.funds)?;
+    let funded_amount = record_funding(deps.storage, amount)?;
+
+    Ok(Response::new().add_event(
+        admin_event("fund")
+            .add_attribute("sender", info.sender)
+            .add_attribute("asset", config.vesting_asset.to_string())
+            .add_attribute("amount", amount)
+            .add_attribute("funded_amount", funded_amount),
+    ))
+}
+
+fn vesting_funds_sent(vesting_asset: &AssetInfo, funds: &[Coin]) -> Result<Uint128, ContractError> {
+    checked_sum(
+        funds
+            .iter()
+            .filter(|coin| AssetInfo::native(&coin.denom) == *vesting_asset)
+            .map(|coin| coin.amount),
+    )
+}
+
+fn query_asset_supply(deps: Deps, vesting_asset: &AssetInfo) -> Result<Uint128, ContractError> {
+    match vesting_asset {
+        AssetInfo::Native(denom) => Ok(deps.querier.query_supply(denom)?.amount),
+        AssetInfo::Cw20(token) => {
+            let token_info: TokenInfoResponse = deps
+                .querier
+                .query_wasm_smart(token, &Cw20QueryMsg::TokenInfo {})?;
+            Ok(token_info.total_supply)
         }
+        _ => Err(ContractError::UnsupportedAsset {}),
     }
-    CONFIG.save(
-        deps.storage,
-        &Config {
-            owner: config.owner,
-            recipient: config.recipient,
-            unlocked_amount: config.unlocked_amount,
-            cliff_amount: config.cliff_amount,
-            vesting_amount: config.vesting_amount,
-            start_time: config.start_time,
-            end_time: config.end_time,
-            whitelisted_addresses: new_addresses.clone(),
-        },
-    )?;
-    Ok(Response::new()
-        .add_attribute("action", "remove_from_whitelist")
-        .add_attribute("whitelisted_addresses", format!("{:?}", new_addresses)))
 }
 
-fn add_to_whitelist(
+fn record_funding(storage: &mut dyn Storage, amount: Uint128) -> Result<Uint128, ContractError> {
+    let funded_amount = FUNDED_AMOUNT
+        .may_load(storage)?
+        .unwrap_or_default()
+        .checked_add(amount)?;
+    FUNDED_AMOUNT.save(storage, &funded_amount)?;
+    Ok(funded_amount)
+}
+
+fn receive_cw20(
     deps: DepsMut,
     info: MessageInfo,
-    data: AddToWhitelistMsg,
+    data: Cw20ReceiveMsg,
 ) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
-    if config.owner != info.sender {
-        return Err(ContractError::Unauthorized {});
+    // only the cw20 token being vested can be sent to the contract
+    if config.vesting_asset != AssetInfo::cw20(info.sender.clone()) {
+        return Err(ContractError::UnsupportedAsset {});
     }
-    let mut new_addresses = config.whitelisted_addresses.clone();
-    for addr in data.addresses {
-        if !config.whitelisted_addresses.contains(&addr) {
-            new_addresses.push(deps.api.addr_validate(addr.as_str())?);
+
+    match from_binary(&data.msg)? {
+        Cw20HookMsg::Fund {} => {
+            let funded_amount = record_funding(deps.storage, data.amount)?;
+            Ok(Response::new().add_event(
+                admin_event("fund")
+                    .add_attribute("sender", data.sender)
+                    .add_attribute("asset", config.vesting_asset.to_string())
+                    .add_attribute("amount", data.amount)
+                    .add_attribute("funded_amount", funded_amount),
+            ))
         }
     }
-    CONFIG.save(
-        deps.storage,
-        &Config {
-            owner: config.owner,
-            recipient: config.recipient,
-            unlocked_amount: config.unlocked_amount,
-            cliff_amount: config.cliff_amount,
-            vesting_amount: config.vesting_amount,
-            start_time: config.start_time,
-            end_time: config.end_time,
-            whitelisted_addresses: new_addresses.clone(),
-        },
-    )?;
-    Ok(Response::new()
-        .add_attribute("action", "add_to_whitelist")
-        .add_attribute("whitelisted_addresses", format!("{:?}", new_addresses)))
 }
 
-fn redelegate_funds(
+fn amend_schedule(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    data: RedelegateFundsMsg,
+    data: AmendScheduleMsg,
 ) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
+    let state = STATE.load(deps.storage)?;
     if config.owner != info.sender {
         return Err(ContractError::Unauthorized {});
     }
-    let msg = CosmosMsg::Staking(StakingMsg::Redelegate {
-        src_validator: data.src_validator.clone(),
-        dst_validator: data.dst_validator.clone(),
-        amount: data.amount.clone(),
-    });
-    let send_reward_msg_src =
-        _withdraw_delegation_rewards(&deps.as_ref(), &env, &config.recipient, &data.src_validator);
-    let send_reward_msg_dst =
-        _withdraw_delegation_rewards(&deps.as_ref(), &env, &config.recipient, &data.dst_validator);
 
-    let mut res = Response::new()
-        .add_message(msg)
-        .add_attribute("action", "redelegate_funds")
-        .add_attribute("src_validator", data.src_validator.to_string())
-        .add_attribute("dst_validator", data.dst_validator.to_string())
-        .add_attribute("denom", data.amount.denom)
-        .add_attribute("amount", data.amount.amount);
+    let new_config = Config {
+        unlocked_amount: data.unlocked_amount.unwrap_or(config.unlocked_amount),
+        cliff_amount: data.cliff_amount.unwrap_or(config.cliff_amount),
+        vesting_amount: data.vesting_amount.unwrap_or(config.vesting_amount),
+        end_time: data.end_time.unwrap_or(config.end_time),
+        ..config.clone()
+    };
 
-    if let Some(send_reward_msg) = send_reward_msg_src {
-        res = res.add_message(DistributionMsg::WithdrawDelegatorReward {
-            validator: data.src_validator,
-        });
-        res = res.add_message(send_reward_msg);
+    if new_config.unlocked_amount < config.unlocked_amount
+        || new_config.cliff_amount < config.cliff_amount
+        || new_config.vesting_amount < config.vesting_amount
+    {
+        return Err(ContractError::ValidationError(
+            "Amounts cannot be decreased".to_string(),
+        ));
     }
-    if let Some(send_reward_msg) = send_reward_msg_dst {
-        res = res.add_message(DistributionMsg::WithdrawDelegatorReward {
-            validator: data.dst_validator,
-        });
-        res = res.add_message(send_reward_msg);
+    if new_config.end_time < config.end_time {
+        return Err(ContractError::ValidationError(
+            "End time cannot be moved earlier".to_string(),
+        ));
+    }
+    validate_schedule(
+        new_config.start_time.u64(),
+        new_config.end_time.u64(),
+        new_config.unlocked_amount,
+        new_config.cliff_amount,
+        new_config.vesting_amount,
+    )?;
+    // funds added to the linear tranche must still vest after what was already withdrawn
+    if new_config.vesting_amount > config.vesting_amount
+        && new_config.end_time <= state.last_withdrawn_time
+    {
+        return Err(ContractError::ValidationError(
+            "End time must be after the last withdrawal to add vesting funds".to_string(),
+        ));
+    }
+    let current_time = env.block.time.seconds();
+    let schedule = Schedule::from(&config);
+    let new_schedule = Schedule::from(&new_config);
+    if vesting::vested_at(&new_schedule, current_time)?
+        < vesting::vested_at(&schedule, current_time)?
+    {
+        return Err(ContractError::ValidationError(
+            "Amendment cannot reduce the already vested amount".to_string(),
+        ));
     }
 
-    Ok(res)
+    // move the last withdrawn time so that the amount already withdrawn stays the same under the new schedule,
+    // rounded up so the recipient can never withdraw more than the new vesting amount
+    let vesting_amount_withdrawn = vesting::vested_at(&schedule, state.last_withdrawn_time.u64())?;
+    let last_withdrawn_time = Uint64::new(vesting::vesting_time(
+        &new_schedule,
+        vesting_amount_withdrawn,
+    )?);
+
+    CONFIG.save(deps.storage, &new_config)?;
+    STATE.save(
+        deps.storage,
+        &State {
+            last_withdrawn_time,
+            ..state
+        },
+    )?;
+
+    Ok(Response::new().add_event(
+        admin_event("amend_schedule")
+            .add_attribute("old_unlocked_amount", config.unlocked_amount)
+            .add_attribute("new_unlocked_amount", new_config.unlocked_amount)
+            .add_attribute("old_cliff_amount", config.cliff_amount)
+            .add_attribute("new_cliff_amount", new_config.cliff_amount)
+            .add_attribute("old_vesting_amount", config.vesting_amount)
+            .add_attribute("new_vesting_amount", new_config.vesting_amount)
+            .add_attribute("old_end_time", config.end_time)
+            .add_attribute("new_end_time", new_config.end_time)
+            .add_attribute("old_last_withdrawn_time", state.last_withdrawn_time)
+            .add_attribute("new_last_withdrawn_time", last_withdrawn_time),
+    ))
 }
 
-fn undelegate_funds(
+fn update_recipient(
     deps: DepsMut,
-    env: Env,
     info: MessageInfo,
-    data: UndelegateFundsMsg,
+    data: UpdateRecipientMsg,
 ) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
     if config.owner != info.sender {
         return Err(ContractError::Unauthorized {});
     }
-    let msg = CosmosMsg::Staking(StakingMsg::Undelegate {
-        validator: data.validator.clone(),
-        amount: data.amount.clone(),
-    });
-    let send_reward_msg =
-        _withdraw_delegation_rewards(&deps.as_ref(), &env, &config.recipient, &data.validator);
-
-    let mut res = Response::new()
-        .add_message(msg)
-        .add_attribute("action", "undelegate_funds")
-        .add_attribute("validator", data.validator.to_string())
-        .add_attribute("denom", data.amount.denom)
-        .add_attribute("amount", data.amount.amount);
-
-    if let Some(send_reward_msg) = send_reward_msg {
-        res = res.add_message(DistributionMsg::WithdrawDelegatorReward {
-            validator: data.validator,
-        });
-        res = res.add_message(send_reward_msg);
-    }
-    Ok(res)
+    save_recipients(deps, config, data)
 }
 
-fn claim_delegator_reward(
+fn save_recipients(
     deps: DepsMut,
-    env: Env,
-    info: MessageInfo,
-    data: WithdrawDelegatorRewardMsg,
+    config: Config,
+    data: UpdateRecipientMsg,
 ) -> Result<Response, ContractError> {
-    let config = CONFIG.load(deps.storage)?;
-    if config.owner != info.sender {
-        return Err(ContractError::Unauthorized {});
+    let recipients = validate_recipients(deps.as_ref(), &data.recipients)?;
+    sync_positions(deps.storage, &recipients)?;
+    CONFIG.save(
+        deps.storage,
+        &Config {
+            recipients: recipients.clone(),
+            ..config
+        },
+    )?;
+    Ok(Response::new().add_event(
+        admin_event("update_recipient").add_attribute("recipients", join_recipients(&recipients)),
+    ))
+}
+
+fn validate_recipients(
+    deps: Deps,
+    recipients: &[(String, u16)],
+) -> Result<Vec<(Addr, u16)>, ContractError> {
+    let mut validated: Vec<(Addr, u16)> = vec![];
+    for (recipient, bps) in recipients {
+        let addr = deps.api.addr_validate(recipient)?;
+        if *bps == 0 {
+            return Err(ContractError::ValidationError(
+                "Recipient share must be positive".to_string(),
+            ));
+        }
+        if validated.iter().any(|(existing, _)| *existing == addr) {
+            return Err(ContractError::ValidationError(
+                "Duplicate recipient".to_string(),
+            ));
+        }
+        validated.push((addr, *bps));
     }
+    if validated
+        .iter()
+        .map(|(_, bps)| u32::from(*bps))
+        .sum::<u32>()
+        != u32::from(TOTAL_BPS)
+    {
+        return Err(ContractError::ValidationError(
+            "Recipient shares must sum to 10000 basis points".to_string(),
+        ));
+    }
+    Ok(validated)
+}
 
-    let send_reward_msg =
-        _withdraw_delegation_rewards(&deps.as_ref(), &env, &config.recipient, &data.validator);
+// splits an amount by the recipients' shares, the rounding dust goes to the first recipient
+fn split_by_share(
+    recipients: &[(Addr, u16)],
+    amount: Uint128,
+) -> Result<Vec<(Addr, Uint128)>, ContractError> {
+    let mut shares: Vec<(Addr, Uint128)> = recipients
+        .iter()
+        .map(|(addr, bps)| {
+            (
+                addr.clone(),
+                amount.multiply_ratio(u128::from(*bps), u128::from(TOTAL_BPS)),
+            )
+        })
+        .collect();
+    let distributed = checked_sum(shares.iter().map(|(_, share)| *share))?;
+    if let Some((_, first)) = shares.first_mut() {
+        *first = first.checked_add(amount.checked_sub(distributed)?)?;
+    }
+    Ok(shares)
+}
 
-    let mut res = Response::new()
-        .add_attribute("action", "withdraw_delegator_rewards")
-        .add_attribute("validator", data.validator.to_string());
+// sum of the amounts, failing instead of panicking on overflow
+fn checked_sum(amounts: impl IntoIterator<Item = Uint128>) -> Result<Uint128, ContractError> {
+    amounts
+        .into_iter()
+        .try_fold(Uint128::zero(), |total, amount| {
+            Ok(total.checked_add(amount)?)
+        })
+}
 
-    if let Some(send_reward_msg) = send_reward_msg {
-        res = res.add_message(DistributionMsg::WithdrawDelegatorReward {
-            validator: data.validator,
-        });
-        res = res.add_message(send_reward_msg);
-    }
-    Ok(res)
+// a withdrawal on its way out of the contract, paid out at once or queued
+struct Payout<'a> {
+    config: &'a Config,
+    sender: &'a Addr,
+    tranche: Tranche,
+    asset: Asset,
+    destination: Option<PayoutDestination>,
+    event: Event,
 }
 
-fn delegate_funds(
-    deps: DepsMut,
-    env: Env,
-    info: MessageInfo,
-    data: DelegateFundsMsg,
+// pays out over IBC when configured for the vesting asset, otherwise to the recipients or destination
+fn payout(
+    storage: &mut dyn Storage,
+    env: &Env,
+    withdrawal: Payout,
 ) -> Result<Response, ContractError> {
-    let config = CONFIG.load(deps.storage)?;
-    if config.owner != info.sender {
-        return Err(ContractError::Unauthorized {});
+    let Payout {
+        config,
+        sender,
+        tranche,
+        asset,
+        destination,
+        event,
+    } = withdrawal;
+    if let Some(destination) = &destination {
+        if !asset.amount.is_zero() {
+            record_payout(
+                storage,
+                env,
+                sender,
+                &tranche,
+                &asset.info,
+                destination.address.to_string(),
+                asset.amount,
+            )?;
+        }
+        return Ok(Response::new()
+            .add_message(destination_msg(&asset, destination)?)
+            .add_event(event.add_attribute("destination", destination.address.to_string())));
     }
-    let msg = CosmosMsg::Staking(StakingMsg::Delegate {
-        validator: data.validator.clone(),
-        amount: data.amount.clone(),
-    });
-    let send_reward_msg =
-        _withdraw_delegation_rewards(&deps.as_ref(), &env, &config.recipient, &data.validator);
 
-    let mut res = Response::new()
-        .add_message(msg)
-        .add_attribute("action", "delegate_funds")
-        .add_attribute("validator", data.validator.to_string())
-        .add_attribute("denom", data.amount.denom)
-        .add_attribute("amount", data.amount.amount);
+    // the share of the IBC payout recipient is sent over IBC, only for the native vesting asset
+    let ibc_payout = match (&config.ibc_payout, &asset.info) {
+        (Some(ibc_payout), AssetInfo::Native(denom)) if asset.info == config.vesting_asset => {
+            Some((ibc_payout, denom))
+        }
+        _ => None,
+    };
+    let mut res = Response::new();
+    let mut event = event;
+    for (payee, amount) in split_by_share(&config.recipients, asset.amount)?
+        .into_iter()
+        .filter(|(_, amount)| !amount.is_zero())
+    {
+        match ibc_payout {
+            Some((ibc_payout, denom)) if payee.as_str() == ibc_payout.recipient => {
+                let timeout = ibc_payout
+                    .timeout_seconds
+                    .checked_mul(Uint64::new(1_000_000_000))?
+                    .checked_add(Uint64::new(env.block.time.nanos()))?;
+                let history_id = record_payout(
+                    storage,
+                    env,
+                    sender,
+                    &tranche,
+                    &asset.info,
+                    ibc_payout.remote_address.clone(),
+                    amount,
+                )?;
+                IBC_TRANSFER_REPLY.save(
+                    storage,
+                    &IbcTransfer {
+                        channel_id: ibc_payout.channel_id.clone(),
+                        tranche: tranche.clone(),
+                        amount,
+                        history_id: Some(history_id),
+                        recipient: payee.clone(),
+                    },
+                )?;
+                let msg = transfer_msg(
+                    &ibc_payout.channel_id,
+                    &Coin::new(amount.u128(), denom),
+                    &env.contract.address,
+                    &ibc_payout.remote_address,
+                    Timestamp::from_nanos(timeout.u64()),
+                );
+                res = res.add_submessage(SubMsg::reply_on_success(msg, IBC_TRANSFER_REPLY_ID));
+                event = event
+                    .add_attribute("ibc_channel_id", ibc_payout.channel_id.clone())
+                    .add_attribute("ibc_remote_address", ibc_payout.remote_address.clone());
+            }
+            _ => {
+                record_payout(
+                    storage,
+                    env,
+                    sender,
+                    &tranche,
+                    &asset.info,
+                    payee.to_string(),
+                    amount,
+                )?;
+                res = res.add_message(Asset::new(asset.info.clone(), amount).transfer_msg(payee)?);
+            }
+        }
+    }
+    Ok(res.add_event(event))
+}
 
-    if let Some(send_reward_msg) = send_reward_msg {
-        res = res.add_message(DistributionMsg::WithdrawDelegatorReward {
-            validator: data.validator,
-        });
-        res = res.add_message(send_reward_msg);
+// appends a payout to the withdrawal history and returns its sequence
+fn record_payout(
+    storage: &mut dyn Storage,
+    env: &Env,
+    sender: &Addr,
+    tranche: &Tranche,
+    asset_info: &AssetInfo,
+    recipient: String,
+    amount: Uint128,
+) -> Result<u64, ContractError> {
+    let id = next_id(WITHDRAWAL_HISTORY_ID.may_load(storage)?)?;
+    WITHDRAWAL_HISTORY_ID.save(storage, &id)?;
+    let denom = match asset_info {
+        AssetInfo::Native(denom) => denom.clone(),
+        AssetInfo::Cw20(token) => token.to_string(),
+        asset_info => asset_info.to_string(),
+    };
+    withdrawal_history().save(
+        storage,
+        id,
+        &WithdrawalHistoryEntry {
+            id,
+            tranche: tranche.clone(),
+            denom,
+            amount,
+            sender: sender.clone(),
+            recipient,
+            time: Uint64::new(env.block.time.seconds()),
+        },
+    )?;
+    Ok(id)
+}
+
+// sends the payout to the destination, executing its hook message with the funds attached
+fn destination_msg(
+    asset: &Asset,
+    destination: &PayoutDestination,
+) -> Result<CosmosMsg, ContractError> {
+    let msg = match &destination.msg {
+        Some(msg) => msg.clone(),
+        None => return Ok(asset.transfer_msg(destination.address.to_string())?),
+    };
+    match &asset.info {
+        AssetInfo::Native(denom) => Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: destination.address.to_string(),
+            msg,
+            funds: vec![Coin::new(asset.amount.u128(), denom)],
+        })),
+        AssetInfo::Cw20(token) => Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: token.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Send {
+                contract: destination.address.to_string(),
+                amount: asset.amount,
+                msg,
+            })?,
+            funds: vec![],
+        })),
+        _ => Err(ContractError::UnsupportedAsset {}),
     }
+}
 
-    Ok(res)
+fn payout_destination(
+    deps: Deps,
+    config: &Config,
+    data: &WithdrawVestedFundsMsg,
+) -> Result<Option<PayoutDestination>, ContractError> {
+    match &data.destination {
+        Some(destination) => {
+            let destination = PayoutDestination {
+                address: deps.api.addr_validate(destination)?,
+                msg: data.msg.clone(),
+            };
+            assert_destination_approved(deps.storage, config, &destination)?;
+            Ok(Some(destination))
+        }
+        None if data.msg.is_some() => Err(ContractError::ValidationError(
+            "A hook message requires a destination".to_string(),
+        )),
+        None => Ok(None),
+    }
 }
 
-fn _withdraw_delegation_rewards(
-    deps: &Deps,
-    env: &Env,
-    recipient: &Addr,
-    validator: &String,
-) -> Option<CosmosMsg> {
-    let delegation_result = deps
-        .querier
-        .query_delegation(env.contract.address.to_string(), validator);
-    if let Ok(Some(delegation)) = delegation_result {
-        let rewards: Vec<Coin> = delegation
-            .accumulated_rewards
-            .iter()
-            .filter(|r| !r.amount.is_zero())
-            .cloned()
-            .collect();
-        if rewards.is_empty() {
-            return None;
+fn assert_destination_approved(
+    storage: &dyn Storage,
+    config: &Config,
+    destination: &PayoutDestination,
+) -> Result<(), ContractError> {
+    // the hook is part of the approval, so whoever withdraws cannot choose what the destination runs
+    for (recipient, _) in &config.recipients {
+        let approved_msg =
+            APPROVED_DESTINATIONS.may_load(storage, (recipient, &destination.address))?;
+        if approved_msg.as_ref() != Some(&destination.msg) {
+            return Err(ContractError::DestinationNotApproved {});
         }
-        return Some(CosmosMsg::Bank(BankMsg::Send {
-            to_address: recipient.to_string(),
-            amount: rewards,
-        }));
     }
-    None
+    Ok(())
 }
 
-fn withdraw_unlocked_funds(
+fn update_destination(
     deps: DepsMut,
-    env: Env,
     info: MessageInfo,
-    data: WithdrawVestedFundsMsg,
+    data: DestinationMsg,
+    approved: bool,
 ) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
-    let mut state = STATE.load(deps.storage)?;
-
-    if !config.whitelisted_addresses.contains(&info.sender) {
+    if !config
+        .recipients
+        .iter()
+        .any(|(recipient, _)| *recipient == info.sender)
+    {
         return Err(ContractError::Unauthorized {});
     }
-
-    let current_balance = deps
-        .querier
-        .query_balance(env.contract.address, data.denom.clone())?
-        .amount;
-
-    let amount_to_withdraw = if data.denom == "uluna" {
-        let withdrawable =
-            current_balance.min(config.unlocked_amount - state.unlocked_amount_withdrawn);
-        state.unlocked_amount_withdrawn += withdrawable;
-        STATE.save(deps.storage, &state)?;
-        withdrawable
+    let destination = deps.api.addr_validate(&data.destination)?;
+    if approved {
+        APPROVED_DESTINATIONS.save(deps.storage, (&info.sender, &destination), &data.msg)?;
     } else {
-        current_balance
-    };
-
-    if amount_to_withdraw.is_zero() {
-        return Err(ContractError::NothingToWithdraw {});
+        APPROVED_DESTINATIONS.remove(deps.storage, (&info.sender, &destination));
     }
 
-    let msg = CosmosMsg::Bank(BankMsg::Send {
-        to_address: config.recipient.to_string(),
-        amount: vec![Coin::new(amount_to_withdraw.u128(), data.denom.clone())],
+    Ok(Response::new().add_event(
+        admin_event(if approved {
+            "approve_destination"
+        } else {
+            "revoke_destination"
+        })
+        .add_attribute("recipient", info.sender)
+        .add_attribute("destination", destination),
+    ))
+}
+
+fn transfer_nft(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    data: TransferNftMsg,
+) -> Result<Response, ContractError> {
+    let (holder, msgs) = transfer_position(deps, &env, &info, &data.token_id, &data.recipient)?;
+    Ok(Response::new().add_messages(msgs).add_event(
+        admin_event("transfer_nft")
+            .add_attribute("sender", info.sender)
+            .add_attribute("recipient", holder)
+            .add_attribute("token_id", data.token_id),
+    ))
+}
+
+fn send_nft(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    data: SendNftMsg,
+) -> Result<Response, ContractError> {
+    let (holder, msgs) = transfer_position(deps, &env, &info, &data.token_id, &data.contract)?;
+    let msg = Cw721ReceiveMsg {
+        sender: info.sender.to_string(),
+        token_id: data.token_id.clone(),
+        msg: data.msg,
+    }
+    .into_cosmos_msg(holder.to_string())?;
+    Ok(Response::new()
+        .add_messages(msgs)
+        .add_message(msg)
+        .add_event(
+            admin_event("send_nft")
+                .add_attribute("sender", info.sender)
+                .add_attribute("recipient", holder)
+                .add_attribute("token_id", data.token_id),
+        ))
+}
+
+// moves a recipient position to a new holder, who takes over its withdraw authorisation,
+// returns the new holder and the revocation of the fee allowance of the previous one
+fn transfer_position(
+    deps: DepsMut,
+    env: &Env,
+    info: &MessageInfo,
+    token_id: &str,
+    recipient: &str,
+) -> Result<(Addr, Vec<CosmosMsg>), ContractError> {
+    assert_not_paused(deps.storage)?;
+    let mut config = CONFIG.load(deps.storage)?;
+    let (id, previous) = position_holder(deps.storage, token_id)?;
+    assert_can_send(deps.storage, env, &info.sender, id, &previous)?;
+    let holder = deps.api.addr_validate(recipient)?;
+    if config.recipients.iter().any(|(addr, _)| *addr == holder) {
+        return Err(ContractError::ValidationError(
+            "Recipient already holds a position".to_string(),
+        ));
+    }
+    for (addr, _) in config.recipients.iter_mut() {
+        if *addr == previous {
+            *addr = holder.clone();
+        }
+    }
+    POSITIONS.save(deps.storage, id, &holder)?;
+    clear_approvals(deps.storage, id)?;
+
+    // the previous holder keeps its whitelist entry only as owner or when whitelisted by the owner,
+    // and fee allowances only cover whitelisted addresses
+    let mut msgs = vec![];
+    if config.owner != previous && !OWNER_WHITELISTED.has(deps.storage, &previous) {
+        config
+            .whitelisted_addresses
+            .retain(|addr| *addr != previous);
+        msgs.extend(release_fee_grant(
+            deps.storage,
+            &deps.querier,
+            env,
+            &previous,
+        )?);
+    }
+    if !config.whitelisted_addresses.contains(&holder) {
+        config.whitelisted_addresses.push(holder.clone());
+    }
+    CONFIG.save(deps.storage, &config)?;
+    Ok((holder, msgs))
+}
+
+// mints a position for every new recipient and burns those of removed recipients,
+// so a token id stays with its holder across recipient updates
+fn sync_positions(
+    storage: &mut dyn Storage,
+    recipients: &[(Addr, u16)],
+) -> Result<(), ContractError> {
+    let positions = POSITIONS
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (id, holder) in &positions {
+        if !recipients.iter().any(|(addr, _)| addr == holder) {
+            POSITIONS.remove(storage, *id);
+            clear_approvals(storage, *id)?;
+        }
+    }
+    for (addr, _) in recipients {
+        if !positions.iter().any(|(_, holder)| holder == addr) {
+            let id = POSITION_ID.may_load(storage)?.unwrap_or_default();
+            POSITIONS.save(storage, id, addr)?;
+            POSITION_ID.save(storage, &next_id(Some(id))?)?;
+        }
+    }
+    Ok(())
+}
+
+fn position_holder(storage: &dyn Storage, token_id: &str) -> StdResult<(u64, Addr)> {
+    let id = token_id
+        .parse::<u64>()
+        .map_err(|_| StdError::not_found("vesting position"))?;
+    let holder = POSITIONS
+        .may_load(storage, id)?
+        .ok_or_else(|| StdError::not_found("vesting position"))?;
+    Ok((id, holder))
+}
+
+// the holder, a spender approved for the position and operators of the holder can move it
+fn assert_can_send(
+    storage: &dyn Storage,
+    env: &Env,
+    sender: &Addr,
+    id: u64,
+    holder: &Addr,
+) -> Result<(), ContractError> {
+    if sender == holder
+        || TOKEN_APPROVALS
+            .may_load(storage, (id, sender))?
+            .map_or(false, |expires| !expires.is_expired(&env.block))
+    {
+        return Ok(());
+    }
+    assert_can_approve(storage, env, sender, holder)
+}
+
+// approvals of a position are managed by its holder and the operators of the holder
+fn assert_can_approve(
+    storage: &dyn Storage,
+    env: &Env,
+    sender: &Addr,
+    holder: &Addr,
+) -> Result<(), ContractError> {
+    if sender == holder
+        || OPERATORS
+            .may_load(storage, (holder, sender))?
+            .map_or(false, |expires| !expires.is_expired(&env.block))
+    {
+        return Ok(());
+    }
+    Err(ContractError::Unauthorized {})
+}
+
+fn clear_approvals(storage: &mut dyn Storage, id: u64) -> StdResult<()> {
+    let spenders = TOKEN_APPROVALS
+        .prefix(id)
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for spender in &spenders {
+        TOKEN_APPROVALS.remove(storage, (id, spender));
+    }
+    Ok(())
+}
+
+fn validate_expiration(
+    env: &Env,
+    expires: Option<Expiration>,
+) -> Result<Expiration, ContractError> {
+    let expires = expires.unwrap_or_default();
+    if expires.is_expired(&env.block) {
+        return Err(ContractError::ValidationError(
+            "Approval is already expired".to_string(),
+        ));
+    }
+    Ok(expires)
+}
+
+fn approve(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    data: ApproveMsg,
+) -> Result<Response, ContractError> {
+    let (id, holder) = position_holder(deps.storage, &data.token_id)?;
+    assert_can_approve(deps.storage, &env, &info.sender, &holder)?;
+    let spender = deps.api.addr_validate(&data.spender)?;
+    let expires = validate_expiration(&env, data.expires)?;
+    TOKEN_APPROVALS.save(deps.storage, (id, &spender), &expires)?;
+    Ok(Response::new().add_event(
+        admin_event("approve")
+            .add_attribute("sender", info.sender)
+            .add_attribute("spender", spender)
+            .add_attribute("token_id", data.token_id)
+            .add_attribute("expires", expires.to_string()),
+    ))
+}
+
+fn revoke(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    data: RevokeMsg,
+) -> Result<Response, ContractError> {
+    let (id, holder) = position_holder(deps.storage, &data.token_id)?;
+    assert_can_approve(deps.storage, &env, &info.sender, &holder)?;
+    let spender = deps.api.addr_validate(&data.spender)?;
+    TOKEN_APPROVALS.remove(deps.storage, (id, &spender));
+    Ok(Response::new().add_event(
+        admin_event("revoke")
+            .add_attribute("sender", info.sender)
+            .add_attribute("spender", spender)
+            .add_attribute("token_id", data.token_id),
+    ))
+}
+
+fn approve_all(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    data: ApproveAllMsg,
+) -> Result<Response, ContractError> {
+    let operator = deps.api.addr_validate(&data.operator)?;
+    let expires = validate_expiration(&env, data.expires)?;
+    OPERATORS.save(deps.storage, (&info.sender, &operator), &expires)?;
+    Ok(Response::new().add_event(
+        admin_event("approve_all")
+            .add_attribute("sender", info.sender)
+            .add_attribute("operator", operator)
+            .add_attribute("expires", expires.to_string()),
+    ))
+}
+
+fn revoke_all(
+    deps: DepsMut,
+    info: MessageInfo,
+    data: RevokeAllMsg,
+) -> Result<Response, ContractError> {
+    let operator = deps.api.addr_validate(&data.operator)?;
+    OPERATORS.remove(deps.storage, (&info.sender, &operator));
+    Ok(Response::new().add_event(
+        admin_event("revoke_all")
+            .add_attribute("sender", info.sender)
+            .add_attribute("operator", operator),
+    ))
+}
+
+fn update_owner(
+    deps: DepsMut,
+    info: MessageInfo,
+    data: UpdateOwnerMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    if config.governance_owner {
+        return Err(ContractError::ValidationError(
+            "Owner cannot be changed in governance owner mode".to_string(),
+        ));
+    }
+    let new_owner = deps.api.addr_validate(&data.owner)?;
+    CONFIG.save(deps.storage, &with_owner(config, new_owner))?;
+
+    Ok(Response::new().add_event(admin_event("update_owner").add_attribute("owner", data.owner)))
+}
+
+// replaces the owner, also on the whitelist
+fn with_owner(config: Config, new_owner: Addr) -> Config {
+    let recipients: Vec<Addr> = config
+        .recipients
+        .iter()
+        .map(|(addr, _)| addr.clone())
+        .collect();
+    let mut new_addresses = vec![new_owner.clone()];
+    new_addresses.extend(recipients.iter().cloned());
+    for addr in &config.whitelisted_addresses {
+        if *addr != config.owner && !recipients.contains(addr) && *addr != new_owner {
+            new_addresses.push(addr.clone());
+        }
+    }
+
+    Config {
+        owner: new_owner,
+        whitelisted_addresses: new_addresses,
+        ..config
+    }
+}
+
+fn remove_from_whitelist(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    data: RemoveFromWhitelistMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    //always keep recipients and owner address on the whitelist
+    let recipients: Vec<Addr> = config
+        .recipients
+        .iter()
+        .map(|(addr, _)| addr.clone())
+        .collect();
+    let mut new_addresses = vec![config.owner.clone()];
+    new_addresses.extend(recipients.iter().cloned());
+    for addr in config.whitelisted_addresses {
+        if !data.addresses.contains(&addr) && !recipients.contains(&addr) && addr != config.owner {
+            new_addresses.push(addr);
+        }
+    }
+    for addr in &data.addresses {
+        OWNER_WHITELISTED.remove(deps.storage, addr);
+    }
+    CONFIG.save(
+        deps.storage,
+        &Config {
+            whitelisted_addresses: new_addresses.clone(),
+            ..config
+        },
+    )?;
+
+    // fee allowances only cover whitelisted addresses
+    let mut msgs = vec![];
+    for addr in &data.addresses {
+        if !new_addresses.contains(addr) {
+            msgs.extend(release_fee_grant(deps.storage, &deps.querier, &env, addr)?);
+        }
+    }
+    Ok(Response::new().add_messages(msgs).add_event(
+        admin_event("remove_from_whitelist")
+            .add_attribute("addresses", join_addresses(&data.addresses))
+            .add_attribute("whitelisted_addresses", join_addresses(&new_addresses)),
+    ))
+}
+
+fn add_to_whitelist(
+    deps: DepsMut,
+    info: MessageInfo,
+    data: AddToWhitelistMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    let mut new_addresses = config.whitelisted_addresses.clone();
+    for addr in data.addresses.iter() {
+        if !config.whitelisted_addresses.contains(addr) {
+            new_addresses.push(deps.api.addr_validate(addr.as_str())?);
+        }
+        OWNER_WHITELISTED.save(deps.storage, addr, &true)?;
+    }
+    CONFIG.save(
+        deps.storage,
+        &Config {
+            whitelisted_addresses: new_addresses.clone(),
+            ..config
+        },
+    )?;
+    Ok(Response::new().add_event(
+        admin_event("add_to_whitelist")
+            .add_attribute("addresses", join_addresses(&data.addresses))
+            .add_attribute("whitelisted_addresses", join_addresses(&new_addresses)),
+    ))
+}
+
+fn redelegate_funds(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    data: RedelegateFundsMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    let msg = CosmosMsg::Staking(StakingMsg::Redelegate {
+        src_validator: data.src_validator.clone(),
+        dst_validator: data.dst_validator.clone(),
+        amount: data.amount.clone(),
     });
+    // redelegating stays available while paused to move stake off a faulty validator,
+    // but rewards are kept in the contract
+    let (send_reward_msgs_src, send_reward_msgs_dst) = if is_paused(deps.storage)? {
+        (None, None)
+    } else {
+        (
+            _withdraw_delegation_rewards(
+                &deps.as_ref(),
+                &env,
+                &config.recipients,
+                &data.src_validator,
+            )?,
+            _withdraw_delegation_rewards(
+                &deps.as_ref(),
+                &env,
+                &config.recipients,
+                &data.dst_validator,
+            )?,
+        )
+    };
+
+    let event = stake_event("redelegate_funds")
+        .add_attribute("src_validator", data.src_validator.to_string())
+        .add_attribute("dst_validator", data.dst_validator.to_string())
+        .add_attribute("denom", data.amount.denom)
+        .add_attribute("amount", data.amount.amount)
+        .add_attribute(
+            "rewards_forwarded",
+            (send_reward_msgs_src.is_some() || send_reward_msgs_dst.is_some()).to_string(),
+        );
+    let mut res = Response::new().add_event(event);
+
+    if let Some(send_reward_msgs) = send_reward_msgs_src {
+        res = res.add_message(DistributionMsg::WithdrawDelegatorReward {
+            validator: data.src_validator,
+        });
+        res = res.add_messages(send_reward_msgs);
+    }
+    if let Some(send_reward_msgs) = send_reward_msgs_dst {
+        res = res.add_message(DistributionMsg::WithdrawDelegatorReward {
+            validator: data.dst_validator,
+        });
+        res = res.add_messages(send_reward_msgs);
+    }
+
+    // after the reward withdrawals, redelegating the whole stake removes the source delegation
+    Ok(res.add_message(msg))
+}
+
+fn undelegate_funds(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    data: UndelegateFundsMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    if AssetInfo::native(&data.amount.denom) == config.vesting_asset {
+        track_unbonding(deps.storage, &env, data.amount.amount)?;
+    }
+
+    let msg = CosmosMsg::Staking(StakingMsg::Undelegate {
+        validator: data.validator.clone(),
+        amount: data.amount.clone(),
+    });
+    // undelegating stays available while paused, but rewards are kept in the contract
+    let send_reward_msgs = if is_paused(deps.storage)? {
+        None
+    } else {
+        _withdraw_delegation_rewards(&deps.as_ref(), &env, &config.recipients, &data.validator)?
+    };
+
+    let event = stake_event("undelegate_funds")
+        .add_attribute("validator", data.validator.to_string())
+        .add_attribute("denom", data.amount.denom)
+        .add_attribute("amount", data.amount.amount)
+        .add_attribute("rewards_forwarded", send_reward_msgs.is_some().to_string());
+    let mut res = Response::new().add_event(event);
+
+    // rewards are withdrawn first, undelegating the whole stake removes the delegation
+    if let Some(send_reward_msgs) = send_reward_msgs {
+        res = res.add_message(DistributionMsg::WithdrawDelegatorReward {
+            validator: data.validator,
+        });
+        res = res.add_messages(send_reward_msgs);
+    }
+    Ok(res.add_message(msg))
+}
+
+// undelegates the vesting asset so that the liquid and unbonding balances cover what is withdrawable
+// at the end of the horizon, taking from the largest delegations first to send few messages
+fn prepare_liquidity(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    data: PrepareLiquidityMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let state = STATE.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    let denom = match &config.vesting_asset {
+        AssetInfo::Native(denom) => denom.clone(),
+        _ => {
+            return Err(ContractError::ValidationError(
+                "Only a native vesting asset can be staked".to_string(),
+            ))
+        }
+    };
+
+    let horizon = Uint64::new(env.block.time.seconds()).checked_add(data.horizon_seconds)?;
+    let required = withdrawable_obligations(&config, &state, horizon.u64())?;
+    let liquid_balance = available_balance(deps.as_ref(), &env, &config, &config.vesting_asset)?;
+    let (_, unbonding_balance) = query_staked_balances(deps.as_ref(), &env, &config)?;
+    let gap = required.saturating_sub(checked_sum([liquid_balance, unbonding_balance])?);
+
+    let mut delegations: Vec<(String, Uint128)> = deps
+        .querier
+        .query_all_delegations(env.contract.address.clone())?
+        .into_iter()
+        .filter(|delegation| {
+            delegation.amount.denom == denom && !delegation.amount.amount.is_zero()
+        })
+        .map(|delegation| (delegation.validator, delegation.amount.amount))
+        .collect();
+    delegations.sort_by(|(a_validator, a), (b_validator, b)| {
+        b.cmp(a).then_with(|| a_validator.cmp(b_validator))
+    });
+
+    // undelegating stays available while paused, but rewards are kept in the contract
+    let paused = is_paused(deps.storage)?;
+    let mut res = Response::new();
+    let mut undelegated = Uint128::zero();
+    for (validator, amount) in delegations {
+        let remaining = gap.checked_sub(undelegated)?;
+        if remaining.is_zero() {
+            break;
+        }
+        let amount = amount.min(remaining);
+        undelegated = undelegated.checked_add(amount)?;
+        // rewards are withdrawn first, undelegating the whole stake removes the delegation
+        if !paused {
+            if let Some(send_reward_msgs) =
+                _withdraw_delegation_rewards(&deps.as_ref(), &env, &config.recipients, &validator)?
+            {
+                res = res
+                    .add_message(DistributionMsg::WithdrawDelegatorReward {
+                        validator: validator.clone(),
+                    })
+                    .add_messages(send_reward_msgs);
+            }
+        }
+        res = res.add_message(StakingMsg::Undelegate {
+            validator,
+            amount: Coin::new(amount.u128(), denom.clone()),
+        });
+    }
+    if !undelegated.is_zero() {
+        track_unbonding(deps.storage, &env, undelegated)?;
+    }
+
+    Ok(res.add_event(
+        stake_event("prepare_liquidity")
+            .add_attribute("horizon_seconds", data.horizon_seconds)
+            .add_attribute("required", required)
+            .add_attribute("liquid_balance", liquid_balance)
+            .add_attribute("unbonding_balance", unbonding_balance)
+            .add_attribute("denom", denom)
+            .add_attribute("amount", undelegated)
+            .add_attribute("shortfall", gap.checked_sub(undelegated)?),
+    ))
+}
+
+fn track_unbonding(
+    storage: &mut dyn Storage,
+    env: &Env,
+    amount: Uint128,
+) -> Result<(), ContractError> {
+    // drop entries whose unbonding already completed, those funds are liquid again
+    let completed = UNBONDING
+        .keys(
+            storage,
+            None,
+            Some(Bound::inclusive(env.block.time.seconds())),
+            Order::Ascending,
+        )
+        .collect::<StdResult<Vec<u64>>>()?;
+    for completion_time in completed {
+        UNBONDING.remove(storage, completion_time);
+    }
+
+    let completion_time =
+        Uint64::new(env.block.time.seconds()).checked_add(Uint64::new(UNBONDING_PERIOD))?;
+    UNBONDING.update(
+        storage,
+        completion_time.u64(),
+        |unbonding| -> Result<_, ContractError> {
+            Ok(unbonding.unwrap_or_default().checked_add(amount)?)
+        },
+    )?;
+    Ok(())
+}
+
+fn claim_delegator_reward(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    data: WithdrawDelegatorRewardMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    assert_not_paused(deps.storage)?;
+
+    let send_reward_msgs =
+        _withdraw_delegation_rewards(&deps.as_ref(), &env, &config.recipients, &data.validator)?;
+
+    let event = stake_event("withdraw_delegator_rewards")
+        .add_attribute("validator", data.validator.to_string())
+        .add_attribute("rewards_forwarded", send_reward_msgs.is_some().to_string());
+    let mut res = Response::new().add_event(event);
+
+    if let Some(send_reward_msgs) = send_reward_msgs {
+        res = res.add_message(DistributionMsg::WithdrawDelegatorReward {
+            validator: data.validator,
+        });
+        res = res.add_messages(send_reward_msgs);
+    }
+    Ok(res)
+}
+
+fn delegate_funds(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    data: DelegateFundsMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    assert_not_paused(deps.storage)?;
+    if config.liquidity_guard && AssetInfo::native(&data.amount.denom) == config.vesting_asset {
+        let state = STATE.load(deps.storage)?;
+        let required = withdrawable_obligations(&config, &state, env.block.time.seconds())?;
+        let balance = available_balance(deps.as_ref(), &env, &config, &config.vesting_asset)?;
+        match balance.checked_sub(data.amount.amount) {
+            Ok(remaining) if remaining >= required => {}
+            _ => return Err(ContractError::InsufficientLiquidity {}),
+        }
+    }
+    let msg = CosmosMsg::Staking(StakingMsg::Delegate {
+        validator: data.validator.clone(),
+        amount: data.amount.clone(),
+    });
+    let send_reward_msgs =
+        _withdraw_delegation_rewards(&deps.as_ref(), &env, &config.recipients, &data.validator)?;
+
+    let event = stake_event("delegate_funds")
+        .add_attribute("validator", data.validator.to_string())
+        .add_attribute("denom", data.amount.denom)
+        .add_attribute("amount", data.amount.amount)
+        .add_attribute("rewards_forwarded", send_reward_msgs.is_some().to_string());
+    let mut res = Response::new().add_message(msg).add_event(event);
+
+    if let Some(send_reward_msgs) = send_reward_msgs {
+        res = res.add_message(DistributionMsg::WithdrawDelegatorReward {
+            validator: data.validator,
+        });
+        res = res.add_messages(send_reward_msgs);
+    }
+
+    Ok(res)
+}
+
+fn _withdraw_delegation_rewards(
+    deps: &Deps,
+    env: &Env,
+    recipients: &[(Addr, u16)],
+    validator: &String,
+) -> Result<Option<Vec<CosmosMsg>>, ContractError> {
+    let delegation_result = deps
+        .querier
+        .query_delegation(env.contract.address.to_string(), validator);
+    if let Ok(Some(delegation)) = delegation_result {
+        let rewards: Vec<Coin> = delegation
+            .accumulated_rewards
+            .iter()
+            .filter(|r| !r.amount.is_zero())
+            .cloned()
+            .collect();
+        if rewards.is_empty() {
+            return Ok(None);
+        }
+        // every denom is split separately, so each recipient gets its share of all rewards
+        let mut payouts: Vec<(Addr, Vec<Coin>)> = recipients
+            .iter()
+            .map(|(addr, _)| (addr.clone(), vec![]))
+            .collect();
+        for reward in rewards {
+            for (i, (_, share)) in split_by_share(recipients, reward.amount)?
+                .into_iter()
+                .enumerate()
+            {
+                if !share.is_zero() {
+                    payouts[i]
+                        .1
+                        .push(Coin::new(share.u128(), reward.denom.clone()));
+                }
+            }
+        }
+        return Ok(Some(
+            payouts
+                .into_iter()
+                .filter(|(_, amount)| !amount.is_empty())
+                .map(|(addr, amount)| {
+                    CosmosMsg::Bank(BankMsg::Send {
+                        to_address: addr.to_string(),
+                        amount,
+                    })
+                })
+                .collect(),
+        ));
+    }
+    Ok(None)
+}
+
+fn withdraw_unlocked_funds(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    data: WithdrawVestedFundsMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let mut state = STATE.load(deps.storage)?;
+    assert_not_paused(deps.storage)?;
+
+    if !config.whitelisted_addresses.contains(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let asset_info = data.asset_info.check(deps.api, None)?;
+    let destination = payout_destination(deps.as_ref(), &config, &data)?;
+    let current_balance = withdrawable_balance(deps.as_ref(), &env, &config, &state, &asset_info)?;
+
+    let amount_to_withdraw = if asset_info == config.vesting_asset {
+        let withdrawable = current_balance.min(
+            config
+                .unlocked_amount
+                .checked_sub(state.unlocked_amount_withdrawn)?,
+        );
+        state.unlocked_amount_withdrawn =
+            state.unlocked_amount_withdrawn.checked_add(withdrawable)?;
+        record_withdrawal(&config, &mut state, &env, withdrawable);
+        STATE.save(deps.storage, &state)?;
+        withdrawable
+    } else {
+        current_balance
+    };
+
+    if amount_to_withdraw.is_zero() {
+        return Err(ContractError::NothingToWithdraw {});
+    }
+
+    let event = withdraw_event("withdraw_unlocked_funds")
+        .add_attribute("sender", &info.sender)
+        .add_attribute("unlocked_amount_withdrawn", state.unlocked_amount_withdrawn);
+
+    release_or_queue(
+        deps.storage,
+        &env,
+        Payout {
+            config: &config,
+            sender: &info.sender,
+            tranche: Tranche::Unlocked,
+            asset: Asset::new(asset_info, amount_to_withdraw),
+            destination,
+            event,
+        },
+    )
+}
+
+fn withdraw_cliff_vested_funds(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    data: WithdrawVestedFundsMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let mut state = STATE.load(deps.storage)?;
+    assert_not_paused(deps.storage)?;
+
+    if !config.whitelisted_addresses.contains(&info.sender)
+        || env.block.time.seconds() < config.start_time.u64()
+        || state.cliff_amount_withdrawn >= config.cliff_amount
+    {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let asset_info = data.asset_info.check(deps.api, None)?;
+    let destination = payout_destination(deps.as_ref(), &config, &data)?;
+    let current_balance = withdrawable_balance(deps.as_ref(), &env, &config, &state, &asset_info)?;
+
+    let amount_to_withdraw = if asset_info == config.vesting_asset {
+        let withdrawable = current_balance.min(
+            config
+                .cliff_amount
+                .checked_sub(state.cliff_amount_withdrawn)?,
+        );
+        state.cliff_amount_withdrawn = state.cliff_amount_withdrawn.checked_add(withdrawable)?;
+        record_withdrawal(&config, &mut state, &env, withdrawable);
+        STATE.save(deps.storage, &state)?;
+        withdrawable
+    } else {
+        current_balance
+    };
+
+    if amount_to_withdraw.is_zero() {
+        return Err(ContractError::NothingToWithdraw {});
+    }
+
+    let event = withdraw_event("withdraw_cliff_vested_funds")
+        .add_attribute("sender", &info.sender)
+        .add_attribute("cliff_amount_withdrawn", state.cliff_amount_withdrawn);
+
+    release_or_queue(
+        deps.storage,
+        &env,
+        Payout {
+            config: &config,
+            sender: &info.sender,
+            tranche: Tranche::Cliff,
+            asset: Asset::new(asset_info, amount_to_withdraw),
+            destination,
+            event,
+        },
+    )
+}
+
+fn withdraw_vested_funds(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    data: WithdrawVestedFundsMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let mut state = STATE.load(deps.storage)?;
+    assert_not_paused(deps.storage)?;
+
+    if !config.whitelisted_addresses.contains(&info.sender)
+        || env.block.time.seconds() < config.start_time.u64()
+    {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // Need to withdraw unlocked amount before linear vesting amount
+    if state.unlocked_amount_withdrawn < config.unlocked_amount {
+        return Err(ContractError::WithdrawUnlockedFirst {});
+    }
+
+    // Need to withdraw cliff amount before linear vesting amount
+    if state.cliff_amount_withdrawn < config.cliff_amount {
+        return Err(ContractError::WithdrawCliffFirst {});
+    }
+
+    let asset_info = data.asset_info.check(deps.api, None)?;
+    let destination = payout_destination(deps.as_ref(), &config, &data)?;
+    let current_balance = withdrawable_balance(deps.as_ref(), &env, &config, &state, &asset_info)?;
+
+    // only withdrawals of the vesting asset move the last withdrawn time, other assets are withdrawn in full
+    let (amount_to_withdraw, last_withdrawn_time) = if asset_info == config.vesting_asset {
+        let (amount, last_withdrawn_time) = vesting::withdraw(
+            &Schedule::from(&config),
+            state.last_withdrawn_time.u64(),
+            env.block.time.seconds(),
+            current_balance,
+        )?;
+        // once the whole schedule is paid out, any surplus sent to the contract is swept as well
+        let amount = if last_withdrawn_time >= config.end_time.u64() {
+            current_balance
+        } else {
+            amount
+        };
+        (amount, Uint64::new(last_withdrawn_time))
+    } else {
+        (current_balance, state.last_withdrawn_time)
+    };
+
+    if asset_info == config.vesting_asset {
+        record_withdrawal(&config, &mut state, &env, amount_to_withdraw);
+    }
+
+    STATE.save(
+        deps.storage,
+        &State {
+            last_withdrawn_time,
+            cliff_amount_withdrawn: state.cliff_amount_withdrawn,
+            unlocked_amount_withdrawn: state.unlocked_amount_withdrawn,
+            recent_withdrawals: state.recent_withdrawals,
+        },
+    )?;
+
+    if amount_to_withdraw.is_zero() {
+        return Err(ContractError::NothingToWithdraw {});
+    }
+
+    let event = withdraw_event("withdraw_vested_funds")
+        .add_attribute("sender", &info.sender)
+        .add_attribute("last_withdrawn_time", last_withdrawn_time);
+
+    release_or_queue(
+        deps.storage,
+        &env,
+        Payout {
+            config: &config,
+            sender: &info.sender,
+            tranche: Tranche::Vesting,
+            asset: Asset::new(asset_info, amount_to_withdraw),
+            destination,
+            event,
+        },
+    )
+}
+
+// balance of the asset held by the contract, excluding withdrawals waiting to be finalized
+// and funds reserved for fees
+fn available_balance(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+    asset_info: &AssetInfo,
+) -> Result<Uint128, ContractError> {
+    let balance = asset_info.query_balance(&deps.querier, env.contract.address.clone())?;
+    let mut reserved = Uint128::zero();
+    if *asset_info == config.vesting_asset {
+        reserved = reserved.checked_add(pending_withdrawals_total(deps.storage)?)?;
+    }
+    if let Some(fee_reserve) = fee_reserve(deps.storage)? {
+        if AssetInfo::native(&fee_reserve.denom) == *asset_info {
+            reserved = reserved.checked_add(fee_reserve.amount)?;
+        }
+    }
+    Ok(balance.saturating_sub(reserved))
+}
+
+// available balance further limited by the remaining allowance of the withdrawal period
+fn withdrawable_balance(
+    deps: Deps,
+    env: &Env,
+    config: &Config,
+    state: &State,
+    asset_info: &AssetInfo,
+) -> Result<Uint128, ContractError> {
+    let balance = available_balance(deps, env, config, asset_info)?;
+    if *asset_info != config.vesting_asset {
+        return Ok(balance);
+    }
+    match period_allowance(config, state, env)? {
+        Some(allowance) if allowance.is_zero() => Err(ContractError::WithdrawLimitReached {}),
+        Some(allowance) => Ok(balance.min(allowance)),
+        None => Ok(balance),
+    }
+}
+
+fn period_allowance(
+    config: &Config,
+    state: &State,
+    env: &Env,
+) -> Result<Option<Uint128>, ContractError> {
+    let limit = match &config.max_withdraw_per_period {
+        Some(limit) => limit,
+        None => return Ok(None),
+    };
+    let withdrawn = checked_sum(
+        state
+            .recent_withdrawals
+            .iter()
+            .filter(|record| in_period(record, limit, env))
+            .map(|record| record.amount),
+    )?;
+    Ok(Some(limit.amount.saturating_sub(withdrawn)))
+}
+
+// whether the withdrawal falls within the rolling window ending now
+fn in_period(record: &WithdrawalRecord, limit: &WithdrawPeriodLimit, env: &Env) -> bool {
+    // a window reaching past the largest timestamp never ends
+    record.time.u64().saturating_add(limit.period_seconds.u64()) > env.block.time.seconds()
+}
+
+fn record_withdrawal(config: &Config, state: &mut State, env: &Env, amount: Uint128) {
+    if amount.is_zero() {
+        return;
+    }
+    if let Some(limit) = &config.max_withdraw_per_period {
+        // records outside of the rolling window are no longer needed
+        state
+            .recent_withdrawals
+            .retain(|record| in_period(record, limit, env));
+        state.recent_withdrawals.push(WithdrawalRecord {
+            time: Uint64::new(env.block.time.seconds()),
+            amount,
+        });
+    }
+}
+
+fn pending_withdrawals_total(storage: &dyn Storage) -> Result<Uint128, ContractError> {
+    let amounts = PENDING_WITHDRAWALS
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, pending)| pending.amount))
+        .collect::<StdResult<Vec<_>>>()?;
+    checked_sum(amounts)
+}
+
+// sequence following the last one of a counter
+fn next_id(last_id: Option<u64>) -> Result<u64, ContractError> {
+    Ok(Uint64::new(last_id.unwrap_or_default())
+        .checked_add(Uint64::new(1))?
+        .u64())
+}
+
+// sends the vesting asset to the recipients, or queues it when a withdrawal delay is configured
+fn release_or_queue(
+    storage: &mut dyn Storage,
+    env: &Env,
+    mut withdrawal: Payout,
+) -> Result<Response, ContractError> {
+    withdrawal.event = withdrawal
+        .event
+        .add_attribute("tranche", withdrawal.tranche.to_string())
+        .add_attribute("asset", withdrawal.asset.info.to_string())
+        .add_attribute("amount", withdrawal.asset.amount);
+    let withdrawal_delay = withdrawal.config.withdrawal_delay.unwrap_or_default();
+    if withdrawal.asset.info != withdrawal.config.vesting_asset || withdrawal_delay.is_zero() {
+        return payout(storage, env, withdrawal);
+    }
+
+    let Payout {
+        tranche,
+        asset,
+        destination,
+        event,
+        ..
+    } = withdrawal;
+    let id = next_id(PENDING_WITHDRAWAL_ID.may_load(storage)?)?;
+    let release_time = Uint64::new(env.block.time.seconds()).checked_add(withdrawal_delay)?;
+    PENDING_WITHDRAWAL_ID.save(storage, &id)?;
+    PENDING_WITHDRAWALS.save(
+        storage,
+        id,
+        &PendingWithdrawal {
+            id,
+            tranche,
+            asset_info: asset.info,
+            amount: asset.amount,
+            release_time,
+            destination,
+            withdrawn_at: Uint64::new(env.block.time.seconds()),
+        },
+    )?;
+
+    Ok(Response::new().add_event(
+        event
+            .add_attribute("pending_withdrawal_id", id.to_string())
+            .add_attribute("release_time", release_time),
+    ))
+}
+
+fn finalize_withdrawal(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    data: PendingWithdrawalMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    assert_not_paused(deps.storage)?;
+    if !config.whitelisted_addresses.contains(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let pending = PENDING_WITHDRAWALS.load(deps.storage, data.id)?;
+    if env.block.time.seconds() < pending.release_time.u64() {
+        return Err(ContractError::WithdrawalLocked {});
+    }
+    PENDING_WITHDRAWALS.remove(deps.storage, data.id);
+    // the approval could have been revoked while the withdrawal was queued
+    if let Some(destination) = &pending.destination {
+        assert_destination_approved(deps.storage, &config, destination)?;
+    }
+
+    let event = withdraw_event("finalize_withdrawal")
+        .add_attribute("sender", &info.sender)
+        .add_attribute("pending_withdrawal_id", data.id.to_string())
+        .add_attribute("tranche", pending.tranche.to_string())
+        .add_attribute("asset", pending.asset_info.to_string())
+        .add_attribute("amount", pending.amount);
+
+    payout(
+        deps.storage,
+        &env,
+        Payout {
+            config: &config,
+            sender: &info.sender,
+            tranche: pending.tranche,
+            asset: Asset::new(pending.asset_info, pending.amount),
+            destination: pending.destination,
+            event,
+        },
+    )
+}
+
+fn cancel_withdrawal(
+    deps: DepsMut,
+    info: MessageInfo,
+    data: PendingWithdrawalMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let mut state = STATE.load(deps.storage)?;
+    if config.owner != info.sender && config.guardian.as_ref() != Some(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let pending = PENDING_WITHDRAWALS.load(deps.storage, data.id)?;
+    PENDING_WITHDRAWALS.remove(deps.storage, data.id);
+
+    restore_entitlement(
+        &config,
+        &mut state,
+        &pending.tranche,
+        pending.amount,
+        pending.withdrawn_at,
+    )?;
+    STATE.save(deps.storage, &state)?;
+
+    Ok(Response::new().add_event(
+        withdraw_event("cancel_withdrawal")
+            .add_attribute("sender", info.sender)
+            .add_attribute("pending_withdrawal_id", data.id.to_string())
+            .add_attribute("tranche", pending.tranche.to_string())
+            .add_attribute("asset", pending.asset_info.to_string())
+            .add_attribute("amount", pending.amount),
+    ))
+}
 
-    Ok(Response::new()
-        .add_message(msg)
-        .add_attribute("action", "withdraw_unlocked_funds")
-        .add_attribute("denom", data.denom)
-        .add_attribute("amount_to_withdraw", amount_to_withdraw)
-        .add_attribute("unlocked_amount_withdrawn", state.cliff_amount_withdrawn))
+// gives an amount that was not paid out back to the recipient's entitlement and to the allowance
+// of the period it was withdrawn in
+fn restore_entitlement(
+    config: &Config,
+    state: &mut State,
+    tranche: &Tranche,
+    amount: Uint128,
+    withdrawn_at: Uint64,
+) -> Result<(), ContractError> {
+    let mut remaining = amount;
+    for record in state
+        .recent_withdrawals
+        .iter_mut()
+        .filter(|record| record.time == withdrawn_at)
+    {
+        let released = record.amount.min(remaining);
+        record.amount = record.amount.checked_sub(released)?;
+        remaining = remaining.checked_sub(released)?;
+    }
+    state
+        .recent_withdrawals
+        .retain(|record| !record.amount.is_zero());
+
+    match tranche {
+        Tranche::Unlocked => {
+            state.unlocked_amount_withdrawn =
+                state.unlocked_amount_withdrawn.saturating_sub(amount);
+        }
+        Tranche::Cliff => {
+            state.cliff_amount_withdrawn = state.cliff_amount_withdrawn.saturating_sub(amount);
+        }
+        Tranche::Vesting => {
+            // rounded up, so no more than the amount becomes withdrawable again
+            let schedule = Schedule::from(config);
+            let withdrawn = vesting::vested_at(&schedule, state.last_withdrawn_time.u64())?;
+            state.last_withdrawn_time = Uint64::new(vesting::vesting_time(
+                &schedule,
+                withdrawn.saturating_sub(amount),
+            )?);
+        }
+    }
+    Ok(())
 }
 
-fn withdraw_cliff_vested_funds(
+fn update_withdrawal_delay(
     deps: DepsMut,
-    env: Env,
     info: MessageInfo,
-    data: WithdrawVestedFundsMsg,
+    data: UpdateWithdrawalDelayMsg,
 ) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
-    let mut state = STATE.load(deps.storage)?;
-
-    if !config.whitelisted_addresses.contains(&info.sender)
-        || env.block.time.seconds() < config.start_time.u64()
-        || state.cliff_amount_withdrawn >= config.cliff_amount
-    {
+    if config.owner != info.sender {
         return Err(ContractError::Unauthorized {});
     }
+    CONFIG.save(
+        deps.storage,
+        &Config {
+            withdrawal_delay: data.withdrawal_delay,
+            ..config
+        },
+    )?;
 
-    let current_balance = deps
-        .querier
-        .query_balance(env.contract.address, data.denom.clone())?
-        .amount;
+    Ok(
+        Response::new().add_event(admin_event("update_withdrawal_delay").add_attribute(
+            "withdrawal_delay",
+            data.withdrawal_delay.unwrap_or_default(),
+        )),
+    )
+}
 
-    let amount_to_withdraw = if data.denom == "uluna" {
-        let withdrawable = current_balance.min(config.cliff_amount - state.cliff_amount_withdrawn);
-        state.cliff_amount_withdrawn += withdrawable;
-        STATE.save(deps.storage, &state)?;
-        withdrawable
-    } else {
-        current_balance
-    };
+fn update_liquidity_guard(
+    deps: DepsMut,
+    info: MessageInfo,
+    data: UpdateLiquidityGuardMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    CONFIG.save(
+        deps.storage,
+        &Config {
+            liquidity_guard: data.liquidity_guard,
+            ..config
+        },
+    )?;
 
-    if amount_to_withdraw.is_zero() {
-        return Err(ContractError::NothingToWithdraw {});
+    Ok(Response::new().add_event(
+        admin_event("update_liquidity_guard")
+            .add_attribute("liquidity_guard", data.liquidity_guard.to_string()),
+    ))
+}
+
+fn update_ibc_payout(
+    deps: DepsMut,
+    info: MessageInfo,
+    data: UpdateIbcPayoutMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
     }
+    validate_ibc_payout(&config.vesting_asset, &config.recipients, &data.ibc_payout)?;
+    CONFIG.save(
+        deps.storage,
+        &Config {
+            ibc_payout: data.ibc_payout.clone(),
+            ..config
+        },
+    )?;
 
-    let msg = CosmosMsg::Bank(BankMsg::Send {
-        to_address: config.recipient.to_string(),
-        amount: vec![Coin::new(amount_to_withdraw.u128(), data.denom.clone())],
+    let ibc_payout = data.ibc_payout.unwrap_or(IbcPayout {
+        recipient: String::new(),
+        channel_id: String::new(),
+        remote_address: String::new(),
+        timeout_seconds: Uint64::zero(),
     });
+    Ok(Response::new().add_event(
+        admin_event("update_ibc_payout")
+            .add_attribute("recipient", ibc_payout.recipient)
+            .add_attribute("channel_id", ibc_payout.channel_id)
+            .add_attribute("remote_address", ibc_payout.remote_address)
+            .add_attribute("timeout_seconds", ibc_payout.timeout_seconds),
+    ))
+}
 
-    Ok(Response::new()
-        .add_message(msg)
-        .add_attribute("action", "withdraw_cliff_vested_funds")
-        .add_attribute("denom", data.denom)
-        .add_attribute("amount_to_withdraw", amount_to_withdraw)
-        .add_attribute("cliff_amount_withdrawn", state.cliff_amount_withdrawn))
+fn top_up_fee_budget(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    let funds = match info.funds.as_slice() {
+        [coin] if !coin.amount.is_zero() => coin,
+        _ => return Err(ContractError::NoFunds {}),
+    };
+    let budget = match FEE_BUDGET.may_load(deps.storage)? {
+        Some(budget) if budget.denom != funds.denom => {
+            return Err(ContractError::ValidationError(format!(
+                "Fee budget is held in {}",
+                budget.denom
+            )))
+        }
+        Some(budget) => Coin {
+            amount: budget.amount.checked_add(funds.amount)?,
+            ..budget
+        },
+        None => funds.clone(),
+    };
+    FEE_BUDGET.save(deps.storage, &budget)?;
+
+    Ok(Response::new().add_event(
+        admin_event("top_up_fee_budget")
+            .add_attribute("amount", funds.to_string())
+            .add_attribute("fee_budget", budget.to_string()),
+    ))
 }
 
-fn withdraw_vested_funds(
+fn grant_fee_allowance(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    data: WithdrawVestedFundsMsg,
+    data: FeeAllowanceMsg,
 ) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
-    let state = STATE.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    let grantee = deps.api.addr_validate(&data.grantee)?;
+    if !config.whitelisted_addresses.contains(&grantee) {
+        return Err(ContractError::ValidationError(
+            "Fee allowances can only be granted to whitelisted addresses".to_string(),
+        ));
+    }
+    // an empty spend limit would be an unlimited allowance
+    if data.spend_limit.is_zero() {
+        return Err(ContractError::ValidationError(
+            "Spend limit must be positive".to_string(),
+        ));
+    }
+    // the feegrant module keeps one allowance per grantee, a new grant replaces the current one
+    let mut msgs = vec![];
+    msgs.extend(release_fee_grant(
+        deps.storage,
+        &deps.querier,
+        &env,
+        &grantee,
+    )?);
+    let budget = FEE_BUDGET
+        .may_load(deps.storage)?
+        .filter(|budget| budget.amount >= data.spend_limit)
+        .ok_or_else(|| ContractError::ValidationError("Fee budget exceeded".to_string()))?;
+    FEE_BUDGET.save(
+        deps.storage,
+        &Coin {
+            amount: budget.amount.checked_sub(data.spend_limit)?,
+            denom: budget.denom.clone(),
+        },
+    )?;
 
-    if !config.whitelisted_addresses.contains(&info.sender)
-        || env.block.time.seconds() < config.start_time.u64()
-    {
+    FEE_GRANTS.save(deps.storage, &grantee, &data.spend_limit)?;
+    let spend_limit = Coin {
+        denom: budget.denom,
+        amount: data.spend_limit,
+    };
+    msgs.push(grant_allowance_msg(
+        &env.contract.address,
+        &grantee,
+        &spend_limit,
+    ));
+
+    Ok(Response::new().add_messages(msgs).add_event(
+        admin_event("grant_fee_allowance")
+            .add_attribute("grantee", grantee)
+            .add_attribute("spend_limit", spend_limit.to_string()),
+    ))
+}
+
+fn revoke_fee_allowance(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    data: RevokeFeeAllowanceMsg,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
         return Err(ContractError::Unauthorized {});
     }
+    let grantee = deps.api.addr_validate(&data.grantee)?;
+    if !FEE_GRANTS.has(deps.storage, &grantee) {
+        return Err(ContractError::ValidationError(
+            "No fee allowance granted".to_string(),
+        ));
+    }
+    let msgs = release_fee_grant(deps.storage, &deps.querier, &env, &grantee)?;
+    let budget = FEE_BUDGET.load(deps.storage)?;
 
-    // Need to withdraw unlocked amount before linear vesting amount
-    if state.unlocked_amount_withdrawn < config.unlocked_amount {
-        return Err(ContractError::WithdrawUnlockedFirst {});
+    Ok(Response::new().add_messages(msgs).add_event(
+        admin_event("revoke_fee_allowance")
+            .add_attribute("grantee", grantee)
+            .add_attribute("fee_budget", budget.to_string()),
+    ))
+}
+
+// removes the grant of `grantee` and gives the unspent part of its allowance back to the fee budget,
+// returns the revocation unless the allowance is already used up and gone
+fn release_fee_grant(
+    storage: &mut dyn Storage,
+    querier: &QuerierWrapper,
+    env: &Env,
+    grantee: &Addr,
+) -> Result<Option<CosmosMsg>, ContractError> {
+    let spend_limit = match FEE_GRANTS.may_load(storage, grantee)? {
+        Some(spend_limit) => spend_limit,
+        None => return Ok(None),
+    };
+    FEE_GRANTS.remove(storage, grantee);
+    let mut budget = FEE_BUDGET.load(storage)?;
+    let remaining =
+        query_remaining_allowance(querier, &env.contract.address, grantee, &budget.denom)?;
+    match remaining {
+        Some(remaining) => {
+            budget.amount = budget.amount.checked_add(remaining.min(spend_limit))?;
+            FEE_BUDGET.save(storage, &budget)?;
+            Ok(Some(revoke_allowance_msg(&env.contract.address, grantee)))
+        }
+        None => Ok(None),
     }
+}
 
-    // Need to withdraw cliff amount before linear vesting amount
-    if state.cliff_amount_withdrawn < config.cliff_amount {
-        return Err(ContractError::WithdrawCliffFirst {});
+// fee funds held for the budget and the granted allowances, the part of an allowance already
+// spent by its grantee stays reserved until the allowance is revoked or replaced, when only the
+// unspent part goes back to the budget
+fn fee_reserve(storage: &dyn Storage) -> Result<Option<Coin>, ContractError> {
+    let budget = match FEE_BUDGET.may_load(storage)? {
+        Some(budget) => budget,
+        None => return Ok(None),
+    };
+    let spend_limits = FEE_GRANTS
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, spend_limit)| spend_limit))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(Some(Coin {
+        amount: budget.amount.checked_add(checked_sum(spend_limits)?)?,
+        denom: budget.denom,
+    }))
+}
+
+fn validate_ibc_payout(
+    vesting_asset: &AssetInfo,
+    recipients: &[(Addr, u16)],
+    ibc_payout: &Option<IbcPayout>,
+) -> Result<(), ContractError> {
+    if let Some(ibc_payout) = ibc_payout {
+        if !matches!(vesting_asset, AssetInfo::Native(_)) {
+            return Err(ContractError::ValidationError(
+                "IBC payouts require a native vesting asset".to_string(),
+            ));
+        }
+        if ibc_payout.channel_id.is_empty()
+            || ibc_payout.remote_address.is_empty()
+            || ibc_payout.timeout_seconds.is_zero()
+        {
+            return Err(ContractError::ValidationError(
+                "IBC payout requires a channel, a remote address and a timeout".to_string(),
+            ));
+        }
+        if !recipients
+            .iter()
+            .any(|(addr, _)| addr.as_str() == ibc_payout.recipient)
+        {
+            return Err(ContractError::ValidationError(
+                "IBC payout recipient must be a recipient".to_string(),
+            ));
+        }
     }
+    Ok(())
+}
 
-    let current_balance = deps
-        .querier
-        .query_balance(env.contract.address.clone(), data.denom.clone())?
-        .amount;
+#[cfg_attr(not(any(feature = "library", feature = "factory")), entry_point)]
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        IBC_TRANSFER_REPLY_ID => {
+            let transfer = IBC_TRANSFER_REPLY.load(deps.storage)?;
+            IBC_TRANSFER_REPLY.remove(deps.storage);
+            let data = msg
+                .result
+                .into_result()
+                .map_err(StdError::generic_err)?
+                .data
+                .ok_or_else(|| StdError::generic_err("Missing IBC transfer response"))?;
+            let sequence = parse_transfer_sequence(&data)?;
+            IBC_TRANSFERS.save(deps.storage, (&transfer.channel_id, sequence), &transfer)?;
+
+            Ok(Response::new().add_event(
+                withdraw_event("ibc_transfer")
+                    .add_attribute("channel_id", transfer.channel_id)
+                    .add_attribute("sequence", sequence.to_string())
+                    .add_attribute("tranche", transfer.tranche.to_string())
+                    .add_attribute("amount", transfer.amount),
+            ))
+        }
+        id => Err(StdError::generic_err(format!("Unknown reply id: {}", id)).into()),
+    }
+}
 
-    // Use end time here to prevent overflow
-    let current_time = config.end_time.u64().min(env.block.time.seconds());
+// MsgTransferResponse only holds the packet sequence, encoded as protobuf varint field 1
+fn parse_transfer_sequence(data: &[u8]) -> StdResult<u64> {
+    if let Some((0x08, varint)) = data.split_first() {
+        let mut sequence = 0u64;
+        for (i, byte) in varint.iter().take(10).enumerate() {
+            sequence |= u64::from(byte & 0x7f) << (7 * i);
+            if byte & 0x80 == 0 {
+                return Ok(sequence);
+            }
+        }
+    }
+    Err(StdError::generic_err("Invalid IBC transfer response"))
+}
+
+#[cfg_attr(not(any(feature = "library", feature = "factory")), entry_point)]
+pub fn sudo(deps: DepsMut, env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
+    match msg {
+        SudoMsg::IbcLifecycleComplete(IbcLifecycleComplete::IbcAck {
+            channel,
+            sequence,
+            success,
+            ..
+        }) => ibc_transfer_complete(deps, channel, sequence, success),
+        SudoMsg::IbcLifecycleComplete(IbcLifecycleComplete::IbcTimeout { channel, sequence }) => {
+            ibc_transfer_complete(deps, channel, sequence, false)
+        }
+        SudoMsg::Pause {} => save_paused(deps.storage, true, "sudo"),
+        SudoMsg::Unpause {} => save_paused(deps.storage, false, "sudo"),
+        SudoMsg::UpdateRecipient(data) => {
+            let config = CONFIG.load(deps.storage)?;
+            save_recipients(deps, config, data)
+        }
+        SudoMsg::Clawback(data) => clawback(deps, env, data),
+    }
+}
 
-    let amount_withdrawable = config.vesting_amount
-        - config.vesting_amount
-            * Uint128::from(state.last_withdrawn_time.u64() - config.start_time.u64())
-            / Uint128::from(config.end_time - config.start_time)
-        - config.vesting_amount * Uint128::from(config.end_time.u64() - current_time)
-            / Uint128::from(config.end_time - config.start_time);
+// ends the schedule at the current time and returns everything not vested yet
+fn clawback(deps: DepsMut, env: Env, data: ClawbackMsg) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let current_time = env.block.time.seconds();
+    let destination = deps.api.addr_validate(&data.destination)?;
 
-    let balance_smaller_than_withdrawable = if current_time < config.end_time.u64() {
-        current_balance < amount_withdrawable
+    // the amount already withdrawn stays the same under the shortened schedule
+    let new_config = if current_time <= config.start_time.u64() {
+        Config {
+            cliff_amount: Uint128::zero(),
+            vesting_amount: Uint128::zero(),
+            ..config.clone()
+        }
+    } else if current_time < config.end_time.u64() {
+        Config {
+            vesting_amount: vesting::vested_at(&Schedule::from(&config), current_time)?,
+            end_time: Uint64::new(current_time),
+            ..config.clone()
+        }
     } else {
-        true
+        config.clone()
     };
+    let amount = config
+        .cliff_amount
+        .checked_add(config.vesting_amount)?
+        .checked_sub(new_config.cliff_amount)?
+        .checked_sub(new_config.vesting_amount)?;
+    if amount.is_zero() {
+        return Err(ContractError::ValidationError(
+            "Nothing to claw back".to_string(),
+        ));
+    }
+    let balance = available_balance(deps.as_ref(), &env, &config, &config.vesting_asset)?;
+    if balance < amount {
+        return Err(ContractError::ValidationError(
+            "Clawback exceeds the liquid balance, undelegate first".to_string(),
+        ));
+    }
+    CONFIG.save(deps.storage, &new_config)?;
 
-    let amount_to_withdraw = if data.denom == "uluna" {
-        if balance_smaller_than_withdrawable {
-            current_balance
-        } else {
-            amount_withdrawable
+    let msg = Asset::new(config.vesting_asset.clone(), amount).transfer_msg(&destination)?;
+    Ok(Response::new().add_message(msg).add_event(
+        admin_event("clawback")
+            .add_attribute("destination", destination)
+            .add_attribute("amount", amount)
+            .add_attribute("cliff_amount", new_config.cliff_amount)
+            .add_attribute("vesting_amount", new_config.vesting_amount)
+            .add_attribute("end_time", new_config.end_time),
+    ))
+}
+
+fn ibc_transfer_complete(
+    deps: DepsMut,
+    channel: String,
+    sequence: u64,
+    success: bool,
+) -> Result<Response, ContractError> {
+    let transfer = match IBC_TRANSFERS.may_load(deps.storage, (&channel, sequence))? {
+        Some(transfer) => transfer,
+        None => return Ok(Response::new()),
+    };
+    IBC_TRANSFERS.remove(deps.storage, (&channel, sequence));
+
+    let mut res = Response::new();
+    let mut event = withdraw_event("ibc_transfer_complete")
+        .add_attribute("channel_id", channel)
+        .add_attribute("sequence", sequence.to_string())
+        .add_attribute("tranche", transfer.tranche.to_string())
+        .add_attribute("amount", transfer.amount)
+        .add_attribute("success", success.to_string());
+
+    // the refund is the share of the IBC recipient alone, so it is paid to them locally instead of
+    // going back into the tranche, which would split it among all recipients again
+    if !success {
+        let config = CONFIG.load(deps.storage)?;
+        res = res.add_message(
+            Asset::new(config.vesting_asset, transfer.amount).transfer_msg(&transfer.recipient)?,
+        );
+        if let Some(history_id) = transfer.history_id {
+            withdrawal_history().update(deps.storage, history_id, |entry| match entry {
+                Some(entry) => Ok(WithdrawalHistoryEntry {
+                    recipient: transfer.recipient.to_string(),
+                    ..entry
+                }),
+                None => Err(StdError::not_found("WithdrawalHistoryEntry")),
+            })?;
+        }
+        event = event.add_attribute("refund_recipient", transfer.recipient);
+    }
+
+    Ok(res.add_event(event))
+}
+
+#[cfg_attr(not(any(feature = "library", feature = "factory")), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::QueryConfig => to_binary(&ConfigResponse::from(CONFIG.load(deps.storage)?)),
+        QueryMsg::QueryState => to_binary(&StateResponse::from(STATE.load(deps.storage)?)),
+        QueryMsg::QueryFunding => to_binary(&query_funding(deps, env)?),
+        QueryMsg::QueryPaused => to_binary(&PausedResponse {
+            paused: is_paused(deps.storage)?,
+        }),
+        QueryMsg::QueryWithdrawable => to_binary(&query_withdrawable(deps, env)?),
+        QueryMsg::QuerySummary => to_binary(&query_summary(deps, env)?),
+        QueryMsg::QuerySolvency => to_binary(&query_solvency(deps, env)?),
+        QueryMsg::QueryApprovedDestinations { recipient } => {
+            to_binary(&query_approved_destinations(deps, recipient)?)
+        }
+        QueryMsg::QueryPendingWithdrawals { start_after, limit } => {
+            to_binary(&query_pending_withdrawals(deps, start_after, limit)?)
+        }
+        QueryMsg::QueryWithdrawalHistory {
+            start_after,
+            limit,
+            tranche,
+            sender,
+        } => to_binary(&query_withdrawal_history(
+            deps,
+            start_after,
+            limit,
+            tranche,
+            sender,
+        )?),
+        QueryMsg::QueryFeeGrants { start_after, limit } => {
+            to_binary(&query_fee_grants(deps, start_after, limit)?)
+        }
+        QueryMsg::OwnerOf {
+            token_id,
+            include_expired,
+        } => to_binary(&query_owner_of(deps, &env, token_id, include_expired)?),
+        QueryMsg::Approval {
+            token_id,
+            spender,
+            include_expired,
+        } => to_binary(&query_approval(
+            deps,
+            &env,
+            token_id,
+            spender,
+            include_expired,
+        )?),
+        QueryMsg::Approvals {
+            token_id,
+            include_expired,
+        } => {
+            let (id, _) = position_holder(deps.storage, &token_id)?;
+            to_binary(&ApprovalsResponse {
+                approvals: query_approvals(deps, &env, id, include_expired)?,
+            })
         }
+        QueryMsg::AllOperators {
+            owner,
+            include_expired,
+            start_after,
+            limit,
+        } => to_binary(&query_operators(
+            deps,
+            &env,
+            owner,
+            include_expired,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::NftInfo { token_id } => to_binary(&query_nft_info(deps, token_id)?),
+        QueryMsg::AllNftInfo {
+            token_id,
+            include_expired,
+        } => to_binary(&AllNftInfoResponse {
+            access: query_owner_of(deps, &env, token_id.clone(), include_expired)?,
+            info: query_nft_info(deps, token_id)?,
+        }),
+        QueryMsg::Tokens {
+            owner,
+            start_after,
+            limit,
+        } => to_binary(&query_tokens(deps, Some(owner), start_after, limit)?),
+        QueryMsg::AllTokens { start_after, limit } => {
+            to_binary(&query_tokens(deps, None, start_after, limit)?)
+        }
+        QueryMsg::NumTokens {} => to_binary(&NumTokensResponse {
+            count: CONFIG.load(deps.storage)?.recipients.len() as u64,
+        }),
+        QueryMsg::ContractInfo {} => to_binary(&ContractInfoResponse {
+            name: POSITION_NAME.to_string(),
+            symbol: POSITION_SYMBOL.to_string(),
+        }),
+    }
+}
+
+fn query_withdrawable(deps: Deps, env: Env) -> StdResult<WithdrawableResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let state = STATE.load(deps.storage)?;
+    let current_time = env.block.time.seconds();
+    let schedule = Schedule::from(&config);
+
+    let cliff_amount = if current_time < config.start_time.u64() {
+        Uint128::zero()
     } else {
-        current_balance
+        config
+            .cliff_amount
+            .saturating_sub(state.cliff_amount_withdrawn)
     };
 
-    let last_withdrawn_time = if balance_smaller_than_withdrawable {
-        //if balance is smaller than withdrawable, we set the withdrawn time in seconds to something smaller than the current blocktime
-        state.last_withdrawn_time
-            + Uint64::try_from(
-                amount_to_withdraw
-                    / (config.vesting_amount / Uint128::from(config.end_time - config.start_time)),
-            )?
+    Ok(WithdrawableResponse {
+        unlocked_amount: config
+            .unlocked_amount
+            .saturating_sub(state.unlocked_amount_withdrawn),
+        cliff_amount,
+        vesting_amount: vesting::withdrawable(
+            &schedule,
+            state.last_withdrawn_time.u64(),
+            current_time,
+        )?,
+        next_unlock_time: vesting::next_unlock_time(&schedule, current_time)?.map(Uint64::new),
+        period_allowance: period_allowance(&config, &state, &env)?,
+    })
+}
+
+fn query_approved_destinations(
+    deps: Deps,
+    recipient: String,
+) -> StdResult<ApprovedDestinationsResponse> {
+    let recipient = deps.api.addr_validate(&recipient)?;
+    let destinations = APPROVED_DESTINATIONS
+        .prefix(&recipient)
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(address, msg)| PayoutDestination { address, msg }))
+        .collect::<StdResult<Vec<_>>>()?;
+    Ok(ApprovedDestinationsResponse { destinations })
+}
+
+fn query_pending_withdrawals(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<PendingWithdrawalsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let withdrawals = PENDING_WITHDRAWALS
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| item.map(|(_, pending)| pending))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(PendingWithdrawalsResponse { withdrawals })
+}
+
+fn query_fee_grants(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<FeeGrantsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start_after = start_after
+        .map(|grantee| deps.api.addr_validate(&grantee))
+        .transpose()?;
+    let grants = FEE_GRANTS
+        .range(
+            deps.storage,
+            start_after.as_ref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| {
+            item.map(|(grantee, spend_limit)| FeeGrant {
+                grantee,
+                spend_limit,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(FeeGrantsResponse {
+        budget: FEE_BUDGET.may_load(deps.storage)?,
+        grants,
+    })
+}
+
+fn query_owner_of(
+    deps: Deps,
+    env: &Env,
+    token_id: String,
+    include_expired: Option<bool>,
+) -> StdResult<OwnerOfResponse> {
+    let (id, holder) = position_holder(deps.storage, &token_id)?;
+    Ok(OwnerOfResponse {
+        owner: holder.to_string(),
+        approvals: query_approvals(deps, env, id, include_expired)?,
+    })
+}
+
+fn query_approval(
+    deps: Deps,
+    env: &Env,
+    token_id: String,
+    spender: String,
+    include_expired: Option<bool>,
+) -> StdResult<ApprovalResponse> {
+    let (id, holder) = position_holder(deps.storage, &token_id)?;
+    let spender = deps.api.addr_validate(&spender)?;
+    // the holder can always move its own position
+    let expires = if spender == holder {
+        Some(Expiration::Never {})
     } else {
-        Uint64::new(current_time)
+        TOKEN_APPROVALS.may_load(deps.storage, (id, &spender))?
     };
+    match expires {
+        Some(expires) if include_expired.unwrap_or(false) || !expires.is_expired(&env.block) => {
+            Ok(ApprovalResponse {
+                approval: Approval {
+                    spender: spender.to_string(),
+                    expires,
+                },
+            })
+        }
+        _ => Err(StdError::not_found("approval")),
+    }
+}
 
-    STATE.save(
-        deps.storage,
-        &State {
-            last_withdrawn_time: if data.denom == "uluna" {
-                //only update the withdrawal block if the asset withdrawn is luna
-                last_withdrawn_time
-            } else {
-                state.last_withdrawn_time
-            },
-            cliff_amount_withdrawn: state.cliff_amount_withdrawn,
-            unlocked_amount_withdrawn: state.unlocked_amount_withdrawn,
-        },
-    )?;
+fn query_approvals(
+    deps: Deps,
+    env: &Env,
+    id: u64,
+    include_expired: Option<bool>,
+) -> StdResult<Vec<Approval>> {
+    TOKEN_APPROVALS
+        .prefix(id)
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter(|item| {
+            include_expired.unwrap_or(false)
+                || item
+                    .as_ref()
+                    .map_or(true, |(_, expires)| !expires.is_expired(&env.block))
+        })
+        .map(|item| {
+            item.map(|(spender, expires)| Approval {
+                spender: spender.to_string(),
+                expires,
+            })
+        })
+        .collect()
+}
 
-    if amount_to_withdraw.is_zero() {
-        return Err(ContractError::NothingToWithdraw {});
-    }
+fn query_operators(
+    deps: Deps,
+    env: &Env,
+    owner: String,
+    include_expired: Option<bool>,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<OperatorsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let owner = deps.api.addr_validate(&owner)?;
+    let start_after = start_after
+        .map(|operator| deps.api.addr_validate(&operator))
+        .transpose()?;
+    let operators = OPERATORS
+        .prefix(&owner)
+        .range(
+            deps.storage,
+            start_after.as_ref().map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .filter(|item| {
+            include_expired.unwrap_or(false)
+                || item
+                    .as_ref()
+                    .map_or(true, |(_, expires)| !expires.is_expired(&env.block))
+        })
+        .take(limit)
+        .map(|item| {
+            item.map(|(spender, expires)| Approval {
+                spender: spender.to_string(),
+                expires,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
 
-    let msg = CosmosMsg::Bank(BankMsg::Send {
-        to_address: config.recipient.to_string(),
-        amount: vec![Coin::new(amount_to_withdraw.u128(), data.denom.clone())],
-    });
+    Ok(OperatorsResponse { operators })
+}
 
-    Ok(Response::new()
-        .add_message(msg)
-        .add_attribute("action", "withdraw_vested_funds")
-        .add_attribute("denom", data.denom)
-        .add_attribute("amount_to_withdraw", amount_to_withdraw)
-        .add_attribute("last_updated_block", env.block.time.seconds().to_string()))
+fn query_nft_info(deps: Deps, token_id: String) -> StdResult<NftInfoResponse<VestingPosition>> {
+    let config = CONFIG.load(deps.storage)?;
+    let (_, holder) = position_holder(deps.storage, &token_id)?;
+    let share_bps = config
+        .recipients
+        .iter()
+        .find(|(addr, _)| *addr == holder)
+        .map(|(_, share_bps)| *share_bps)
+        .ok_or_else(|| StdError::not_found("vesting position"))?;
+    Ok(NftInfoResponse {
+        token_uri: None,
+        extension: VestingPosition { share_bps },
+    })
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::QueryConfig => to_binary(&CONFIG.load(deps.storage)?),
-        QueryMsg::QueryState => to_binary(&STATE.load(deps.storage)?),
+fn query_tokens(
+    deps: Deps,
+    owner: Option<String>,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<TokensResponse> {
+    let owner = owner
+        .map(|owner| deps.api.addr_validate(&owner))
+        .transpose()?;
+    let start = start_after
+        .map(|token_id| {
+            token_id
+                .parse::<u64>()
+                .map_err(|_| StdError::not_found("vesting position"))
+        })
+        .transpose()?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let tokens = POSITIONS
+        .range(
+            deps.storage,
+            start.map(Bound::exclusive),
+            None,
+            Order::Ascending,
+        )
+        .filter(|item| {
+            item.as_ref().map_or(true, |(_, holder)| {
+                owner.as_ref().map_or(true, |owner| owner == holder)
+            })
+        })
+        .take(limit)
+        .map(|item| item.map(|(id, _)| id.to_string()))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(TokensResponse { tokens })
+}
+
+fn query_withdrawal_history(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+    tranche: Option<Tranche>,
+    sender: Option<String>,
+) -> StdResult<WithdrawalHistoryResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+    let sender = sender
+        .map(|sender| deps.api.addr_validate(&sender))
+        .transpose()?;
+    let history = withdrawal_history();
+    // the sender index is used when both filters are given, the tranche is checked per entry
+    let entries = match (sender, &tranche) {
+        (Some(sender), _) => {
+            history
+                .idx
+                .sender
+                .prefix(sender)
+                .range(deps.storage, start, None, Order::Ascending)
+        }
+        (None, Some(tranche)) => history.idx.tranche.prefix(tranche.to_string()).range(
+            deps.storage,
+            start,
+            None,
+            Order::Ascending,
+        ),
+        (None, None) => history.range(deps.storage, start, None, Order::Ascending),
+    };
+    let withdrawals = entries
+        .filter(|item| {
+            item.as_ref().map_or(true, |(_, entry)| {
+                tranche
+                    .as_ref()
+                    .map_or(true, |tranche| entry.tranche == *tranche)
+            })
+        })
+        .take(limit)
+        .map(|item| item.map(|(_, entry)| entry))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(WithdrawalHistoryResponse { withdrawals })
+}
+
+fn query_summary(deps: Deps, env: Env) -> StdResult<SummaryResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let state = STATE.load(deps.storage)?;
+    let schedule = Schedule::from(&config);
+
+    let vested = vesting::vested_at(&schedule, env.block.time.seconds())?;
+    let percent_vested = if config.vesting_amount.is_zero() {
+        Decimal::percent(10_000)
+    } else {
+        Decimal::from_ratio(vested, config.vesting_amount) * Decimal::percent(10_000)
+    };
+    let liquid_balance = config
+        .vesting_asset
+        .query_balance(&deps.querier, env.contract.address.clone())
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+    let (delegated_balance, unbonding_balance) = query_staked_balances(deps, &env, &config)?;
+
+    Ok(SummaryResponse {
+        vesting_asset: config.vesting_asset.clone(),
+        unlocked_amount: config.unlocked_amount,
+        cliff_amount: config.cliff_amount,
+        vesting_amount: config.vesting_amount,
+        start_time: config.start_time,
+        end_time: config.end_time,
+        percent_vested,
+        unlocked_amount_withdrawn: state.unlocked_amount_withdrawn,
+        cliff_amount_withdrawn: state.cliff_amount_withdrawn,
+        vesting_amount_withdrawn: vesting::vested_at(&schedule, state.last_withdrawn_time.u64())?,
+        withdrawable: query_withdrawable(deps, env.clone())?,
+        paused: is_paused(deps.storage)?,
+        liquid_balance,
+        delegated_balance,
+        pending_rewards: query_pending_rewards(deps, &env)?,
+        unbonding_balance,
+    })
+}
+
+// rewards accrued on every delegation of the contract, summed per denom
+fn query_pending_rewards(deps: Deps, env: &Env) -> StdResult<Vec<Coin>> {
+    let mut rewards: Vec<Coin> = vec![];
+    for delegation in deps
+        .querier
+        .query_all_delegations(env.contract.address.clone())?
+    {
+        let accumulated_rewards = deps
+            .querier
+            .query_delegation(env.contract.address.clone(), delegation.validator)?
+            .map(|delegation| delegation.accumulated_rewards)
+            .unwrap_or_default();
+        for reward in accumulated_rewards {
+            match rewards.iter_mut().find(|coin| coin.denom == reward.denom) {
+                Some(coin) => coin.amount = coin.amount.checked_add(reward.amount)?,
+                None => rewards.push(reward),
+            }
+        }
     }
+    rewards.retain(|coin| !coin.amount.is_zero());
+    Ok(rewards)
+}
+
+fn query_funding(deps: Deps, env: Env) -> StdResult<FundingResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let state = STATE.load(deps.storage)?;
+
+    // queued withdrawals are accounted as withdrawn but are still held by the contract
+    let total_obligations = outstanding_obligations(&config, &state)?
+        .checked_add(pending_withdrawals_total(deps.storage)?)?;
+    let liquid_balance = config
+        .vesting_asset
+        .query_balance(&deps.querier, env.contract.address.clone())
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+    let (delegated_balance, unbonding_balance) = query_staked_balances(deps, &env, &config)?;
+    let total_holdings = checked_sum([liquid_balance, delegated_balance, unbonding_balance])?;
+
+    Ok(FundingResponse {
+        total_obligations,
+        total_funded: FUNDED_AMOUNT.may_load(deps.storage)?.unwrap_or_default(),
+        liquid_balance,
+        delegated_balance,
+        unbonding_balance,
+        total_holdings,
+        shortfall: total_obligations.saturating_sub(total_holdings),
+    })
+}
+
+fn query_solvency(deps: Deps, env: Env) -> StdResult<SolvencyResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let state = STATE.load(deps.storage)?;
+
+    let total_obligations = outstanding_obligations(&config, &state)?;
+    let withdrawable_obligations =
+        withdrawable_obligations(&config, &state, env.block.time.seconds())?;
+    let liquid_balance = available_balance(deps, &env, &config, &config.vesting_asset)?;
+    let (delegated_balance, unbonding_balance) = query_staked_balances(deps, &env, &config)?;
+    let total_holdings = checked_sum([liquid_balance, delegated_balance, unbonding_balance])?;
+
+    Ok(SolvencyResponse {
+        total_obligations,
+        withdrawable_obligations,
+        liquid_balance,
+        delegated_balance,
+        unbonding_balance,
+        liquid_shortfall: withdrawable_obligations.saturating_sub(liquid_balance),
+        shortfall: total_obligations.saturating_sub(total_holdings),
+    })
+}
+
+// amount of the schedule that is yet to be withdrawn by the recipient
+fn outstanding_obligations(config: &Config, state: &State) -> Result<Uint128, ContractError> {
+    let vesting_amount_withdrawn =
+        vesting::vested_at(&Schedule::from(config), state.last_withdrawn_time.u64())?;
+
+    checked_sum([
+        config
+            .unlocked_amount
+            .saturating_sub(state.unlocked_amount_withdrawn),
+        config
+            .cliff_amount
+            .saturating_sub(state.cliff_amount_withdrawn),
+        config
+            .vesting_amount
+            .saturating_sub(vesting_amount_withdrawn),
+    ])
+}
+
+// part of the outstanding obligations the recipient can withdraw at `time`
+fn withdrawable_obligations(
+    config: &Config,
+    state: &State,
+    time: u64,
+) -> Result<Uint128, ContractError> {
+    let cliff_amount = if time < config.start_time.u64() {
+        Uint128::zero()
+    } else {
+        config
+            .cliff_amount
+            .saturating_sub(state.cliff_amount_withdrawn)
+    };
+
+    checked_sum([
+        config
+            .unlocked_amount
+            .saturating_sub(state.unlocked_amount_withdrawn),
+        cliff_amount,
+        vesting::withdrawable(
+            &Schedule::from(config),
+            state.last_withdrawn_time.u64(),
+            time,
+        )?,
+    ])
+}
+
+// delegated and unbonding amounts of the vesting asset, always zero for cw20 assets
+fn query_staked_balances(deps: Deps, env: &Env, config: &Config) -> StdResult<(Uint128, Uint128)> {
+    let denom = match &config.vesting_asset {
+        AssetInfo::Native(denom) => denom,
+        _ => return Ok((Uint128::zero(), Uint128::zero())),
+    };
+
+    let delegated = checked_sum(
+        deps.querier
+            .query_all_delegations(env.contract.address.clone())?
+            .iter()
+            .filter(|delegation| delegation.amount.denom == *denom)
+            .map(|delegation| delegation.amount.amount),
+    )?;
+    let unbonding = UNBONDING
+        .range(
+            deps.storage,
+            Some(Bound::exclusive(env.block.time.seconds())),
+            None,
+            Order::Ascending,
+        )
+        .map(|item| item.map(|(_, amount)| amount))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok((delegated, checked_sum(unbonding)?))
 }
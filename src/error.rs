@@ -1,4 +1,5 @@
-use cosmwasm_std::{ConversionOverflowError, StdError};
+use cosmwasm_std::{ConversionOverflowError, DivideByZeroError, OverflowError, StdError};
+use cw_asset::AssetError;
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -6,9 +7,24 @@ pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error("{0}")]
+    Asset(#[from] AssetError),
+
     #[error("Unauthorized")]
     Unauthorized {},
 
+    #[error("Contract is paused")]
+    Paused {},
+
+    #[error("Withdraw limit for the period reached")]
+    WithdrawLimitReached {},
+
+    #[error("Delegation would leave less liquid funds than are withdrawable")]
+    InsufficientLiquidity {},
+
+    #[error("Withdrawal is still locked")]
+    WithdrawalLocked {},
+
     #[error("Withdraw unlocked amount first")]
     WithdrawUnlockedFirst {},
 
@@ -18,8 +34,26 @@ pub enum ContractError {
     #[error("Nothing to withdraw")]
     NothingToWithdraw {},
 
+    #[error("Destination is not approved by every recipient")]
+    DestinationNotApproved {},
+
+    #[error("Unsupported asset")]
+    UnsupportedAsset {},
+
+    #[error("No funds sent")]
+    NoFunds {},
+
     #[error("Validation error {0}")]
     ValidationError(String),
+
+    #[error("{0}")]
+    Overflow(#[from] OverflowError),
+
+    #[error("{0}")]
+    DivideByZero(#[from] DivideByZeroError),
+
+    #[error("Invalid schedule: {0}")]
+    InvalidSchedule(String),
 }
 
 impl From<ConversionOverflowError> for ContractError {
@@ -27,3 +61,15 @@ impl From<ConversionOverflowError> for ContractError {
         ContractError::Std(StdError::generic_err(value.value))
     }
 }
+
+// queries share the arithmetic of the handlers but can only fail with a StdError
+impl From<ContractError> for StdError {
+    fn from(value: ContractError) -> Self {
+        match value {
+            ContractError::Std(err) => err,
+            ContractError::Overflow(err) => err.into(),
+            ContractError::DivideByZero(err) => err.into(),
+            err => StdError::generic_err(err.to_string()),
+        }
+    }
+}
@@ -0,0 +1,45 @@
+use cosmwasm_std::{Addr, Event};
+
+// wasmd prefixes custom event types with `wasm-`
+pub const WITHDRAW_EVENT: &str = "vesting-withdraw";
+pub const ADMIN_EVENT: &str = "vesting-admin";
+pub const STAKE_EVENT: &str = "vesting-stake";
+pub const FACTORY_EVENT: &str = "vesting-factory";
+
+/// Payouts, queued withdrawals and IBC transfer outcomes
+pub fn withdraw_event(action: &str) -> Event {
+    Event::new(WITHDRAW_EVENT).add_attribute("action", action)
+}
+
+/// Configuration, whitelist, funding and schedule changes
+pub fn admin_event(action: &str) -> Event {
+    Event::new(ADMIN_EVENT).add_attribute("action", action)
+}
+
+/// Delegation, undelegation, redelegation and reward withdrawals
+pub fn stake_event(action: &str) -> Event {
+    Event::new(STAKE_EVENT).add_attribute("action", action)
+}
+
+/// Instances created by the factory and factory configuration changes
+pub fn factory_event(action: &str) -> Event {
+    Event::new(FACTORY_EVENT).add_attribute("action", action)
+}
+
+// addresses joined by commas, e.g. `terra1...,terra1...`
+pub fn join_addresses(addresses: &[Addr]) -> String {
+    addresses
+        .iter()
+        .map(|addr| addr.to_string())
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+// recipients with their share in basis points, e.g. `terra1...:7000,terra1...:3000`
+pub fn join_recipients(recipients: &[(Addr, u16)]) -> String {
+    recipients
+        .iter()
+        .map(|(addr, bps)| format!("{}:{}", addr, bps))
+        .collect::<Vec<String>>()
+        .join(",")
+}
@@ -0,0 +1,359 @@
+use crate::events::{factory_event, join_addresses};
+use crate::{ContractError, InstantiateMsg, UpdateOwnerMsg};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    entry_point, instantiate2_address, to_binary, Addr, Binary, Deps, DepsMut, Env, MessageInfo,
+    Order, Response, StdError, StdResult, Uint64, WasmMsg,
+};
+use cw_storage_plus::{Bound, Index, IndexList, IndexedMap, Item, Map, MultiIndex};
+
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
+#[cw_serde]
+pub struct FactoryInstantiateMsg {
+    pub owner: String,
+    pub vesting_code_id: u64,
+}
+
+#[cw_serde]
+pub enum FactoryExecuteMsg {
+    // boxed, the instantiate message dwarfs the other variants
+    CreateVesting(Box<CreateVestingMsg>),
+    UpdateCodeId(UpdateCodeIdMsg),
+    UpdateOwner(UpdateOwnerMsg),
+}
+
+#[cw_serde]
+pub struct CreateVestingMsg {
+    pub label: String,
+    /// Salt of the instance address, between 1 and 64 bytes
+    pub salt: Binary,
+    /// Migration admin of the instance
+    pub admin: Option<String>,
+    pub instantiate_msg: InstantiateMsg,
+}
+
+#[cw_serde]
+pub struct UpdateCodeIdMsg {
+    pub vesting_code_id: u64,
+}
+
+#[cw_serde]
+pub enum FactoryQueryMsg {
+    QueryConfig,
+    QueryInstanceAddress {
+        salt: Binary,
+    },
+    QueryInstances {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    QueryInstancesByOwner {
+        owner: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    QueryInstancesByRecipient {
+        recipient: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+}
+
+#[cw_serde]
+pub struct FactoryConfig {
+    pub owner: Addr,
+    pub vesting_code_id: u64,
+}
+
+/// Vesting contract created by the factory, with its owner and recipients at creation
+#[cw_serde]
+pub struct VestingInstance {
+    pub address: Addr,
+    pub owner: Addr,
+    pub recipients: Vec<Addr>,
+    pub code_id: u64,
+    pub label: String,
+    pub created_at: Uint64,
+}
+
+#[cw_serde]
+pub struct InstanceAddressResponse {
+    pub address: Addr,
+}
+
+#[cw_serde]
+pub struct InstancesResponse {
+    pub instances: Vec<VestingInstance>,
+}
+
+pub const FACTORY_CONFIG: Item<FactoryConfig> = Item::new("factory_config");
+// instances keyed by (recipient, instance address)
+pub const RECIPIENT_INSTANCES: Map<(&Addr, &Addr), bool> = Map::new("recipient_instances");
+
+pub struct InstanceIndexes<'a> {
+    pub owner: MultiIndex<'a, Addr, VestingInstance, Addr>,
+}
+
+impl<'a> IndexList<VestingInstance> for InstanceIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<VestingInstance>> + '_> {
+        let v: Vec<&dyn Index<VestingInstance>> = vec![&self.owner];
+        Box::new(v.into_iter())
+    }
+}
+
+// instances keyed by their address, indexed by owner
+pub fn instances<'a>() -> IndexedMap<'a, &'a Addr, VestingInstance, InstanceIndexes<'a>> {
+    let indexes = InstanceIndexes {
+        owner: MultiIndex::new(
+            |_pk, instance| instance.owner.clone(),
+            "instances",
+            "instances__owner",
+        ),
+    };
+    IndexedMap::new("instances", indexes)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn instantiate(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: FactoryInstantiateMsg,
+) -> Result<Response, ContractError> {
+    let owner = deps.api.addr_validate(&msg.owner)?;
+    FACTORY_CONFIG.save(
+        deps.storage,
+        &FactoryConfig {
+            owner: owner.clone(),
+            vesting_code_id: msg.vesting_code_id,
+        },
+    )?;
+
+    Ok(Response::new().add_event(
+        factory_event("instantiate")
+            .add_attribute("owner", owner)
+            .add_attribute("vesting_code_id", msg.vesting_code_id.to_string()),
+    ))
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: FactoryExecuteMsg,
+) -> Result<Response, ContractError> {
+    match msg {
+        FactoryExecuteMsg::CreateVesting(data) => create_vesting(deps, env, info, *data),
+        FactoryExecuteMsg::UpdateCodeId(data) => update_code_id(deps, info, data),
+        FactoryExecuteMsg::UpdateOwner(data) => update_owner(deps, info, data),
+    }
+}
+
+fn create_vesting(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    data: CreateVestingMsg,
+) -> Result<Response, ContractError> {
+    let config = FACTORY_CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    // fail here rather than in the instantiation of the instance
+    data.instantiate_msg.validate(env.block.time.seconds())?;
+    let address = instance_address(deps.as_ref(), &env, config.vesting_code_id, &data.salt)?;
+    if instances().has(deps.storage, &address) {
+        return Err(ContractError::ValidationError(
+            "Salt was already used".to_string(),
+        ));
+    }
+
+    let owner = deps.api.addr_validate(&data.instantiate_msg.owner)?;
+    let recipients = data
+        .instantiate_msg
+        .recipients
+        .iter()
+        .map(|(recipient, _)| deps.api.addr_validate(recipient))
+        .collect::<StdResult<Vec<Addr>>>()?;
+    let admin = data
+        .admin
+        .map(|admin| deps.api.addr_validate(&admin))
+        .transpose()?;
+
+    instances().save(
+        deps.storage,
+        &address,
+        &VestingInstance {
+            address: address.clone(),
+            owner: owner.clone(),
+            recipients: recipients.clone(),
+            code_id: config.vesting_code_id,
+            label: data.label.clone(),
+            created_at: Uint64::new(env.block.time.seconds()),
+        },
+    )?;
+    for recipient in &recipients {
+        RECIPIENT_INSTANCES.save(deps.storage, (recipient, &address), &true)?;
+    }
+
+    // funds sent to the factory fund the new instance
+    let msg = WasmMsg::Instantiate2 {
+        admin: admin.map(|admin| admin.to_string()),
+        code_id: config.vesting_code_id,
+        label: data.label,
+        msg: to_binary(&data.instantiate_msg)?,
+        funds: info.funds,
+        salt: data.salt,
+    };
+
+    Ok(Response::new().add_message(msg).add_event(
+        factory_event("create_vesting")
+            .add_attribute("address", address)
+            .add_attribute("owner", owner)
+            .add_attribute("recipients", join_addresses(&recipients))
+            .add_attribute("code_id", config.vesting_code_id.to_string()),
+    ))
+}
+
+fn update_code_id(
+    deps: DepsMut,
+    info: MessageInfo,
+    data: UpdateCodeIdMsg,
+) -> Result<Response, ContractError> {
+    let config = FACTORY_CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    FACTORY_CONFIG.save(
+        deps.storage,
+        &FactoryConfig {
+            vesting_code_id: data.vesting_code_id,
+            ..config
+        },
+    )?;
+
+    Ok(Response::new().add_event(
+        factory_event("update_code_id")
+            .add_attribute("vesting_code_id", data.vesting_code_id.to_string()),
+    ))
+}
+
+fn update_owner(
+    deps: DepsMut,
+    info: MessageInfo,
+    data: UpdateOwnerMsg,
+) -> Result<Response, ContractError> {
+    let config = FACTORY_CONFIG.load(deps.storage)?;
+    if config.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    let owner = deps.api.addr_validate(&data.owner)?;
+    FACTORY_CONFIG.save(
+        deps.storage,
+        &FactoryConfig {
+            owner: owner.clone(),
+            ..config
+        },
+    )?;
+
+    Ok(Response::new().add_event(factory_event("update_owner").add_attribute("owner", owner)))
+}
+
+// address of an instance created with the salt from the current code id
+fn instance_address(deps: Deps, env: &Env, code_id: u64, salt: &Binary) -> StdResult<Addr> {
+    let checksum = deps.querier.query_wasm_code_info(code_id)?.checksum;
+    let creator = deps.api.addr_canonicalize(env.contract.address.as_str())?;
+    let address = instantiate2_address(checksum.as_slice(), &creator, salt)
+        .map_err(|err| StdError::generic_err(err.to_string()))?;
+    deps.api.addr_humanize(&address)
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: FactoryQueryMsg) -> StdResult<Binary> {
+    match msg {
+        FactoryQueryMsg::QueryConfig => to_binary(&FACTORY_CONFIG.load(deps.storage)?),
+        FactoryQueryMsg::QueryInstanceAddress { salt } => {
+            let config = FACTORY_CONFIG.load(deps.storage)?;
+            to_binary(&InstanceAddressResponse {
+                address: instance_address(deps, &env, config.vesting_code_id, &salt)?,
+            })
+        }
+        FactoryQueryMsg::QueryInstances { start_after, limit } => {
+            to_binary(&query_instances(deps, start_after, limit)?)
+        }
+        FactoryQueryMsg::QueryInstancesByOwner {
+            owner,
+            start_after,
+            limit,
+        } => to_binary(&query_instances_by_owner(deps, owner, start_after, limit)?),
+        FactoryQueryMsg::QueryInstancesByRecipient {
+            recipient,
+            start_after,
+            limit,
+        } => to_binary(&query_instances_by_recipient(
+            deps,
+            recipient,
+            start_after,
+            limit,
+        )?),
+    }
+}
+
+fn query_instances(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<InstancesResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|address| Bound::ExclusiveRaw(address.into_bytes()));
+    let instances = instances()
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, instance)| instance))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(InstancesResponse { instances })
+}
+
+fn query_instances_by_owner(
+    deps: Deps,
+    owner: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<InstancesResponse> {
+    let owner = deps.api.addr_validate(&owner)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|address| Bound::ExclusiveRaw(address.into_bytes()));
+    let instances = instances()
+        .idx
+        .owner
+        .prefix(owner)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, instance)| instance))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(InstancesResponse { instances })
+}
+
+fn query_instances_by_recipient(
+    deps: Deps,
+    recipient: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<InstancesResponse> {
+    let recipient = deps.api.addr_validate(&recipient)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(|address| Bound::ExclusiveRaw(address.into_bytes()));
+    let instances = RECIPIENT_INSTANCES
+        .prefix(&recipient)
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.and_then(|address| instances().load(deps.storage, &address)))
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(InstancesResponse { instances })
+}
@@ -0,0 +1,351 @@
+use crate::factory::{
+    execute, instantiate, query, CreateVestingMsg, FactoryConfig, FactoryExecuteMsg,
+    FactoryInstantiateMsg, FactoryQueryMsg, InstanceAddressResponse, InstancesResponse,
+    UpdateCodeIdMsg, VestingInstance,
+};
+use crate::{ContractError, InstantiateMsg};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::testing::{mock_env, mock_info, MockApi, MockQuerier, MockStorage};
+use cosmwasm_std::{
+    coins, from_binary, to_binary, Addr, Api, Binary, CanonicalAddr, ContractResult, CosmosMsg,
+    Env, HexBinary, OwnedDeps, RecoverPubkeyError, StdResult, SystemError, SystemResult, Uint128,
+    Uint64, VerificationError, WasmMsg, WasmQuery,
+};
+use std::marker::PhantomData;
+
+const VESTING_CODE_ID: u64 = 7;
+
+// MockApi only humanizes canonical addresses of its own length, instantiate2 addresses are 32 bytes
+#[derive(Copy, Clone, Default)]
+struct FactoryApi(MockApi);
+
+impl Api for FactoryApi {
+    fn addr_validate(&self, human: &str) -> StdResult<Addr> {
+        self.0.addr_validate(human)
+    }
+
+    fn addr_canonicalize(&self, human: &str) -> StdResult<CanonicalAddr> {
+        self.0.addr_canonicalize(human)
+    }
+
+    fn addr_humanize(&self, canonical: &CanonicalAddr) -> StdResult<Addr> {
+        if canonical.len() == 32 {
+            let hex: String = canonical
+                .as_slice()
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect();
+            return Ok(Addr::unchecked(format!("instance{}", hex)));
+        }
+        self.0.addr_humanize(canonical)
+    }
+
+    fn secp256k1_verify(
+        &self,
+        message_hash: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<bool, VerificationError> {
+        self.0.secp256k1_verify(message_hash, signature, public_key)
+    }
+
+    fn secp256k1_recover_pubkey(
+        &self,
+        message_hash: &[u8],
+        signature: &[u8],
+        recovery_param: u8,
+    ) -> Result<Vec<u8>, RecoverPubkeyError> {
+        self.0
+            .secp256k1_recover_pubkey(message_hash, signature, recovery_param)
+    }
+
+    fn ed25519_verify(
+        &self,
+        message: &[u8],
+        signature: &[u8],
+        public_key: &[u8],
+    ) -> Result<bool, VerificationError> {
+        self.0.ed25519_verify(message, signature, public_key)
+    }
+
+    fn ed25519_batch_verify(
+        &self,
+        messages: &[&[u8]],
+        signatures: &[&[u8]],
+        public_keys: &[&[u8]],
+    ) -> Result<bool, VerificationError> {
+        self.0
+            .ed25519_batch_verify(messages, signatures, public_keys)
+    }
+
+    fn debug(&self, message: &str) {
+        self.0.debug(message)
+    }
+}
+
+#[cw_serde]
+struct CodeInfo {
+    code_id: u64,
+    creator: String,
+    checksum: HexBinary,
+}
+
+fn instantiate_factory() -> (OwnedDeps<MockStorage, FactoryApi, MockQuerier>, Env) {
+    let mut querier = MockQuerier::default();
+    querier.update_wasm(|query| match query {
+        WasmQuery::CodeInfo { code_id } => SystemResult::Ok(ContractResult::Ok(
+            to_binary(&CodeInfo {
+                code_id: *code_id,
+                creator: "vlad".to_string(),
+                checksum: HexBinary::from(vec![*code_id as u8; 32]),
+            })
+            .unwrap(),
+        )),
+        _ => SystemResult::Err(SystemError::Unknown {}),
+    });
+    let mut deps = OwnedDeps {
+        storage: MockStorage::default(),
+        api: FactoryApi::default(),
+        querier,
+        custom_query_type: PhantomData,
+    };
+    let env = mock_env();
+
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("vlad", &[]),
+        FactoryInstantiateMsg {
+            owner: "vlad".to_string(),
+            vesting_code_id: VESTING_CODE_ID,
+        },
+    )
+    .unwrap();
+
+    (deps, env)
+}
+
+fn vesting_instantiate_msg(owner: &str, recipients: &[&str]) -> InstantiateMsg {
+    InstantiateMsg {
+        owner: owner.to_string(),
+        guardian: None,
+        recipients: recipients
+            .iter()
+            .enumerate()
+            .map(|(i, recipient)| {
+                // the first recipient takes the rounding dust of the shares
+                let share = 10_000 / recipients.len() as u16;
+                let dust = if i == 0 {
+                    10_000 % recipients.len() as u16
+                } else {
+                    0
+                };
+                (recipient.to_string(), share + dust)
+            })
+            .collect(),
+        vesting_asset: None,
+        withdrawal_delay: None,
+        max_withdraw_per_period: None,
+        ibc_payout: None,
+        unlocked_amount: Uint128::new(1_000),
+        cliff_amount: Uint128::new(1_000),
+        vesting_amount: Uint128::new(1_000),
+        start_time: None,
+        end_time: Uint64::new(2_000_000_000),
+        require_full_funding: None,
+        governance_owner: None,
+        liquidity_guard: None,
+    }
+}
+
+fn create_vesting(
+    deps: &mut OwnedDeps<MockStorage, FactoryApi, MockQuerier>,
+    env: &Env,
+    salt: &str,
+    owner: &str,
+    recipients: &[&str],
+) -> Result<CosmosMsg, ContractError> {
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("vlad", &coins(3_000, "uluna")),
+        FactoryExecuteMsg::CreateVesting(Box::new(CreateVestingMsg {
+            label: format!("vesting {}", salt),
+            salt: Binary::from(salt.as_bytes()),
+            admin: Some("vlad".to_string()),
+            instantiate_msg: vesting_instantiate_msg(owner, recipients),
+        })),
+    )?;
+    Ok(res.messages[0].msg.clone())
+}
+
+fn query_instances(
+    deps: &OwnedDeps<MockStorage, FactoryApi, MockQuerier>,
+    env: &Env,
+    msg: FactoryQueryMsg,
+) -> Vec<VestingInstance> {
+    let res: InstancesResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), msg).unwrap()).unwrap();
+    res.instances
+}
+
+#[test]
+fn test_create_vesting() {
+    let (mut deps, env) = instantiate_factory();
+
+    let res: InstanceAddressResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            FactoryQueryMsg::QueryInstanceAddress {
+                salt: Binary::from(b"grant-1".to_vec()),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+
+    let msg = create_vesting(&mut deps, &env, "grant-1", "vlad", &["javier"]).unwrap();
+    assert_eq!(
+        msg,
+        CosmosMsg::Wasm(WasmMsg::Instantiate2 {
+            admin: Some("vlad".to_string()),
+            code_id: VESTING_CODE_ID,
+            label: "vesting grant-1".to_string(),
+            msg: to_binary(&vesting_instantiate_msg("vlad", &["javier"])).unwrap(),
+            funds: coins(3_000, "uluna"),
+            salt: Binary::from(b"grant-1".to_vec()),
+        })
+    );
+
+    // the instance is registered at the predicted address
+    let instances = query_instances(
+        &deps,
+        &env,
+        FactoryQueryMsg::QueryInstancesByRecipient {
+            recipient: "javier".to_string(),
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(
+        instances,
+        vec![VestingInstance {
+            address: res.address,
+            owner: Addr::unchecked("vlad"),
+            recipients: vec![Addr::unchecked("javier")],
+            code_id: VESTING_CODE_ID,
+            label: "vesting grant-1".to_string(),
+            created_at: Uint64::new(env.block.time.seconds()),
+        }]
+    );
+
+    // a salt can only be used once per code id
+    let err = create_vesting(&mut deps, &env, "grant-1", "vlad", &["javier"]).unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::ValidationError("Salt was already used".to_string())
+    );
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("vlad", &[]),
+        FactoryExecuteMsg::UpdateCodeId(UpdateCodeIdMsg {
+            vesting_code_id: VESTING_CODE_ID + 1,
+        }),
+    )
+    .unwrap();
+    let config: FactoryConfig =
+        from_binary(&query(deps.as_ref(), env.clone(), FactoryQueryMsg::QueryConfig).unwrap())
+            .unwrap();
+    assert_eq!(config.vesting_code_id, VESTING_CODE_ID + 1);
+    create_vesting(&mut deps, &env, "grant-1", "vlad", &["javier"]).unwrap();
+}
+
+#[test]
+fn test_create_vesting_unauthorized() {
+    let (mut deps, env) = instantiate_factory();
+
+    let err = execute(
+        deps.as_mut(),
+        env,
+        mock_info("javier", &[]),
+        FactoryExecuteMsg::CreateVesting(Box::new(CreateVestingMsg {
+            label: "vesting".to_string(),
+            salt: Binary::from(b"grant-1".to_vec()),
+            admin: None,
+            instantiate_msg: vesting_instantiate_msg("javier", &["javier"]),
+        })),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+}
+
+#[test]
+fn test_query_instances_paginated() {
+    let (mut deps, env) = instantiate_factory();
+    create_vesting(&mut deps, &env, "grant-1", "vlad", &["javier", "rando"]).unwrap();
+    create_vesting(&mut deps, &env, "grant-2", "vlad", &["javier"]).unwrap();
+    create_vesting(&mut deps, &env, "grant-3", "warp", &["rando"]).unwrap();
+
+    let all = query_instances(
+        &deps,
+        &env,
+        FactoryQueryMsg::QueryInstances {
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(all.len(), 3);
+
+    let by_owner = query_instances(
+        &deps,
+        &env,
+        FactoryQueryMsg::QueryInstancesByOwner {
+            owner: "vlad".to_string(),
+            start_after: None,
+            limit: None,
+        },
+    );
+    assert_eq!(by_owner.len(), 2);
+    assert!(by_owner
+        .iter()
+        .all(|instance| instance.owner == Addr::unchecked("vlad")));
+
+    let first = query_instances(
+        &deps,
+        &env,
+        FactoryQueryMsg::QueryInstancesByRecipient {
+            recipient: "rando".to_string(),
+            start_after: None,
+            limit: Some(1),
+        },
+    );
+    let second = query_instances(
+        &deps,
+        &env,
+        FactoryQueryMsg::QueryInstancesByRecipient {
+            recipient: "rando".to_string(),
+            start_after: Some(first[0].address.to_string()),
+            limit: Some(1),
+        },
+    );
+    assert_eq!(first.len(), 1);
+    assert_eq!(second.len(), 1);
+    assert_ne!(first[0].address, second[0].address);
+    let mut labels = vec![first[0].label.clone(), second[0].label.clone()];
+    labels.sort();
+    assert_eq!(labels, vec!["vesting grant-1", "vesting grant-3"]);
+
+    let second_page = query_instances(
+        &deps,
+        &env,
+        FactoryQueryMsg::QueryInstancesByOwner {
+            owner: "vlad".to_string(),
+            start_after: Some(by_owner[0].address.to_string()),
+            limit: None,
+        },
+    );
+    assert_eq!(second_page, vec![by_owner[1].clone()]);
+}
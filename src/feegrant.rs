@@ -0,0 +1,120 @@
+// protobuf encoding of the feegrant module messages and queries, sent as stargate requests
+use crate::proto::{decode_bytes, encode_bytes};
+use cosmwasm_std::{
+    to_vec, Addr, Binary, Coin, ContractResult, CosmosMsg, Empty, QuerierWrapper, QueryRequest,
+    StdError, StdResult, SystemResult, Uint128,
+};
+
+pub const MSG_GRANT_ALLOWANCE: &str = "/cosmos.feegrant.v1beta1.MsgGrantAllowance";
+pub const MSG_REVOKE_ALLOWANCE: &str = "/cosmos.feegrant.v1beta1.MsgRevokeAllowance";
+pub const QUERY_ALLOWANCE: &str = "/cosmos.feegrant.v1beta1.Query/Allowance";
+const BASIC_ALLOWANCE: &str = "/cosmos.feegrant.v1beta1.BasicAllowance";
+const ALLOWED_MSG_ALLOWANCE: &str = "/cosmos.feegrant.v1beta1.AllowedMsgAllowance";
+// keepers only need fees to execute contracts
+const MSG_EXECUTE_CONTRACT: &str = "/cosmwasm.wasm.v1.MsgExecuteContract";
+
+/// Grants `grantee` fees up to `spend_limit`, paid by `granter` and limited to contract executions
+pub fn grant_allowance_msg(granter: &Addr, grantee: &Addr, spend_limit: &Coin) -> CosmosMsg {
+    // BasicAllowance { spend_limit: [Coin { denom, amount }] } without expiration
+    let mut coin = vec![];
+    encode_bytes(1, spend_limit.denom.as_bytes(), &mut coin);
+    encode_bytes(2, spend_limit.amount.to_string().as_bytes(), &mut coin);
+    let mut basic = vec![];
+    encode_bytes(1, &coin, &mut basic);
+
+    // AllowedMsgAllowance { allowance, allowed_messages }
+    let mut allowed = vec![];
+    encode_bytes(1, &encode_any(BASIC_ALLOWANCE, &basic), &mut allowed);
+    encode_bytes(2, MSG_EXECUTE_CONTRACT.as_bytes(), &mut allowed);
+
+    let mut msg = vec![];
+    encode_bytes(1, granter.as_bytes(), &mut msg);
+    encode_bytes(2, grantee.as_bytes(), &mut msg);
+    encode_bytes(3, &encode_any(ALLOWED_MSG_ALLOWANCE, &allowed), &mut msg);
+    CosmosMsg::Stargate {
+        type_url: MSG_GRANT_ALLOWANCE.to_string(),
+        value: Binary::from(msg),
+    }
+}
+
+/// Revokes the allowance `granter` gave to `grantee`
+pub fn revoke_allowance_msg(granter: &Addr, grantee: &Addr) -> CosmosMsg {
+    let mut msg = vec![];
+    encode_bytes(1, granter.as_bytes(), &mut msg);
+    encode_bytes(2, grantee.as_bytes(), &mut msg);
+    CosmosMsg::Stargate {
+        type_url: MSG_REVOKE_ALLOWANCE.to_string(),
+        value: Binary::from(msg),
+    }
+}
+
+/// Unspent part in `denom` of the allowance `granter` gave to `grantee`, none when there is no
+/// allowance, which the feegrant module also deletes once its spend limit is used up
+pub fn query_remaining_allowance(
+    querier: &QuerierWrapper,
+    granter: &Addr,
+    grantee: &Addr,
+    denom: &str,
+) -> StdResult<Option<Uint128>> {
+    // QueryAllowanceRequest { granter, grantee }
+    let mut data = vec![];
+    encode_bytes(1, granter.as_bytes(), &mut data);
+    encode_bytes(2, grantee.as_bytes(), &mut data);
+    let request = to_vec(&QueryRequest::<Empty>::Stargate {
+        path: QUERY_ALLOWANCE.to_string(),
+        data: Binary::from(data),
+    })?;
+    match querier.raw_query(&request) {
+        SystemResult::Err(err) => Err(StdError::generic_err(format!(
+            "Querier system error: {}",
+            err
+        ))),
+        // the module answers with a not found error for a missing allowance
+        SystemResult::Ok(ContractResult::Err(_)) => Ok(None),
+        SystemResult::Ok(ContractResult::Ok(response)) => {
+            remaining_spend_limit(&response, denom).map(Some)
+        }
+    }
+}
+
+// QueryAllowanceResponse { allowance: Grant { granter, grantee, allowance: Any } }, with the
+// BasicAllowance granted by the contract wrapped in an AllowedMsgAllowance
+fn remaining_spend_limit(response: &[u8], denom: &str) -> StdResult<Uint128> {
+    let grant = single_field(response, 1)?;
+    let mut allowance = decode_any(single_field(grant, 3)?)?;
+    if allowance.0 == ALLOWED_MSG_ALLOWANCE {
+        allowance = decode_any(single_field(allowance.1, 1)?)?;
+    }
+    if allowance.0 != BASIC_ALLOWANCE {
+        return Err(StdError::parse_err(
+            allowance.0,
+            "unsupported fee allowance",
+        ));
+    }
+    for coin in decode_bytes(allowance.1, 1)? {
+        if single_field(coin, 1)? == denom.as_bytes() {
+            let amount = String::from_utf8(single_field(coin, 2)?.to_vec())?;
+            return amount.parse();
+        }
+    }
+    Ok(Uint128::zero())
+}
+
+// type url and value of an Any
+fn decode_any(any: &[u8]) -> StdResult<(String, &[u8])> {
+    let type_url = String::from_utf8(single_field(any, 1)?.to_vec())?;
+    Ok((type_url, single_field(any, 2)?))
+}
+
+fn single_field(buf: &[u8], field: u64) -> StdResult<&[u8]> {
+    decode_bytes(buf, field)?
+        .pop()
+        .ok_or_else(|| StdError::parse_err("protobuf", format!("missing field {}", field)))
+}
+
+fn encode_any(type_url: &str, value: &[u8]) -> Vec<u8> {
+    let mut any = vec![];
+    encode_bytes(1, type_url.as_bytes(), &mut any);
+    encode_bytes(2, value, &mut any);
+    any
+}
@@ -0,0 +1,428 @@
+// typed client of a deployed vesting contract, for other contracts and off-chain tooling
+use crate::{
+    AddToWhitelistMsg, AmendScheduleMsg, ApproveAllMsg, ApproveMsg, ApprovedDestinationsResponse,
+    ConfigResponse, Cw20HookMsg, DelegateFundsMsg, DestinationMsg, ExecuteMsg, FeeAllowanceMsg,
+    FeeGrantsResponse, FundingResponse, PausedResponse, PendingWithdrawalMsg,
+    PendingWithdrawalsResponse, PrepareLiquidityMsg, QueryMsg, RedelegateFundsMsg,
+    RemoveFromWhitelistMsg, RevokeAllMsg, RevokeFeeAllowanceMsg, RevokeMsg, SendNftMsg,
+    SolvencyResponse, StateResponse, SummaryResponse, Tranche, TransferNftMsg, UndelegateFundsMsg,
+    UpdateGuardianMsg, UpdateIbcPayoutMsg, UpdateLiquidityGuardMsg, UpdateOwnerMsg,
+    UpdateRecipientMsg, UpdateWithdrawalDelayMsg, VestingPosition, WithdrawDelegatorRewardMsg,
+    WithdrawVestedFundsMsg, WithdrawableResponse, WithdrawalHistoryResponse,
+};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{
+    to_binary, Addr, Coin, CustomQuery, QuerierWrapper, StdResult, Uint128, WasmMsg,
+};
+use cw20::Cw20ExecuteMsg;
+use cw721::{
+    AllNftInfoResponse, ApprovalResponse, ApprovalsResponse, ContractInfoResponse, NftInfoResponse,
+    NumTokensResponse, OperatorsResponse, OwnerOfResponse, TokensResponse,
+};
+
+/// Address of a community pool vesting contract, building its execute messages and queries
+#[cw_serde]
+pub struct CommunityPoolVestingContract(pub Addr);
+
+impl CommunityPoolVestingContract {
+    pub fn addr(&self) -> Addr {
+        self.0.clone()
+    }
+
+    /// Executes `msg` on the contract with `funds` attached
+    pub fn call_with_funds<T: Into<ExecuteMsg>>(
+        &self,
+        msg: T,
+        funds: Vec<Coin>,
+    ) -> StdResult<WasmMsg> {
+        Ok(WasmMsg::Execute {
+            contract_addr: self.addr().into(),
+            msg: to_binary(&msg.into())?,
+            funds,
+        })
+    }
+
+    /// Executes `msg` on the contract without funds
+    pub fn call<T: Into<ExecuteMsg>>(&self, msg: T) -> StdResult<WasmMsg> {
+        self.call_with_funds(msg, vec![])
+    }
+
+    pub fn withdraw_unlocked_funds(&self, msg: WithdrawVestedFundsMsg) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::WithdrawUnlockedFunds(msg))
+    }
+
+    pub fn withdraw_vested_funds(&self, msg: WithdrawVestedFundsMsg) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::WithdrawVestedFunds(msg))
+    }
+
+    pub fn withdraw_cliff_vested_funds(&self, msg: WithdrawVestedFundsMsg) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::WithdrawCliffVestedFunds(msg))
+    }
+
+    pub fn withdraw_delegator_reward(&self, msg: WithdrawDelegatorRewardMsg) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::WithdrawDelegatorReward(msg))
+    }
+
+    pub fn delegate_funds(&self, msg: DelegateFundsMsg) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::DelegateFunds(msg))
+    }
+
+    pub fn undelegate_funds(&self, msg: UndelegateFundsMsg) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::UndelegateFunds(msg))
+    }
+
+    pub fn redelegate_funds(&self, msg: RedelegateFundsMsg) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::RedelegateFunds(msg))
+    }
+
+    pub fn add_to_whitelist(&self, msg: AddToWhitelistMsg) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::AddToWhitelist(msg))
+    }
+
+    pub fn remove_from_whitelist(&self, msg: RemoveFromWhitelistMsg) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::RemoveFromWhitelist(msg))
+    }
+
+    pub fn update_owner(&self, msg: UpdateOwnerMsg) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::UpdateOwner(msg))
+    }
+
+    pub fn update_recipient(&self, msg: UpdateRecipientMsg) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::UpdateRecipient(msg))
+    }
+
+    /// Funds the contract with the native vesting asset
+    pub fn fund(&self, funds: Vec<Coin>) -> StdResult<WasmMsg> {
+        self.call_with_funds(ExecuteMsg::Fund {}, funds)
+    }
+
+    /// Funds the contract with a cw20 vesting asset, executed on the `token` contract as the
+    /// contract only accepts the `Receive` message from the token itself
+    pub fn fund_cw20(&self, token: &Addr, amount: Uint128) -> StdResult<WasmMsg> {
+        Ok(WasmMsg::Execute {
+            contract_addr: token.into(),
+            msg: to_binary(&Cw20ExecuteMsg::Send {
+                contract: self.addr().into(),
+                amount,
+                msg: to_binary(&Cw20HookMsg::Fund {})?,
+            })?,
+            funds: vec![],
+        })
+    }
+
+    pub fn amend_schedule(&self, msg: AmendScheduleMsg) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::AmendSchedule(msg))
+    }
+
+    pub fn pause(&self) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::Pause {})
+    }
+
+    pub fn unpause(&self) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::Unpause {})
+    }
+
+    pub fn update_guardian(&self, msg: UpdateGuardianMsg) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::UpdateGuardian(msg))
+    }
+
+    pub fn finalize_withdrawal(&self, msg: PendingWithdrawalMsg) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::FinalizeWithdrawal(msg))
+    }
+
+    pub fn cancel_withdrawal(&self, msg: PendingWithdrawalMsg) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::CancelWithdrawal(msg))
+    }
+
+    pub fn update_withdrawal_delay(&self, msg: UpdateWithdrawalDelayMsg) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::UpdateWithdrawalDelay(msg))
+    }
+
+    pub fn update_liquidity_guard(&self, msg: UpdateLiquidityGuardMsg) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::UpdateLiquidityGuard(msg))
+    }
+
+    pub fn prepare_liquidity(&self, msg: PrepareLiquidityMsg) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::PrepareLiquidity(msg))
+    }
+
+    pub fn approve_destination(&self, msg: DestinationMsg) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::ApproveDestination(msg))
+    }
+
+    pub fn revoke_destination(&self, msg: DestinationMsg) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::RevokeDestination(msg))
+    }
+
+    pub fn update_ibc_payout(&self, msg: UpdateIbcPayoutMsg) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::UpdateIbcPayout(msg))
+    }
+
+    pub fn transfer_nft(&self, msg: TransferNftMsg) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::TransferNft(msg))
+    }
+
+    pub fn send_nft(&self, msg: SendNftMsg) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::SendNft(msg))
+    }
+
+    pub fn approve(&self, msg: ApproveMsg) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::Approve(msg))
+    }
+
+    pub fn revoke(&self, msg: RevokeMsg) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::Revoke(msg))
+    }
+
+    pub fn approve_all(&self, msg: ApproveAllMsg) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::ApproveAll(msg))
+    }
+
+    pub fn revoke_all(&self, msg: RevokeAllMsg) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::RevokeAll(msg))
+    }
+
+    pub fn top_up_fee_budget(&self, funds: Vec<Coin>) -> StdResult<WasmMsg> {
+        self.call_with_funds(ExecuteMsg::TopUpFeeBudget {}, funds)
+    }
+
+    pub fn grant_fee_allowance(&self, msg: FeeAllowanceMsg) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::GrantFeeAllowance(msg))
+    }
+
+    pub fn revoke_fee_allowance(&self, msg: RevokeFeeAllowanceMsg) -> StdResult<WasmMsg> {
+        self.call(ExecuteMsg::RevokeFeeAllowance(msg))
+    }
+
+    pub fn config<C: CustomQuery>(&self, querier: &QuerierWrapper<C>) -> StdResult<ConfigResponse> {
+        querier.query_wasm_smart(self.addr(), &QueryMsg::QueryConfig)
+    }
+
+    pub fn state<C: CustomQuery>(&self, querier: &QuerierWrapper<C>) -> StdResult<StateResponse> {
+        querier.query_wasm_smart(self.addr(), &QueryMsg::QueryState)
+    }
+
+    pub fn funding<C: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<C>,
+    ) -> StdResult<FundingResponse> {
+        querier.query_wasm_smart(self.addr(), &QueryMsg::QueryFunding)
+    }
+
+    pub fn paused<C: CustomQuery>(&self, querier: &QuerierWrapper<C>) -> StdResult<bool> {
+        let res: PausedResponse = querier.query_wasm_smart(self.addr(), &QueryMsg::QueryPaused)?;
+        Ok(res.paused)
+    }
+
+    pub fn pending_withdrawals<C: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<C>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    ) -> StdResult<PendingWithdrawalsResponse> {
+        querier.query_wasm_smart(
+            self.addr(),
+            &QueryMsg::QueryPendingWithdrawals { start_after, limit },
+        )
+    }
+
+    pub fn withdrawable<C: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<C>,
+    ) -> StdResult<WithdrawableResponse> {
+        querier.query_wasm_smart(self.addr(), &QueryMsg::QueryWithdrawable)
+    }
+
+    pub fn approved_destinations<C: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<C>,
+        recipient: impl Into<String>,
+    ) -> StdResult<ApprovedDestinationsResponse> {
+        querier.query_wasm_smart(
+            self.addr(),
+            &QueryMsg::QueryApprovedDestinations {
+                recipient: recipient.into(),
+            },
+        )
+    }
+
+    pub fn withdrawal_history<C: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<C>,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+        tranche: Option<Tranche>,
+        sender: Option<String>,
+    ) -> StdResult<WithdrawalHistoryResponse> {
+        querier.query_wasm_smart(
+            self.addr(),
+            &QueryMsg::QueryWithdrawalHistory {
+                start_after,
+                limit,
+                tranche,
+                sender,
+            },
+        )
+    }
+
+    pub fn fee_grants<C: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<C>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<FeeGrantsResponse> {
+        querier.query_wasm_smart(
+            self.addr(),
+            &QueryMsg::QueryFeeGrants { start_after, limit },
+        )
+    }
+
+    pub fn summary<C: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<C>,
+    ) -> StdResult<SummaryResponse> {
+        querier.query_wasm_smart(self.addr(), &QueryMsg::QuerySummary)
+    }
+
+    pub fn solvency<C: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<C>,
+    ) -> StdResult<SolvencyResponse> {
+        querier.query_wasm_smart(self.addr(), &QueryMsg::QuerySolvency)
+    }
+
+    /// Holder of the recipient position `token_id`
+    pub fn owner_of<C: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<C>,
+        token_id: impl Into<String>,
+    ) -> StdResult<OwnerOfResponse> {
+        querier.query_wasm_smart(
+            self.addr(),
+            &QueryMsg::OwnerOf {
+                token_id: token_id.into(),
+                include_expired: None,
+            },
+        )
+    }
+
+    /// Approval of `spender` on the recipient position `token_id`
+    pub fn approval<C: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<C>,
+        token_id: impl Into<String>,
+        spender: impl Into<String>,
+        include_expired: Option<bool>,
+    ) -> StdResult<ApprovalResponse> {
+        querier.query_wasm_smart(
+            self.addr(),
+            &QueryMsg::Approval {
+                token_id: token_id.into(),
+                spender: spender.into(),
+                include_expired,
+            },
+        )
+    }
+
+    pub fn approvals<C: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<C>,
+        token_id: impl Into<String>,
+        include_expired: Option<bool>,
+    ) -> StdResult<ApprovalsResponse> {
+        querier.query_wasm_smart(
+            self.addr(),
+            &QueryMsg::Approvals {
+                token_id: token_id.into(),
+                include_expired,
+            },
+        )
+    }
+
+    /// Operators approved by `owner` for all of its positions
+    pub fn all_operators<C: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<C>,
+        owner: impl Into<String>,
+        include_expired: Option<bool>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<OperatorsResponse> {
+        querier.query_wasm_smart(
+            self.addr(),
+            &QueryMsg::AllOperators {
+                owner: owner.into(),
+                include_expired,
+                start_after,
+                limit,
+            },
+        )
+    }
+
+    pub fn nft_info<C: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<C>,
+        token_id: impl Into<String>,
+    ) -> StdResult<NftInfoResponse<VestingPosition>> {
+        querier.query_wasm_smart(
+            self.addr(),
+            &QueryMsg::NftInfo {
+                token_id: token_id.into(),
+            },
+        )
+    }
+
+    pub fn all_nft_info<C: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<C>,
+        token_id: impl Into<String>,
+        include_expired: Option<bool>,
+    ) -> StdResult<AllNftInfoResponse<VestingPosition>> {
+        querier.query_wasm_smart(
+            self.addr(),
+            &QueryMsg::AllNftInfo {
+                token_id: token_id.into(),
+                include_expired,
+            },
+        )
+    }
+
+    pub fn tokens<C: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<C>,
+        owner: impl Into<String>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<TokensResponse> {
+        querier.query_wasm_smart(
+            self.addr(),
+            &QueryMsg::Tokens {
+                owner: owner.into(),
+                start_after,
+                limit,
+            },
+        )
+    }
+
+    pub fn all_tokens<C: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<C>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    ) -> StdResult<TokensResponse> {
+        querier.query_wasm_smart(self.addr(), &QueryMsg::AllTokens { start_after, limit })
+    }
+
+    pub fn num_tokens<C: CustomQuery>(&self, querier: &QuerierWrapper<C>) -> StdResult<u64> {
+        let res: NumTokensResponse =
+            querier.query_wasm_smart(self.addr(), &QueryMsg::NumTokens {})?;
+        Ok(res.count)
+    }
+
+    pub fn contract_info<C: CustomQuery>(
+        &self,
+        querier: &QuerierWrapper<C>,
+    ) -> StdResult<ContractInfoResponse> {
+        querier.query_wasm_smart(self.addr(), &QueryMsg::ContractInfo {})
+    }
+}
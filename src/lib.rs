@@ -1,25 +1,77 @@
 pub mod contract;
 mod error;
+pub mod events;
+#[cfg(feature = "factory")]
+pub mod factory;
+pub mod feegrant;
+pub mod helpers;
+mod proto;
 pub mod state;
+pub mod transfer;
+pub mod vesting;
 
+#[cfg(all(test, feature = "factory"))]
+mod factory_tests;
+#[cfg(test)]
+mod multitest;
 #[cfg(test)]
 mod tests;
+#[cfg(test)]
+mod vesting_tests;
 
 pub use crate::error::ContractError;
 
-use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Coin, Uint128, Uint64};
+use crate::contract::{GOVERNANCE_MODULE, MAX_VESTING_DURATION};
+
+use cosmwasm_schema::{cw_serde, QueryResponses};
+use cosmwasm_std::{Addr, Binary, Coin, Decimal, Uint128, Uint64};
+use cw20::Cw20ReceiveMsg;
+use cw721::{
+    AllNftInfoResponse, ApprovalResponse, ApprovalsResponse, ContractInfoResponse, Expiration,
+    NftInfoResponse, NumTokensResponse, OperatorsResponse, OwnerOfResponse, TokensResponse,
+};
+use cw_asset::{AssetInfo, AssetInfoUnchecked};
+use std::fmt;
 
 #[cw_serde]
 pub struct Config {
     pub owner: Addr,
-    pub recipient: Addr,
+    pub guardian: Option<Addr>,
+    /// Payout addresses with their share in basis points, summing to 10 000
+    pub recipients: Vec<(Addr, u16)>,
+    pub vesting_asset: AssetInfo,
+    pub withdrawal_delay: Option<Uint64>,
+    pub max_withdraw_per_period: Option<WithdrawPeriodLimit>,
+    pub ibc_payout: Option<IbcPayout>,
     pub unlocked_amount: Uint128,
     pub cliff_amount: Uint128,
     pub vesting_amount: Uint128,
     pub start_time: Uint64,
     pub end_time: Uint64,
     pub whitelisted_addresses: Vec<Addr>,
+    /// Owner is fixed to the governance module account
+    #[serde(default)]
+    pub governance_owner: bool,
+    /// Reject delegations leaving less liquid funds than the vested amount not withdrawn yet
+    #[serde(default)]
+    pub liquidity_guard: bool,
+}
+
+#[cw_serde]
+pub struct WithdrawPeriodLimit {
+    pub amount: Uint128,
+    pub period_seconds: Uint64,
+}
+
+#[cw_serde]
+pub struct IbcPayout {
+    /// Recipient whose share is paid out on the remote chain
+    pub recipient: String,
+    pub channel_id: String,
+    /// Address on the remote chain receiving the payouts
+    pub remote_address: String,
+    /// Seconds after which an unreceived transfer times out
+    pub timeout_seconds: Uint64,
 }
 
 #[cw_serde]
@@ -27,17 +79,120 @@ pub struct State {
     pub last_withdrawn_time: Uint64,
     pub cliff_amount_withdrawn: Uint128,
     pub unlocked_amount_withdrawn: Uint128,
+    /// Withdrawals of the vesting asset within the last `max_withdraw_per_period` window
+    #[serde(default)]
+    pub recent_withdrawals: Vec<WithdrawalRecord>,
+}
+
+#[cw_serde]
+pub struct WithdrawalRecord {
+    pub time: Uint64,
+    pub amount: Uint128,
 }
 
 #[cw_serde]
 pub struct InstantiateMsg {
     pub owner: String,
-    pub recipient: String,
+    /// Address allowed to pause and unpause the contract next to the owner
+    pub guardian: Option<String>,
+    /// Payout addresses with their share in basis points, summing to 10 000
+    pub recipients: Vec<(String, u16)>,
+    /// Asset subject to the vesting schedule, defaults to native `uluna`
+    pub vesting_asset: Option<AssetInfoUnchecked>,
+    /// Seconds a withdrawal of the vesting asset is queued for before it can be finalized
+    pub withdrawal_delay: Option<Uint64>,
+    /// Maximum amount of the vesting asset that can be withdrawn within a rolling period
+    pub max_withdraw_per_period: Option<WithdrawPeriodLimit>,
+    /// Pays out the native vesting asset over IBC instead of to the recipients
+    pub ibc_payout: Option<IbcPayout>,
     pub unlocked_amount: Uint128,
     pub cliff_amount: Uint128,
     pub vesting_amount: Uint128,
     pub start_time: Option<Uint64>,
     pub end_time: Uint64,
+    /// Require the instantiate funds to cover the full schedule
+    pub require_full_funding: Option<bool>,
+    /// Only accept owner actions from the governance module account, which must then be the `owner`
+    pub governance_owner: Option<bool>,
+    /// Reject delegations leaving less liquid funds than the vested amount not withdrawn yet
+    pub liquidity_guard: Option<bool>,
+}
+
+impl InstantiateMsg {
+    /// Rejects degenerate schedules, `now` is the block time the schedule starts at by default
+    pub fn validate(&self, now: u64) -> Result<(), ContractError> {
+        let start_time = self.start_time.map_or(now, |time| time.u64());
+        if start_time < now {
+            return Err(ContractError::ValidationError(
+                "Start time cannot be in the past".to_string(),
+            ));
+        }
+        validate_schedule(
+            start_time,
+            self.end_time.u64(),
+            self.unlocked_amount,
+            self.cliff_amount,
+            self.vesting_amount,
+        )?;
+
+        if let Some(limit) = &self.max_withdraw_per_period {
+            if limit.amount.is_zero() || limit.period_seconds.is_zero() {
+                return Err(ContractError::ValidationError(
+                    "Withdraw limit amount and period must be positive".to_string(),
+                ));
+            }
+        }
+        if self
+            .recipients
+            .iter()
+            .any(|(recipient, _)| *recipient == self.owner)
+        {
+            return Err(ContractError::ValidationError(
+                "Owner cannot be a recipient".to_string(),
+            ));
+        }
+        if self.governance_owner.unwrap_or(false) && self.owner != GOVERNANCE_MODULE {
+            return Err(ContractError::ValidationError(
+                "Owner must be the governance module account".to_string(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Rejects schedules vesting at once, longer than 100 years or whose total is zero or does not fit
+/// in a `Uint128`, shared by the instantiation and schedule amendments
+pub fn validate_schedule(
+    start_time: u64,
+    end_time: u64,
+    unlocked_amount: Uint128,
+    cliff_amount: Uint128,
+    vesting_amount: Uint128,
+) -> Result<(), ContractError> {
+    // a schedule without duration would vest the whole amount at once
+    if end_time <= start_time {
+        return Err(ContractError::ValidationError(
+            "End time must be after start time".to_string(),
+        ));
+    }
+    if end_time - start_time > MAX_VESTING_DURATION {
+        return Err(ContractError::ValidationError(
+            "Vesting duration cannot exceed 100 years".to_string(),
+        ));
+    }
+
+    let total_amount = unlocked_amount
+        .checked_add(cliff_amount)
+        .and_then(|amount| amount.checked_add(vesting_amount))
+        .map_err(|_| {
+            ContractError::ValidationError("Total amount overflows a Uint128".to_string())
+        })?;
+    if total_amount.is_zero() {
+        return Err(ContractError::ValidationError(
+            "Schedule amounts cannot all be zero".to_string(),
+        ));
+    }
+    Ok(())
 }
 
 #[cw_serde]
@@ -53,11 +208,43 @@ pub enum ExecuteMsg {
     RemoveFromWhitelist(RemoveFromWhitelistMsg),
     UpdateOwner(UpdateOwnerMsg),
     UpdateRecipient(UpdateRecipientMsg),
+    Receive(Cw20ReceiveMsg),
+    Fund {},
+    AmendSchedule(AmendScheduleMsg),
+    Pause {},
+    Unpause {},
+    UpdateGuardian(UpdateGuardianMsg),
+    FinalizeWithdrawal(PendingWithdrawalMsg),
+    CancelWithdrawal(PendingWithdrawalMsg),
+    UpdateWithdrawalDelay(UpdateWithdrawalDelayMsg),
+    UpdateLiquidityGuard(UpdateLiquidityGuardMsg),
+    PrepareLiquidity(PrepareLiquidityMsg),
+    ApproveDestination(DestinationMsg),
+    RevokeDestination(DestinationMsg),
+    UpdateIbcPayout(UpdateIbcPayoutMsg),
+    TransferNft(TransferNftMsg),
+    SendNft(SendNftMsg),
+    Approve(ApproveMsg),
+    Revoke(RevokeMsg),
+    ApproveAll(ApproveAllMsg),
+    RevokeAll(RevokeAllMsg),
+    TopUpFeeBudget {},
+    GrantFeeAllowance(FeeAllowanceMsg),
+    RevokeFeeAllowance(RevokeFeeAllowanceMsg),
+}
+
+#[cw_serde]
+pub enum Cw20HookMsg {
+    Fund {},
 }
 
 #[cw_serde]
 pub struct WithdrawVestedFundsMsg {
-    pub denom: String,
+    pub asset_info: AssetInfoUnchecked,
+    /// Address receiving the payout instead of the recipients, must be approved by every recipient
+    pub destination: Option<String>,
+    /// Message executed on the destination with the payout attached
+    pub msg: Option<Binary>,
 }
 #[cw_serde]
 pub struct WithdrawDelegatorRewardMsg {
@@ -100,11 +287,449 @@ pub struct UpdateOwnerMsg {
 
 #[cw_serde]
 pub struct UpdateRecipientMsg {
+    pub recipients: Vec<(String, u16)>,
+}
+
+#[cw_serde]
+pub struct MigrateMsg {
+    /// Enables or disables the governance owner mode, enabling it makes the governance module the owner
+    pub governance_owner: Option<bool>,
+}
+
+#[cw_serde]
+pub enum SudoMsg {
+    /// Outcome of an IBC transfer, in the format of the ibc-hooks middleware
+    IbcLifecycleComplete(IbcLifecycleComplete),
+    /// Emergency actions of chain governance
+    Pause {},
+    Unpause {},
+    UpdateRecipient(UpdateRecipientMsg),
+    Clawback(ClawbackMsg),
+}
+
+#[cw_serde]
+pub struct ClawbackMsg {
+    pub destination: String,
+}
+
+#[cw_serde]
+pub enum IbcLifecycleComplete {
+    IbcAck {
+        channel: String,
+        sequence: u64,
+        ack: String,
+        success: bool,
+    },
+    IbcTimeout {
+        channel: String,
+        sequence: u64,
+    },
+}
+
+#[cw_serde]
+pub struct UpdateGuardianMsg {
+    pub guardian: Option<String>,
+}
+
+#[cw_serde]
+pub struct PendingWithdrawalMsg {
+    pub id: u64,
+}
+
+#[cw_serde]
+pub struct UpdateWithdrawalDelayMsg {
+    pub withdrawal_delay: Option<Uint64>,
+}
+
+#[cw_serde]
+pub struct UpdateLiquidityGuardMsg {
+    pub liquidity_guard: bool,
+}
+
+#[cw_serde]
+pub struct PrepareLiquidityMsg {
+    /// Undelegate enough to cover what vests within this many seconds
+    pub horizon_seconds: Uint64,
+}
+
+#[cw_serde]
+pub struct UpdateIbcPayoutMsg {
+    pub ibc_payout: Option<IbcPayout>,
+}
+
+#[cw_serde]
+pub struct DestinationMsg {
+    pub destination: String,
+    /// Hook message approved along with the destination, ignored when revoking
+    pub msg: Option<Binary>,
+}
+
+#[cw_serde]
+pub struct FeeAllowanceMsg {
+    /// Whitelisted address paying its transaction fees from the allowance
+    pub grantee: String,
+    /// Drawn from the fee budget, replaces the current allowance of the grantee
+    pub spend_limit: Uint128,
+}
+
+#[cw_serde]
+pub struct RevokeFeeAllowanceMsg {
+    pub grantee: String,
+}
+
+#[cw_serde]
+pub struct TransferNftMsg {
     pub recipient: String,
+    pub token_id: String,
 }
 
 #[cw_serde]
+pub struct SendNftMsg {
+    pub contract: String,
+    pub token_id: String,
+    pub msg: Binary,
+}
+
+#[cw_serde]
+pub struct ApproveMsg {
+    pub spender: String,
+    pub token_id: String,
+    pub expires: Option<Expiration>,
+}
+
+#[cw_serde]
+pub struct RevokeMsg {
+    pub spender: String,
+    pub token_id: String,
+}
+
+#[cw_serde]
+pub struct ApproveAllMsg {
+    pub operator: String,
+    pub expires: Option<Expiration>,
+}
+
+#[cw_serde]
+pub struct RevokeAllMsg {
+    pub operator: String,
+}
+
+#[cw_serde]
+pub struct AmendScheduleMsg {
+    pub unlocked_amount: Option<Uint128>,
+    pub cliff_amount: Option<Uint128>,
+    pub vesting_amount: Option<Uint128>,
+    pub end_time: Option<Uint64>,
+}
+
+#[cw_serde]
+#[derive(QueryResponses)]
 pub enum QueryMsg {
+    #[returns(ConfigResponse)]
     QueryConfig,
+    #[returns(StateResponse)]
     QueryState,
+    #[returns(FundingResponse)]
+    QueryFunding,
+    #[returns(PausedResponse)]
+    QueryPaused,
+    #[returns(PendingWithdrawalsResponse)]
+    QueryPendingWithdrawals {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    #[returns(WithdrawableResponse)]
+    QueryWithdrawable,
+    #[returns(ApprovedDestinationsResponse)]
+    QueryApprovedDestinations { recipient: String },
+    #[returns(WithdrawalHistoryResponse)]
+    QueryWithdrawalHistory {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+        tranche: Option<Tranche>,
+        sender: Option<String>,
+    },
+    #[returns(FeeGrantsResponse)]
+    QueryFeeGrants {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    // cw721 queries, with one token per recipient position
+    #[returns(OwnerOfResponse)]
+    OwnerOf {
+        token_id: String,
+        include_expired: Option<bool>,
+    },
+    #[returns(ApprovalResponse)]
+    Approval {
+        token_id: String,
+        spender: String,
+        include_expired: Option<bool>,
+    },
+    #[returns(ApprovalsResponse)]
+    Approvals {
+        token_id: String,
+        include_expired: Option<bool>,
+    },
+    #[returns(OperatorsResponse)]
+    AllOperators {
+        owner: String,
+        include_expired: Option<bool>,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(NftInfoResponse<VestingPosition>)]
+    NftInfo { token_id: String },
+    #[returns(AllNftInfoResponse<VestingPosition>)]
+    AllNftInfo {
+        token_id: String,
+        include_expired: Option<bool>,
+    },
+    #[returns(TokensResponse)]
+    Tokens {
+        owner: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(TokensResponse)]
+    AllTokens {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(NumTokensResponse)]
+    NumTokens {},
+    #[returns(ContractInfoResponse)]
+    ContractInfo {},
+    #[returns(SummaryResponse)]
+    QuerySummary,
+    #[returns(SolvencyResponse)]
+    QuerySolvency,
+}
+
+#[cw_serde]
+pub struct ConfigResponse {
+    pub owner: Addr,
+    pub guardian: Option<Addr>,
+    /// Payout addresses with their share in basis points, summing to 10 000
+    pub recipients: Vec<(Addr, u16)>,
+    pub vesting_asset: AssetInfo,
+    pub withdrawal_delay: Option<Uint64>,
+    pub max_withdraw_per_period: Option<WithdrawPeriodLimit>,
+    pub ibc_payout: Option<IbcPayout>,
+    pub unlocked_amount: Uint128,
+    pub cliff_amount: Uint128,
+    pub vesting_amount: Uint128,
+    pub start_time: Uint64,
+    pub end_time: Uint64,
+    pub whitelisted_addresses: Vec<Addr>,
+    pub governance_owner: bool,
+    pub liquidity_guard: bool,
+}
+
+impl From<Config> for ConfigResponse {
+    fn from(config: Config) -> Self {
+        ConfigResponse {
+            owner: config.owner,
+            guardian: config.guardian,
+            recipients: config.recipients,
+            vesting_asset: config.vesting_asset,
+            withdrawal_delay: config.withdrawal_delay,
+            max_withdraw_per_period: config.max_withdraw_per_period,
+            ibc_payout: config.ibc_payout,
+            unlocked_amount: config.unlocked_amount,
+            cliff_amount: config.cliff_amount,
+            vesting_amount: config.vesting_amount,
+            start_time: config.start_time,
+            end_time: config.end_time,
+            whitelisted_addresses: config.whitelisted_addresses,
+            governance_owner: config.governance_owner,
+            liquidity_guard: config.liquidity_guard,
+        }
+    }
+}
+
+#[cw_serde]
+pub struct StateResponse {
+    pub last_withdrawn_time: Uint64,
+    pub cliff_amount_withdrawn: Uint128,
+    pub unlocked_amount_withdrawn: Uint128,
+    /// Withdrawals of the vesting asset within the last `max_withdraw_per_period` window
+    pub recent_withdrawals: Vec<WithdrawalRecord>,
+}
+
+impl From<State> for StateResponse {
+    fn from(state: State) -> Self {
+        StateResponse {
+            last_withdrawn_time: state.last_withdrawn_time,
+            cliff_amount_withdrawn: state.cliff_amount_withdrawn,
+            unlocked_amount_withdrawn: state.unlocked_amount_withdrawn,
+            recent_withdrawals: state.recent_withdrawals,
+        }
+    }
+}
+
+#[cw_serde]
+pub struct WithdrawableResponse {
+    pub unlocked_amount: Uint128,
+    pub cliff_amount: Uint128,
+    pub vesting_amount: Uint128,
+    /// Time at which the withdrawable vesting amount next increases, none once fully vested
+    pub next_unlock_time: Option<Uint64>,
+    /// Amount that can still be withdrawn in the current period, if a limit is configured
+    pub period_allowance: Option<Uint128>,
+}
+
+#[cw_serde]
+pub struct SolvencyResponse {
+    /// Part of the schedule that has not been withdrawn yet
+    pub total_obligations: Uint128,
+    /// Vested part of the obligations, withdrawable as soon as the liquid balance covers it
+    pub withdrawable_obligations: Uint128,
+    /// Balance of the vesting asset not reserved for queued withdrawals or fees
+    pub liquid_balance: Uint128,
+    pub delegated_balance: Uint128,
+    pub unbonding_balance: Uint128,
+    /// Withdrawable obligations the liquid balance does not cover
+    pub liquid_shortfall: Uint128,
+    /// Obligations the liquid, delegated and unbonding balances do not cover
+    pub shortfall: Uint128,
+}
+
+/// Everything a dashboard shows about the contract, in a single query
+#[cw_serde]
+pub struct SummaryResponse {
+    pub vesting_asset: AssetInfo,
+    pub unlocked_amount: Uint128,
+    pub cliff_amount: Uint128,
+    pub vesting_amount: Uint128,
+    pub start_time: Uint64,
+    pub end_time: Uint64,
+    /// Percentage of `vesting_amount` vested so far, from 0 to 100
+    pub percent_vested: Decimal,
+    pub unlocked_amount_withdrawn: Uint128,
+    pub cliff_amount_withdrawn: Uint128,
+    pub vesting_amount_withdrawn: Uint128,
+    /// Amounts withdrawable now, with the next unlock time of the vesting tranche
+    pub withdrawable: WithdrawableResponse,
+    pub paused: bool,
+    /// Balance of the vesting asset held by the contract
+    pub liquid_balance: Uint128,
+    pub delegated_balance: Uint128,
+    /// Staking rewards accrued on all delegations and not withdrawn yet
+    pub pending_rewards: Vec<Coin>,
+    pub unbonding_balance: Uint128,
+}
+
+#[cw_serde]
+pub enum Tranche {
+    Unlocked,
+    Cliff,
+    Vesting,
+}
+
+impl fmt::Display for Tranche {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Tranche::Unlocked => write!(f, "unlocked"),
+            Tranche::Cliff => write!(f, "cliff"),
+            Tranche::Vesting => write!(f, "vesting"),
+        }
+    }
+}
+
+#[cw_serde]
+pub struct PendingWithdrawal {
+    pub id: u64,
+    pub tranche: Tranche,
+    pub asset_info: AssetInfo,
+    pub amount: Uint128,
+    pub release_time: Uint64,
+    pub destination: Option<PayoutDestination>,
+    /// Time of the withdrawal, counted against the withdraw limit of its period
+    #[serde(default)]
+    pub withdrawn_at: Uint64,
+}
+
+#[cw_serde]
+pub struct IbcTransfer {
+    pub channel_id: String,
+    pub tranche: Tranche,
+    pub amount: Uint128,
+    /// Withdrawal history entry of the transfer, pointed at the local recipient if the transfer fails
+    #[serde(default)]
+    pub history_id: Option<u64>,
+    /// Recipient whose share is transferred, paid locally if the transfer fails
+    pub recipient: Addr,
+}
+
+#[cw_serde]
+pub struct PayoutDestination {
+    pub address: Addr,
+    pub msg: Option<Binary>,
+}
+
+#[cw_serde]
+pub struct PendingWithdrawalsResponse {
+    pub withdrawals: Vec<PendingWithdrawal>,
+}
+
+#[cw_serde]
+pub struct WithdrawalHistoryEntry {
+    pub id: u64,
+    pub tranche: Tranche,
+    /// Native denom or CW20 contract address of the asset paid out
+    pub denom: String,
+    pub amount: Uint128,
+    /// Address that executed the payout
+    pub sender: Addr,
+    /// Recipient, approved destination or remote IBC address that received the payout
+    pub recipient: String,
+    pub time: Uint64,
+}
+
+#[cw_serde]
+pub struct WithdrawalHistoryResponse {
+    pub withdrawals: Vec<WithdrawalHistoryEntry>,
+}
+
+#[cw_serde]
+pub struct FeeGrant {
+    pub grantee: Addr,
+    pub spend_limit: Uint128,
+}
+
+#[cw_serde]
+pub struct FeeGrantsResponse {
+    /// Fee funds reserved by the owner and not granted yet
+    pub budget: Option<Coin>,
+    pub grants: Vec<FeeGrant>,
+}
+
+/// Extension of the cw721 `NftInfo` response
+#[cw_serde]
+pub struct VestingPosition {
+    pub share_bps: u16,
+}
+
+#[cw_serde]
+pub struct ApprovedDestinationsResponse {
+    pub destinations: Vec<PayoutDestination>,
+}
+
+#[cw_serde]
+pub struct PausedResponse {
+    pub paused: bool,
+}
+
+#[cw_serde]
+pub struct FundingResponse {
+    /// Part of the schedule that has not been withdrawn yet
+    pub total_obligations: Uint128,
+    /// Sum of all deposits recorded by the contract
+    pub total_funded: Uint128,
+    pub liquid_balance: Uint128,
+    pub delegated_balance: Uint128,
+    pub unbonding_balance: Uint128,
+    pub total_holdings: Uint128,
+    pub shortfall: Uint128,
 }
@@ -0,0 +1,1091 @@
+use crate::contract::{execute, instantiate, query};
+use crate::helpers::CommunityPoolVestingContract;
+use crate::vesting::{vested_at, Schedule};
+use crate::{
+    AddToWhitelistMsg, AmendScheduleMsg, ApproveAllMsg, ApproveMsg, ConfigResponse, ContractError,
+    Cw20HookMsg, DelegateFundsMsg, DestinationMsg, ExecuteMsg, FeeAllowanceMsg, FundingResponse,
+    IbcPayout, InstantiateMsg, PendingWithdrawalMsg, PendingWithdrawalsResponse,
+    PrepareLiquidityMsg, QueryMsg, RedelegateFundsMsg, RemoveFromWhitelistMsg, RevokeAllMsg,
+    RevokeFeeAllowanceMsg, RevokeMsg, SendNftMsg, TransferNftMsg, UndelegateFundsMsg,
+    UpdateGuardianMsg, UpdateIbcPayoutMsg, UpdateLiquidityGuardMsg, UpdateOwnerMsg,
+    UpdateRecipientMsg, UpdateWithdrawalDelayMsg, WithdrawDelegatorRewardMsg,
+    WithdrawVestedFundsMsg, WithdrawableResponse, WithdrawalHistoryEntry,
+    WithdrawalHistoryResponse,
+};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_schema::schemars::JsonSchema;
+use cosmwasm_schema::serde::de::DeserializeOwned;
+use cosmwasm_std::testing::mock_env;
+use cosmwasm_std::{
+    coin, coins, to_binary, Addr, Api, BankMsg, BankQuery, Binary, BlockInfo, Coin, CustomQuery,
+    Decimal, Empty, Querier, Storage, Uint128, Uint64, Validator,
+};
+use cw20::{BalanceResponse, Cw20Coin, Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg};
+use cw721::Expiration;
+use cw_asset::AssetInfoUnchecked;
+use cw_multi_test::{
+    AppBuilder, AppResponse, Bank, BankKeeper, BankSudo, Contract, ContractWrapper, CosmosRouter,
+    Executor, Module, StakingInfo, StakingSudo, SudoMsg,
+};
+use proptest::prelude::*;
+use proptest::test_runner::{RngAlgorithm, TestRng, TestRunner};
+
+const OWNER: &str = "owner";
+const RECIPIENT: &str = "recipient";
+// receives positions and guardianship in the simulation
+const OTHER: &str = "other";
+// approved payout destination in the simulation, not a recipient
+const DESTINATION: &str = "destination";
+const VALIDATOR: &str = "validator";
+const OTHER_VALIDATOR: &str = "other_validator";
+
+const UNLOCKED_AMOUNT: u128 = 1_000_000;
+const CLIFF_AMOUNT: u128 = 2_000_000;
+const VESTING_AMOUNT: u128 = 10_000_000;
+const VESTING_DURATION: u64 = 1_000;
+
+const YEAR_IN_SECONDS: u64 = 365 * 24 * 60 * 60;
+const UNBONDING_PERIOD: u64 = 21 * 24 * 60 * 60;
+
+type App = cw_multi_test::App<SupplyBank>;
+
+// the multi-test bank does not answer supply queries, which instantiate needs for a native asset
+#[derive(Default)]
+struct SupplyBank(BankKeeper);
+
+// SupplyResponse is non exhaustive
+#[cw_serde]
+struct SupplyResponse {
+    amount: Coin,
+}
+
+impl Bank for SupplyBank {}
+
+impl Module for SupplyBank {
+    type ExecT = BankMsg;
+    type QueryT = BankQuery;
+    type SudoT = BankSudo;
+
+    fn execute<ExecC, QueryC>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        sender: Addr,
+        msg: BankMsg,
+    ) -> anyhow::Result<AppResponse>
+    where
+        ExecC: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        self.0.execute(api, storage, router, block, sender, msg)
+    }
+
+    fn sudo<ExecC, QueryC>(
+        &self,
+        api: &dyn Api,
+        storage: &mut dyn Storage,
+        router: &dyn CosmosRouter<ExecC = ExecC, QueryC = QueryC>,
+        block: &BlockInfo,
+        msg: BankSudo,
+    ) -> anyhow::Result<AppResponse>
+    where
+        ExecC: std::fmt::Debug + Clone + PartialEq + JsonSchema + DeserializeOwned + 'static,
+        QueryC: CustomQuery + DeserializeOwned + 'static,
+    {
+        self.0.sudo(api, storage, router, block, msg)
+    }
+
+    fn query(
+        &self,
+        api: &dyn Api,
+        storage: &dyn Storage,
+        querier: &dyn Querier,
+        block: &BlockInfo,
+        request: BankQuery,
+    ) -> anyhow::Result<Binary> {
+        match request {
+            // the uluna staking_app mints to the owner, which covers the schedule
+            BankQuery::Supply { denom } => Ok(to_binary(&SupplyResponse {
+                amount: coin(UNLOCKED_AMOUNT + CLIFF_AMOUNT + VESTING_AMOUNT, denom),
+            })?),
+            request => self.0.query(api, storage, querier, block, request),
+        }
+    }
+}
+
+fn mock_app() -> App {
+    AppBuilder::new()
+        .with_bank(SupplyBank::default())
+        .build(|_, _, _| {})
+}
+
+fn vesting_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(execute, instantiate, query))
+}
+
+fn cw20_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(
+        cw20_base::contract::execute,
+        cw20_base::contract::instantiate,
+        cw20_base::contract::query,
+    ))
+}
+
+fn instantiate_cw20(app: &mut App, symbol: &str) -> Addr {
+    let code_id = app.store_code(cw20_contract());
+    app.instantiate_contract(
+        code_id,
+        Addr::unchecked(OWNER),
+        &cw20_base::msg::InstantiateMsg {
+            name: "Vesting Token".to_string(),
+            symbol: symbol.to_string(),
+            decimals: 6,
+            initial_balances: vec![Cw20Coin {
+                address: OWNER.to_string(),
+                amount: Uint128::new(UNLOCKED_AMOUNT + CLIFF_AMOUNT + VESTING_AMOUNT),
+            }],
+            mint: None,
+            marketing: None,
+        },
+        &[],
+        symbol,
+        None,
+    )
+    .unwrap()
+}
+
+fn instantiate_vesting(app: &mut App, token: &Addr) -> Addr {
+    let code_id = app.store_code(vesting_contract());
+    let start_time = app.block_info().time.seconds();
+    app.instantiate_contract(
+        code_id,
+        Addr::unchecked(OWNER),
+        &InstantiateMsg {
+            owner: OWNER.to_string(),
+            guardian: None,
+            recipients: vec![(RECIPIENT.to_string(), 10_000)],
+            vesting_asset: Some(AssetInfoUnchecked::cw20(token.to_string())),
+            withdrawal_delay: None,
+            max_withdraw_per_period: None,
+            ibc_payout: None,
+            unlocked_amount: Uint128::new(UNLOCKED_AMOUNT),
+            cliff_amount: Uint128::new(CLIFF_AMOUNT),
+            vesting_amount: Uint128::new(VESTING_AMOUNT),
+            start_time: None,
+            end_time: Uint64::new(start_time + VESTING_DURATION),
+            require_full_funding: None,
+            governance_owner: None,
+            liquidity_guard: None,
+        },
+        &[],
+        "community-pool-vesting",
+        None,
+    )
+    .unwrap()
+}
+
+// app with the owner holding the whole schedule in uluna, staked at 10% apr without commission
+fn staking_app() -> App {
+    AppBuilder::new()
+        .with_bank(SupplyBank::default())
+        .build(|router, api, storage| {
+            router
+                .bank
+                .0
+                .init_balance(
+                    storage,
+                    &Addr::unchecked(OWNER),
+                    coins(UNLOCKED_AMOUNT + CLIFF_AMOUNT + VESTING_AMOUNT, "uluna"),
+                )
+                .unwrap();
+            router
+                .staking
+                .setup(
+                    storage,
+                    StakingInfo {
+                        bonded_denom: "uluna".to_string(),
+                        unbonding_time: UNBONDING_PERIOD,
+                        apr: Decimal::percent(10),
+                    },
+                )
+                .unwrap();
+            for validator in [VALIDATOR, OTHER_VALIDATOR] {
+                router
+                    .staking
+                    .add_validator(
+                        api,
+                        storage,
+                        &mock_env().block,
+                        Validator {
+                            address: validator.to_string(),
+                            commission: Decimal::zero(),
+                            max_commission: Decimal::one(),
+                            max_change_rate: Decimal::one(),
+                        },
+                    )
+                    .unwrap();
+            }
+        })
+}
+
+// native uluna schedule vesting over a year
+fn instantiate_native_vesting(app: &mut App) -> Addr {
+    let code_id = app.store_code(vesting_contract());
+    let start_time = app.block_info().time.seconds();
+    app.instantiate_contract(
+        code_id,
+        Addr::unchecked(OWNER),
+        &InstantiateMsg {
+            owner: OWNER.to_string(),
+            guardian: None,
+            recipients: vec![(RECIPIENT.to_string(), 10_000)],
+            vesting_asset: None,
+            withdrawal_delay: None,
+            max_withdraw_per_period: None,
+            ibc_payout: None,
+            unlocked_amount: Uint128::new(UNLOCKED_AMOUNT),
+            cliff_amount: Uint128::new(CLIFF_AMOUNT),
+            vesting_amount: Uint128::new(VESTING_AMOUNT),
+            start_time: None,
+            end_time: Uint64::new(start_time + YEAR_IN_SECONDS),
+            require_full_funding: None,
+            governance_owner: None,
+            liquidity_guard: None,
+        },
+        &[],
+        "community-pool-vesting",
+        None,
+    )
+    .unwrap()
+}
+
+fn execute_as_owner(app: &mut App, vesting: &Addr, msg: &ExecuteMsg) {
+    app.execute_contract(Addr::unchecked(OWNER), vesting.clone(), msg, &[])
+        .unwrap();
+}
+
+fn query_uluna_balance(app: &App, address: &str) -> Uint128 {
+    app.wrap().query_balance(address, "uluna").unwrap().amount
+}
+
+// rewards the contract accrued on the validator, as reported by the distribution module
+fn query_rewards(app: &App, vesting: &Addr, validator: &str) -> Uint128 {
+    app.wrap()
+        .query_delegation(vesting, validator)
+        .unwrap()
+        .map(|delegation| {
+            delegation
+                .accumulated_rewards
+                .iter()
+                .filter(|reward| reward.denom == "uluna")
+                .map(|reward| reward.amount)
+                .sum()
+        })
+        .unwrap_or_default()
+}
+
+fn advance_time(app: &mut App, seconds: u64) {
+    app.update_block(|block| {
+        block.time = block.time.plus_seconds(seconds);
+        block.height += seconds / 5;
+    });
+}
+
+fn fund_vesting(app: &mut App, token: &Addr, vesting: &Addr, amount: u128) -> anyhow::Result<()> {
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        token.clone(),
+        &Cw20ExecuteMsg::Send {
+            contract: vesting.to_string(),
+            amount: Uint128::new(amount),
+            msg: to_binary(&Cw20HookMsg::Fund {}).unwrap(),
+        },
+        &[],
+    )?;
+    Ok(())
+}
+
+fn query_cw20_balance(app: &App, token: &Addr, address: &str) -> Uint128 {
+    let res: BalanceResponse = app
+        .wrap()
+        .query_wasm_smart(
+            token,
+            &Cw20QueryMsg::Balance {
+                address: address.to_string(),
+            },
+        )
+        .unwrap();
+    res.balance
+}
+
+fn withdraw(
+    app: &mut App,
+    vesting: &Addr,
+    token: &Addr,
+    msg: fn(WithdrawVestedFundsMsg) -> ExecuteMsg,
+) {
+    app.execute_contract(
+        Addr::unchecked(RECIPIENT),
+        vesting.clone(),
+        &msg(WithdrawVestedFundsMsg {
+            asset_info: AssetInfoUnchecked::cw20(token.to_string()),
+            destination: None,
+            msg: None,
+        }),
+        &[],
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_cw20_fund_and_withdraw_all_tranches() {
+    let mut app = mock_app();
+    let token = instantiate_cw20(&mut app, "VEST");
+    let vesting = instantiate_vesting(&mut app, &token);
+
+    fund_vesting(
+        &mut app,
+        &token,
+        &vesting,
+        UNLOCKED_AMOUNT + CLIFF_AMOUNT + VESTING_AMOUNT,
+    )
+    .unwrap();
+    assert_eq!(
+        query_cw20_balance(&app, &token, vesting.as_str()),
+        Uint128::new(UNLOCKED_AMOUNT + CLIFF_AMOUNT + VESTING_AMOUNT)
+    );
+    let funding: FundingResponse = app
+        .wrap()
+        .query_wasm_smart(&vesting, &QueryMsg::QueryFunding {})
+        .unwrap();
+    assert_eq!(
+        funding.total_funded,
+        Uint128::new(UNLOCKED_AMOUNT + CLIFF_AMOUNT + VESTING_AMOUNT)
+    );
+    assert_eq!(funding.shortfall, Uint128::zero());
+
+    withdraw(
+        &mut app,
+        &vesting,
+        &token,
+        ExecuteMsg::WithdrawUnlockedFunds,
+    );
+    assert_eq!(
+        query_cw20_balance(&app, &token, RECIPIENT),
+        Uint128::new(UNLOCKED_AMOUNT)
+    );
+
+    withdraw(
+        &mut app,
+        &vesting,
+        &token,
+        ExecuteMsg::WithdrawCliffVestedFunds,
+    );
+    assert_eq!(
+        query_cw20_balance(&app, &token, RECIPIENT),
+        Uint128::new(UNLOCKED_AMOUNT + CLIFF_AMOUNT)
+    );
+
+    // half way through the vesting period
+    app.update_block(|block| block.time = block.time.plus_seconds(VESTING_DURATION / 2));
+    withdraw(&mut app, &vesting, &token, ExecuteMsg::WithdrawVestedFunds);
+    assert_eq!(
+        query_cw20_balance(&app, &token, RECIPIENT),
+        Uint128::new(UNLOCKED_AMOUNT + CLIFF_AMOUNT + VESTING_AMOUNT / 2)
+    );
+
+    // past the end of the vesting period
+    app.update_block(|block| block.time = block.time.plus_seconds(VESTING_DURATION));
+    withdraw(&mut app, &vesting, &token, ExecuteMsg::WithdrawVestedFunds);
+    assert_eq!(
+        query_cw20_balance(&app, &token, RECIPIENT),
+        Uint128::new(UNLOCKED_AMOUNT + CLIFF_AMOUNT + VESTING_AMOUNT)
+    );
+    assert_eq!(
+        query_cw20_balance(&app, &token, vesting.as_str()),
+        Uint128::zero()
+    );
+}
+
+#[test]
+fn test_cw20_receive_unsupported_token() {
+    let mut app = mock_app();
+    let token = instantiate_cw20(&mut app, "VEST");
+    let other_token = instantiate_cw20(&mut app, "OTHER");
+    let vesting = instantiate_vesting(&mut app, &token);
+
+    let err = fund_vesting(&mut app, &other_token, &vesting, UNLOCKED_AMOUNT).unwrap_err();
+    assert_eq!(
+        err.root_cause().to_string(),
+        ContractError::UnsupportedAsset {}.to_string()
+    );
+    assert_eq!(
+        query_cw20_balance(&app, &other_token, vesting.as_str()),
+        Uint128::zero()
+    );
+}
+
+#[test]
+fn test_native_lifecycle_with_staking() {
+    let mut app = staking_app();
+    let vesting = instantiate_native_vesting(&mut app);
+    let total = UNLOCKED_AMOUNT + CLIFF_AMOUNT + VESTING_AMOUNT;
+    let asset_info = AssetInfoUnchecked::native("uluna");
+
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        vesting.clone(),
+        &ExecuteMsg::Fund {},
+        &coins(total, "uluna"),
+    )
+    .unwrap();
+    assert_eq!(query_uluna_balance(&app, OWNER), Uint128::zero());
+    assert_eq!(
+        query_uluna_balance(&app, vesting.as_str()),
+        Uint128::new(total)
+    );
+
+    execute_as_owner(
+        &mut app,
+        &vesting,
+        &ExecuteMsg::DelegateFunds(DelegateFundsMsg {
+            validator: VALIDATOR.to_string(),
+            amount: coin(5_000_000, "uluna"),
+        }),
+    );
+    assert_eq!(
+        query_uluna_balance(&app, vesting.as_str()),
+        Uint128::new(total - 5_000_000)
+    );
+
+    let withdraw_uluna = |app: &mut App, msg: fn(WithdrawVestedFundsMsg) -> ExecuteMsg| {
+        app.execute_contract(
+            Addr::unchecked(RECIPIENT),
+            vesting.clone(),
+            &msg(WithdrawVestedFundsMsg {
+                asset_info: asset_info.clone(),
+                destination: None,
+                msg: None,
+            }),
+            &[],
+        )
+        .unwrap();
+    };
+    withdraw_uluna(&mut app, ExecuteMsg::WithdrawUnlockedFunds);
+    withdraw_uluna(&mut app, ExecuteMsg::WithdrawCliffVestedFunds);
+    assert_eq!(
+        query_uluna_balance(&app, RECIPIENT),
+        Uint128::new(UNLOCKED_AMOUNT + CLIFF_AMOUNT)
+    );
+    assert_eq!(
+        query_uluna_balance(&app, vesting.as_str()),
+        Uint128::new(5_000_000)
+    );
+
+    // a quarter of a year at 10% on 5m, forwarded to the recipient when redelegating
+    advance_time(&mut app, YEAR_IN_SECONDS / 4);
+    assert_eq!(
+        query_rewards(&app, &vesting, VALIDATOR),
+        Uint128::new(125_000)
+    );
+    execute_as_owner(
+        &mut app,
+        &vesting,
+        &ExecuteMsg::RedelegateFunds(RedelegateFundsMsg {
+            src_validator: VALIDATOR.to_string(),
+            dst_validator: OTHER_VALIDATOR.to_string(),
+            amount: coin(2_000_000, "uluna"),
+        }),
+    );
+    assert_eq!(
+        query_uluna_balance(&app, RECIPIENT),
+        Uint128::new(UNLOCKED_AMOUNT + CLIFF_AMOUNT + 125_000)
+    );
+    let delegation = app
+        .wrap()
+        .query_delegation(&vesting, OTHER_VALIDATOR)
+        .unwrap()
+        .unwrap();
+    assert_eq!(delegation.amount, coin(2_000_000, "uluna"));
+
+    // half way through the schedule, rewards of both validators are claimed
+    advance_time(&mut app, YEAR_IN_SECONDS / 4);
+    assert_eq!(
+        query_rewards(&app, &vesting, VALIDATOR),
+        Uint128::new(75_000)
+    );
+    assert_eq!(
+        query_rewards(&app, &vesting, OTHER_VALIDATOR),
+        Uint128::new(50_000)
+    );
+    for validator in [VALIDATOR, OTHER_VALIDATOR] {
+        execute_as_owner(
+            &mut app,
+            &vesting,
+            &ExecuteMsg::WithdrawDelegatorReward(WithdrawDelegatorRewardMsg {
+                validator: validator.to_string(),
+            }),
+        );
+    }
+    let rewards = 125_000 + 75_000 + 50_000;
+    assert_eq!(
+        query_uluna_balance(&app, RECIPIENT),
+        Uint128::new(UNLOCKED_AMOUNT + CLIFF_AMOUNT + rewards)
+    );
+
+    // the liquid balance covers the half of the vesting amount vested so far
+    withdraw_uluna(&mut app, ExecuteMsg::WithdrawVestedFunds);
+    assert_eq!(
+        query_uluna_balance(&app, RECIPIENT),
+        Uint128::new(UNLOCKED_AMOUNT + CLIFF_AMOUNT + VESTING_AMOUNT / 2 + rewards)
+    );
+    assert_eq!(query_uluna_balance(&app, vesting.as_str()), Uint128::zero());
+
+    // nothing accrued since the claim, so nothing is forwarded when undelegating
+    for (validator, amount) in [(VALIDATOR, 3_000_000), (OTHER_VALIDATOR, 2_000_000)] {
+        execute_as_owner(
+            &mut app,
+            &vesting,
+            &ExecuteMsg::UndelegateFunds(UndelegateFundsMsg {
+                validator: validator.to_string(),
+                amount: coin(amount, "uluna"),
+            }),
+        );
+    }
+    assert_eq!(query_uluna_balance(&app, vesting.as_str()), Uint128::zero());
+
+    advance_time(&mut app, UNBONDING_PERIOD);
+    app.sudo(SudoMsg::Staking(StakingSudo::ProcessQueue {}))
+        .unwrap();
+    assert_eq!(
+        query_uluna_balance(&app, vesting.as_str()),
+        Uint128::new(5_000_000)
+    );
+
+    // past the end of the schedule the rest is withdrawn and nothing is left behind
+    advance_time(&mut app, YEAR_IN_SECONDS);
+    withdraw_uluna(&mut app, ExecuteMsg::WithdrawVestedFunds);
+    assert_eq!(
+        query_uluna_balance(&app, RECIPIENT),
+        Uint128::new(total + rewards)
+    );
+    assert_eq!(query_uluna_balance(&app, vesting.as_str()), Uint128::zero());
+    assert_eq!(query_uluna_balance(&app, OWNER), Uint128::zero());
+}
+
+#[test]
+fn test_helpers() {
+    let mut app = staking_app();
+    let vesting = CommunityPoolVestingContract(instantiate_native_vesting(&mut app));
+    let total = UNLOCKED_AMOUNT + CLIFF_AMOUNT + VESTING_AMOUNT;
+
+    app.execute(
+        Addr::unchecked(OWNER),
+        vesting.fund(coins(total, "uluna")).unwrap().into(),
+    )
+    .unwrap();
+    app.execute(
+        Addr::unchecked(OWNER),
+        vesting
+            .delegate_funds(DelegateFundsMsg {
+                validator: VALIDATOR.to_string(),
+                amount: coin(5_000_000, "uluna"),
+            })
+            .unwrap()
+            .into(),
+    )
+    .unwrap();
+    app.execute(
+        Addr::unchecked(RECIPIENT),
+        vesting
+            .withdraw_unlocked_funds(WithdrawVestedFundsMsg {
+                asset_info: AssetInfoUnchecked::native("uluna"),
+                destination: None,
+                msg: None,
+            })
+            .unwrap()
+            .into(),
+    )
+    .unwrap();
+
+    let querier = app.wrap();
+    let config = vesting.config(&querier).unwrap();
+    assert_eq!(config.owner, Addr::unchecked(OWNER));
+    assert_eq!(
+        config.recipients,
+        vec![(Addr::unchecked(RECIPIENT), 10_000)]
+    );
+    let state = vesting.state(&querier).unwrap();
+    assert_eq!(
+        state.unlocked_amount_withdrawn,
+        Uint128::new(UNLOCKED_AMOUNT)
+    );
+    assert_eq!(
+        vesting.funding(&querier).unwrap().total_funded,
+        Uint128::new(total)
+    );
+    assert!(!vesting.paused(&querier).unwrap());
+    assert_eq!(
+        vesting.withdrawable(&querier).unwrap().cliff_amount,
+        Uint128::new(CLIFF_AMOUNT)
+    );
+    assert_eq!(vesting.num_tokens(&querier).unwrap(), 1);
+    assert_eq!(
+        vesting.owner_of(&querier, "0").unwrap().owner,
+        RECIPIENT.to_string()
+    );
+
+    let recipient = Addr::unchecked(RECIPIENT);
+    let approve = vesting
+        .approve(ApproveMsg {
+            spender: OTHER.to_string(),
+            token_id: "0".to_string(),
+            expires: None,
+        })
+        .unwrap();
+    app.execute(recipient.clone(), approve.into()).unwrap();
+    let approval = vesting.approval(&app.wrap(), "0", OTHER, None).unwrap();
+    assert_eq!(approval.approval.spender, OTHER);
+    let info = vesting.all_nft_info(&app.wrap(), "0", None).unwrap();
+    assert_eq!(info.access.owner, RECIPIENT);
+    assert_eq!(info.access.approvals, vec![approval.approval]);
+    let revoke = vesting
+        .revoke(RevokeMsg {
+            spender: OTHER.to_string(),
+            token_id: "0".to_string(),
+        })
+        .unwrap();
+    app.execute(recipient.clone(), revoke.into()).unwrap();
+    let approvals = vesting.approvals(&app.wrap(), "0", None).unwrap();
+    assert!(approvals.approvals.is_empty());
+
+    let approve_all = vesting
+        .approve_all(ApproveAllMsg {
+            operator: OTHER.to_string(),
+            expires: None,
+        })
+        .unwrap();
+    app.execute(recipient.clone(), approve_all.into()).unwrap();
+    let operators = vesting
+        .all_operators(&app.wrap(), RECIPIENT, None, None, None)
+        .unwrap();
+    assert_eq!(operators.operators.len(), 1);
+    assert_eq!(operators.operators[0].spender, OTHER);
+    let revoke_all = vesting
+        .revoke_all(RevokeAllMsg {
+            operator: OTHER.to_string(),
+        })
+        .unwrap();
+    app.execute(recipient, revoke_all.into()).unwrap();
+    let operators = vesting
+        .all_operators(&app.wrap(), RECIPIENT, None, None, None)
+        .unwrap();
+    assert!(operators.operators.is_empty());
+}
+
+// senders of the simulated messages, only the owner ever sends funds
+const ACTORS: [&str; 3] = [OWNER, RECIPIENT, OTHER];
+const VALIDATORS: [&str; 2] = [VALIDATOR, OTHER_VALIDATOR];
+// funds of the owner on top of the schedule, used to fund the contract and the fee budget
+const EXTRA_FUNDS: u128 = 5_000_000;
+// number of simulated kinds of steps, every execute message plus advancing the block time
+const STEP_KINDS: u8 = 35;
+
+// builds the message of a simulated step from its kind and a random parameter, none for a time advance
+fn simulation_msg(
+    kind: u8,
+    param: u64,
+    vesting: &Addr,
+    config: &ConfigResponse,
+) -> Option<(ExecuteMsg, Vec<Coin>)> {
+    let amount = u128::from(param % 3_000_000 + 1);
+    let validator = VALIDATORS[param as usize % 2].to_string();
+    let withdraw_msg = WithdrawVestedFundsMsg {
+        asset_info: AssetInfoUnchecked::native("uluna"),
+        destination: (param % 2 == 1).then(|| DESTINATION.to_string()),
+        msg: None,
+    };
+    let msg = match kind {
+        0 => ExecuteMsg::WithdrawUnlockedFunds(withdraw_msg),
+        1 => ExecuteMsg::WithdrawVestedFunds(withdraw_msg),
+        2 => ExecuteMsg::WithdrawCliffVestedFunds(withdraw_msg),
+        3 => ExecuteMsg::WithdrawDelegatorReward(WithdrawDelegatorRewardMsg { validator }),
+        4 => ExecuteMsg::DelegateFunds(DelegateFundsMsg {
+            validator,
+            amount: coin(amount, "uluna"),
+        }),
+        5 => ExecuteMsg::UndelegateFunds(UndelegateFundsMsg {
+            validator,
+            amount: coin(amount, "uluna"),
+        }),
+        6 => ExecuteMsg::RedelegateFunds(RedelegateFundsMsg {
+            dst_validator: VALIDATORS[(param as usize + 1) % 2].to_string(),
+            src_validator: validator,
+            amount: coin(amount, "uluna"),
+        }),
+        7 => ExecuteMsg::AddToWhitelist(AddToWhitelistMsg {
+            addresses: vec![Addr::unchecked(OTHER)],
+        }),
+        8 => ExecuteMsg::RemoveFromWhitelist(RemoveFromWhitelistMsg {
+            addresses: vec![Addr::unchecked(OTHER)],
+        }),
+        9 => ExecuteMsg::UpdateOwner(UpdateOwnerMsg {
+            owner: OWNER.to_string(),
+        }),
+        10 => ExecuteMsg::UpdateRecipient(UpdateRecipientMsg {
+            recipients: match param % 3 {
+                0 => vec![(RECIPIENT.to_string(), 10_000)],
+                1 => vec![(OTHER.to_string(), 10_000)],
+                _ => vec![(RECIPIENT.to_string(), 5_000), (OTHER.to_string(), 5_000)],
+            },
+        }),
+        // only a cw20 contract can fund through the hook, sent by an account it must fail
+        11 => ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: OWNER.to_string(),
+            amount: Uint128::new(amount),
+            msg: to_binary(&Cw20HookMsg::Fund {}).unwrap(),
+        }),
+        12 => return Some((ExecuteMsg::Fund {}, coins(amount, "uluna"))),
+        13 => {
+            let increase = Uint128::new(amount);
+            ExecuteMsg::AmendSchedule(AmendScheduleMsg {
+                unlocked_amount: None,
+                cliff_amount: (param % 3 == 0).then_some(config.cliff_amount + increase),
+                vesting_amount: (param % 3 == 1).then_some(config.vesting_amount + increase),
+                end_time: (param % 3 == 2)
+                    .then_some(config.end_time + Uint64::new(param % YEAR_IN_SECONDS)),
+            })
+        }
+        14 => ExecuteMsg::Pause {},
+        15 => ExecuteMsg::Unpause {},
+        16 => ExecuteMsg::UpdateGuardian(UpdateGuardianMsg {
+            guardian: (param % 2 == 0).then(|| OTHER.to_string()),
+        }),
+        17 => ExecuteMsg::FinalizeWithdrawal(PendingWithdrawalMsg { id: param % 4 }),
+        18 => ExecuteMsg::CancelWithdrawal(PendingWithdrawalMsg { id: param % 4 }),
+        19 => ExecuteMsg::UpdateWithdrawalDelay(UpdateWithdrawalDelayMsg {
+            withdrawal_delay: (param % 3 != 0).then_some(Uint64::new(param % UNBONDING_PERIOD)),
+        }),
+        20 => ExecuteMsg::ApproveDestination(DestinationMsg {
+            destination: DESTINATION.to_string(),
+            msg: None,
+        }),
+        21 => ExecuteMsg::RevokeDestination(DestinationMsg {
+            destination: DESTINATION.to_string(),
+            msg: None,
+        }),
+        // ibc payouts cannot be delivered in the app, withdrawals paying the remote share fail
+        22 => ExecuteMsg::UpdateIbcPayout(UpdateIbcPayoutMsg {
+            ibc_payout: (param % 4 == 0).then(|| IbcPayout {
+                recipient: RECIPIENT.to_string(),
+                channel_id: "channel-0".to_string(),
+                remote_address: "remote".to_string(),
+                timeout_seconds: Uint64::new(600),
+            }),
+        }),
+        23 => ExecuteMsg::TransferNft(TransferNftMsg {
+            recipient: ACTORS[1 + param as usize % 2].to_string(),
+            token_id: (param % 2).to_string(),
+        }),
+        24 => ExecuteMsg::SendNft(SendNftMsg {
+            contract: vesting.to_string(),
+            token_id: (param % 2).to_string(),
+            msg: Binary::default(),
+        }),
+        25 => return Some((ExecuteMsg::TopUpFeeBudget {}, coins(amount, "uluna"))),
+        26 => ExecuteMsg::GrantFeeAllowance(FeeAllowanceMsg {
+            grantee: OTHER.to_string(),
+            spend_limit: Uint128::new(amount),
+        }),
+        27 => ExecuteMsg::RevokeFeeAllowance(RevokeFeeAllowanceMsg {
+            grantee: OTHER.to_string(),
+        }),
+        28 => ExecuteMsg::UpdateLiquidityGuard(UpdateLiquidityGuardMsg {
+            liquidity_guard: param % 2 == 0,
+        }),
+        29 => ExecuteMsg::PrepareLiquidity(PrepareLiquidityMsg {
+            horizon_seconds: Uint64::new(param % YEAR_IN_SECONDS),
+        }),
+        // approved spenders and operators can transfer positions, expiring during the simulation
+        30 => ExecuteMsg::Approve(ApproveMsg {
+            spender: OTHER.to_string(),
+            token_id: (param % 2).to_string(),
+            expires: (param % 3 == 0).then(|| {
+                Expiration::AtTime(mock_env().block.time.plus_seconds(param % YEAR_IN_SECONDS))
+            }),
+        }),
+        31 => ExecuteMsg::Revoke(RevokeMsg {
+            spender: OTHER.to_string(),
+            token_id: (param % 2).to_string(),
+        }),
+        32 => ExecuteMsg::ApproveAll(ApproveAllMsg {
+            operator: OTHER.to_string(),
+            expires: (param % 3 == 0).then(|| {
+                Expiration::AtTime(mock_env().block.time.plus_seconds(param % YEAR_IN_SECONDS))
+            }),
+        }),
+        33 => ExecuteMsg::RevokeAll(RevokeAllMsg {
+            operator: OTHER.to_string(),
+        }),
+        _ => return None,
+    };
+    Some((msg, vec![]))
+}
+
+// share of an address in the payouts, in basis points
+fn share_of(config: &ConfigResponse, address: &str) -> u16 {
+    config
+        .recipients
+        .iter()
+        .find(|(recipient, _)| recipient.as_str() == address)
+        .map_or(0, |(_, bps)| *bps)
+}
+
+// payouts recorded after the given history id
+fn query_history_after(
+    app: &App,
+    vesting: &Addr,
+    mut start_after: Option<u64>,
+) -> Vec<WithdrawalHistoryEntry> {
+    let mut entries = vec![];
+    loop {
+        let page: WithdrawalHistoryResponse = app
+            .wrap()
+            .query_wasm_smart(
+                vesting,
+                &QueryMsg::QueryWithdrawalHistory {
+                    start_after,
+                    limit: Some(30),
+                    tranche: None,
+                    sender: None,
+                },
+            )
+            .unwrap();
+        match page.withdrawals.last() {
+            Some(last) => start_after = Some(last.id),
+            None => return entries,
+        }
+        entries.extend(page.withdrawals);
+    }
+}
+
+// amount of the queued withdrawals, committed to the recipients but not paid yet
+fn query_pending(app: &App, vesting: &Addr) -> Uint128 {
+    let mut pending = Uint128::zero();
+    let mut start_after = None;
+    loop {
+        let page: PendingWithdrawalsResponse = app
+            .wrap()
+            .query_wasm_smart(
+                vesting,
+                &QueryMsg::QueryPendingWithdrawals {
+                    start_after,
+                    limit: Some(30),
+                },
+            )
+            .unwrap();
+        match page.withdrawals.last() {
+            Some(last) => start_after = Some(last.id),
+            None => return pending,
+        }
+        pending += page
+            .withdrawals
+            .iter()
+            .map(|withdrawal| withdrawal.amount)
+            .sum::<Uint128>();
+    }
+}
+
+// validators the contract delegates to, with the delegated amount
+fn query_delegated(app: &App, vesting: &Addr) -> Vec<(String, Uint128)> {
+    app.wrap()
+        .query_all_delegations(vesting)
+        .unwrap()
+        .into_iter()
+        .map(|delegation| (delegation.validator, delegation.amount.amount))
+        .collect()
+}
+
+fn simulate(steps: Vec<(u8, usize, u64)>) -> Result<(), TestCaseError> {
+    let mut app = staking_app();
+    let initial_funds = UNLOCKED_AMOUNT + CLIFF_AMOUNT + VESTING_AMOUNT + EXTRA_FUNDS;
+    app.init_modules(|router, _, storage| {
+        router
+            .bank
+            .0
+            .init_balance(
+                storage,
+                &Addr::unchecked(OWNER),
+                coins(initial_funds, "uluna"),
+            )
+            .unwrap()
+    });
+    let vesting = instantiate_native_vesting(&mut app);
+    app.execute_contract(
+        Addr::unchecked(OWNER),
+        vesting.clone(),
+        &ExecuteMsg::Fund {},
+        &coins(UNLOCKED_AMOUNT + CLIFF_AMOUNT + VESTING_AMOUNT, "uluna"),
+    )
+    .unwrap();
+    let start_time = app.block_info().time.seconds();
+    // payouts to each actor from the history, and the share of all payouts to recipients that
+    // the shares in effect at the time of each payout entitle it to
+    let mut paid = [Uint128::zero(); ACTORS.len()];
+    let mut paid_by_share = [Uint128::zero(); ACTORS.len()];
+    let mut paid_total = Uint128::zero();
+    let mut last_history_id = None;
+
+    for (i, (kind, sender, param)) in steps.into_iter().enumerate() {
+        let config: ConfigResponse = app
+            .wrap()
+            .query_wasm_smart(&vesting, &QueryMsg::QueryConfig)
+            .unwrap();
+        let shares = ACTORS.map(|actor| share_of(&config, actor));
+        match simulation_msg(kind, param, &vesting, &config) {
+            // most messages fail for most senders, a failed message changes nothing
+            Some((msg, funds)) => {
+                let _ = app.execute_contract(
+                    Addr::unchecked(ACTORS[sender]),
+                    vesting.clone(),
+                    &msg,
+                    &funds,
+                );
+            }
+            None => {
+                advance_time(&mut app, param % (YEAR_IN_SECONDS / 8));
+                app.sudo(SudoMsg::Staking(StakingSudo::ProcessQueue {}))
+                    .unwrap();
+            }
+        }
+
+        // recipients never receive more than the unlocked, cliff and vested amounts of the current
+        // schedule, plus the rewards of 10% apr on at most all funds, rounded up once per step
+        let config: ConfigResponse = app
+            .wrap()
+            .query_wasm_smart(&vesting, &QueryMsg::QueryConfig)
+            .unwrap();
+        let now = app.block_info().time.seconds();
+        let entitled = config.unlocked_amount
+            + config.cliff_amount
+            + vested_at(
+                &Schedule {
+                    amount: config.vesting_amount,
+                    start_time: config.start_time.u64(),
+                    end_time: config.end_time.u64(),
+                },
+                now,
+            )
+            .unwrap();
+        let rewards = Uint128::new(initial_funds)
+            .multiply_ratio(now - start_time, 10 * YEAR_IN_SECONDS)
+            + Uint128::new(i as u128 + 1);
+        let received = [RECIPIENT, OTHER, DESTINATION]
+            .iter()
+            .map(|address| query_uluna_balance(&app, address))
+            .sum::<Uint128>();
+        prop_assert!(
+            received <= entitled + rewards,
+            "recipients received {} of {} entitled and {} rewards",
+            received,
+            entitled,
+            rewards
+        );
+
+        // payouts to the destination are made on behalf of all recipients, the others are split
+        // by the shares in effect before the step, as no step both pays out and changes them
+        let entries = query_history_after(&app, &vesting, last_history_id);
+        last_history_id = entries.last().map(|entry| entry.id).or(last_history_id);
+        let mut paid_to_recipients = Uint128::zero();
+        for entry in entries {
+            paid_total += entry.amount;
+            if let Some(index) = ACTORS.iter().position(|actor| *actor == entry.recipient) {
+                paid[index] += entry.amount;
+                paid_to_recipients += entry.amount;
+            }
+        }
+        for (index, share) in shares.iter().enumerate() {
+            paid_by_share[index] += paid_to_recipients.multiply_ratio(*share, 10_000u128);
+        }
+
+        // every recipient was paid no more than its share of what has vested, counting what it
+        // can still withdraw, with the rounding dust of one payout per step
+        let unpaid = entitled
+            .checked_sub(paid_total + query_pending(&app, &vesting))
+            .map_err(|_| {
+                TestCaseError::fail(format!(
+                    "{} paid and queued of {} entitled",
+                    paid_total, entitled
+                ))
+            })?;
+        let withdrawable: WithdrawableResponse = app
+            .wrap()
+            .query_wasm_smart(&vesting, &QueryMsg::QueryWithdrawable)
+            .unwrap();
+        let withdrawable =
+            withdrawable.unlocked_amount + withdrawable.cliff_amount + withdrawable.vesting_amount;
+        for (index, actor) in ACTORS.iter().enumerate() {
+            let share = share_of(&config, actor);
+            let claimed = paid[index] + withdrawable.multiply_ratio(share, 10_000u128);
+            let vested_share = paid_by_share[index] + unpaid.multiply_ratio(share, 10_000u128);
+            prop_assert!(
+                claimed <= vested_share + Uint128::new(i as u128 + 1),
+                "{} was paid and can withdraw {} of its {} share of the vested amount",
+                actor,
+                claimed,
+                vested_share
+            );
+        }
+    }
+
+    // unbond everything, then no funds may have left the owner, the recipients and the contract
+    let _ = app.execute_contract(
+        Addr::unchecked(OWNER),
+        vesting.clone(),
+        &ExecuteMsg::Unpause {},
+        &[],
+    );
+    for (validator, amount) in query_delegated(&app, &vesting) {
+        app.execute_contract(
+            Addr::unchecked(OWNER),
+            vesting.clone(),
+            &ExecuteMsg::UndelegateFunds(UndelegateFundsMsg {
+                validator,
+                amount: coin(amount.u128(), "uluna"),
+            }),
+            &[],
+        )
+        .unwrap();
+    }
+    advance_time(&mut app, UNBONDING_PERIOD + 1);
+    app.sudo(SudoMsg::Staking(StakingSudo::ProcessQueue {}))
+        .unwrap();
+    prop_assert!(query_delegated(&app, &vesting).is_empty());
+    let total = [OWNER, RECIPIENT, OTHER, DESTINATION, vesting.as_str()]
+        .iter()
+        .map(|address| query_uluna_balance(&app, address))
+        .sum::<Uint128>();
+    prop_assert!(
+        total >= Uint128::new(initial_funds),
+        "{} of {} funds left",
+        total,
+        initial_funds
+    );
+    Ok(())
+}
+
+#[test]
+fn test_simulation() {
+    // seeded, so every run simulates the same sequences
+    let mut runner = TestRunner::new_with_rng(
+        ProptestConfig {
+            cases: 32,
+            ..ProptestConfig::default()
+        },
+        TestRng::deterministic_rng(RngAlgorithm::ChaCha),
+    );
+    let steps = prop::collection::vec((0..STEP_KINDS, 0..ACTORS.len(), any::<u64>()), 1..64);
+    runner.run(&steps, simulate).unwrap();
+}
@@ -0,0 +1,63 @@
+// protobuf wire format encoding of the stargate messages sent by the contract, and the decoding
+// of the stargate query responses it reads
+use cosmwasm_std::{StdError, StdResult};
+
+// length delimited field, used for strings, bytes and embedded messages
+pub(crate) fn encode_bytes(field: u64, bytes: &[u8], buf: &mut Vec<u8>) {
+    encode_varint(field << 3 | 2, buf);
+    encode_varint(bytes.len() as u64, buf);
+    buf.extend_from_slice(bytes);
+}
+
+// varint field, used for unsigned integers
+pub(crate) fn encode_uint64(field: u64, value: u64, buf: &mut Vec<u8>) {
+    encode_varint(field << 3, buf);
+    encode_varint(value, buf);
+}
+
+fn encode_varint(mut value: u64, buf: &mut Vec<u8>) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+// values of the length delimited `field`, skipping the other fields
+pub(crate) fn decode_bytes(mut buf: &[u8], field: u64) -> StdResult<Vec<&[u8]>> {
+    let mut values = vec![];
+    while !buf.is_empty() {
+        let key = decode_varint(&mut buf)?;
+        let len = match key & 7 {
+            0 => {
+                decode_varint(&mut buf)?;
+                0
+            }
+            1 => 8,
+            2 => decode_varint(&mut buf)? as usize,
+            5 => 4,
+            _ => return Err(StdError::parse_err("protobuf", "unsupported wire type")),
+        };
+        if len > buf.len() {
+            return Err(StdError::parse_err("protobuf", "truncated field"));
+        }
+        let (value, rest) = buf.split_at(len);
+        if key & 7 == 2 && key >> 3 == field {
+            values.push(value);
+        }
+        buf = rest;
+    }
+    Ok(values)
+}
+
+fn decode_varint(buf: &mut &[u8]) -> StdResult<u64> {
+    let mut value = 0;
+    for (i, byte) in buf.iter().take(10).enumerate() {
+        value |= u64::from(byte & 0x7f) << (7 * i);
+        if byte & 0x80 == 0 {
+            *buf = &buf[i + 1..];
+            return Ok(value);
+        }
+    }
+    Err(StdError::parse_err("protobuf", "invalid varint"))
+}
@@ -1,5 +1,101 @@
-use crate::{Config, State};
-use cw_storage_plus::Item;
+use crate::{
+    Config, IbcTransfer, PendingWithdrawal, State, WithdrawPeriodLimit, WithdrawalHistoryEntry,
+};
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Binary, Coin, Uint128, Uint64};
+use cw721::Expiration;
+use cw_asset::AssetInfo;
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex};
 
 pub const CONFIG: Item<Config> = Item::new("config");
 pub const STATE: Item<State> = Item::new("state");
+pub const PAUSED: Item<bool> = Item::new("paused");
+pub const FUNDED_AMOUNT: Item<Uint128> = Item::new("funded_amount");
+// undelegated amounts keyed by the unbonding completion time in seconds
+pub const UNBONDING: Map<u64, Uint128> = Map::new("unbonding");
+pub const PENDING_WITHDRAWAL_ID: Item<u64> = Item::new("pending_withdrawal_id");
+pub const PENDING_WITHDRAWALS: Map<u64, PendingWithdrawal> = Map::new("pending_withdrawals");
+// payout destinations approved by each recipient with the only hook message they can be paid with,
+// keyed by (recipient, destination)
+pub const APPROVED_DESTINATIONS: Map<(&Addr, &Addr), Option<Binary>> =
+    Map::new("approved_destinations");
+// IBC payout waiting for its packet sequence from the reply
+pub const IBC_TRANSFER_REPLY: Item<IbcTransfer> = Item::new("ibc_transfer_reply");
+// in flight IBC payouts keyed by (channel id, packet sequence)
+pub const IBC_TRANSFERS: Map<(&str, u64), IbcTransfer> = Map::new("ibc_transfers");
+
+// fee funds reserved by the owner, not granted yet
+pub const FEE_BUDGET: Item<Coin> = Item::new("fee_budget");
+// spend limit of the fee allowance granted to each whitelisted address
+pub const FEE_GRANTS: Map<&Addr, Uint128> = Map::new("fee_grants");
+
+// holder of each recipient position, keyed by its cw721 token id
+pub const POSITIONS: Map<u64, Addr> = Map::new("positions");
+pub const POSITION_ID: Item<u64> = Item::new("position_id");
+// spenders approved for a single position, keyed by (token id, spender)
+pub const TOKEN_APPROVALS: Map<(u64, &Addr), Expiration> = Map::new("token_approvals");
+// operators approved for all positions of a holder, keyed by (holder, operator)
+pub const OPERATORS: Map<(&Addr, &Addr), Expiration> = Map::new("operators");
+// addresses whitelisted by the owner, as opposed to through the positions they hold
+pub const OWNER_WHITELISTED: Map<&Addr, bool> = Map::new("owner_whitelisted");
+
+pub const WITHDRAWAL_HISTORY_ID: Item<u64> = Item::new("withdrawal_history_id");
+
+pub struct WithdrawalHistoryIndexes<'a> {
+    pub tranche: MultiIndex<'a, String, WithdrawalHistoryEntry, u64>,
+    pub sender: MultiIndex<'a, Addr, WithdrawalHistoryEntry, u64>,
+}
+
+impl<'a> IndexList<WithdrawalHistoryEntry> for WithdrawalHistoryIndexes<'a> {
+    fn get_indexes(
+        &'_ self,
+    ) -> Box<dyn Iterator<Item = &'_ dyn Index<WithdrawalHistoryEntry>> + '_> {
+        let v: Vec<&dyn Index<WithdrawalHistoryEntry>> = vec![&self.tranche, &self.sender];
+        Box::new(v.into_iter())
+    }
+}
+
+// every payout keyed by its sequence, indexed by tranche and by sender
+pub fn withdrawal_history<'a>(
+) -> IndexedMap<'a, u64, WithdrawalHistoryEntry, WithdrawalHistoryIndexes<'a>> {
+    let indexes = WithdrawalHistoryIndexes {
+        tranche: MultiIndex::new(
+            |_pk, entry| entry.tranche.to_string(),
+            "withdrawal_history",
+            "withdrawal_history__tranche",
+        ),
+        sender: MultiIndex::new(
+            |_pk, entry| entry.sender.clone(),
+            "withdrawal_history",
+            "withdrawal_history__sender",
+        ),
+    };
+    IndexedMap::new("withdrawal_history", indexes)
+}
+
+// config layout with a single recipient, read once by `migrate`
+pub const LEGACY_CONFIG: Item<LegacyConfig> = Item::new("config");
+
+#[cw_serde]
+pub struct LegacyConfig {
+    pub owner: Addr,
+    #[serde(default)]
+    pub guardian: Option<Addr>,
+    pub recipient: Addr,
+    #[serde(default = "default_vesting_asset")]
+    pub vesting_asset: AssetInfo,
+    #[serde(default)]
+    pub withdrawal_delay: Option<Uint64>,
+    #[serde(default)]
+    pub max_withdraw_per_period: Option<WithdrawPeriodLimit>,
+    pub unlocked_amount: Uint128,
+    pub cliff_amount: Uint128,
+    pub vesting_amount: Uint128,
+    pub start_time: Uint64,
+    pub end_time: Uint64,
+    pub whitelisted_addresses: Vec<Addr>,
+}
+
+fn default_vesting_asset() -> AssetInfo {
+    AssetInfo::native("uluna")
+}
@@ -1,15 +1,39 @@
-use crate::contract::{execute, instantiate, query};
-use crate::state::{CONFIG, STATE};
+use crate::contract::{
+    execute, instantiate, migrate, query, reply, sudo, GOVERNANCE_MODULE, MAX_VESTING_DURATION,
+};
+use crate::feegrant::{grant_allowance_msg, revoke_allowance_msg, QUERY_ALLOWANCE};
+use crate::helpers::CommunityPoolVestingContract;
+use crate::proto::{decode_bytes, encode_bytes};
+use crate::state::{CONFIG, FUNDED_AMOUNT, IBC_TRANSFERS, PAUSED, STATE};
+use crate::transfer::transfer_msg;
 use crate::{
-    AddToWhitelistMsg, Config, ContractError, DelegateFundsMsg, ExecuteMsg, InstantiateMsg,
-    QueryMsg, RedelegateFundsMsg, RemoveFromWhitelistMsg, State, UndelegateFundsMsg,
-    UpdateOwnerMsg, UpdateRecipientMsg, WithdrawDelegatorRewardMsg, WithdrawVestedFundsMsg,
+    AddToWhitelistMsg, AmendScheduleMsg, ApproveAllMsg, ApproveMsg, ApprovedDestinationsResponse,
+    ClawbackMsg, Config, ConfigResponse, ContractError, Cw20HookMsg, DelegateFundsMsg,
+    DestinationMsg, ExecuteMsg, FeeAllowanceMsg, FeeGrant, FeeGrantsResponse, FundingResponse,
+    IbcLifecycleComplete, IbcPayout, InstantiateMsg, MigrateMsg, PausedResponse, PayoutDestination,
+    PendingWithdrawal, PendingWithdrawalMsg, PendingWithdrawalsResponse, PrepareLiquidityMsg,
+    QueryMsg, RedelegateFundsMsg, RemoveFromWhitelistMsg, RevokeFeeAllowanceMsg, SendNftMsg,
+    SolvencyResponse, State, StateResponse, SudoMsg, SummaryResponse, Tranche, TransferNftMsg,
+    UndelegateFundsMsg, UpdateGuardianMsg, UpdateIbcPayoutMsg, UpdateLiquidityGuardMsg,
+    UpdateOwnerMsg, UpdateRecipientMsg, UpdateWithdrawalDelayMsg, VestingPosition,
+    WithdrawDelegatorRewardMsg, WithdrawPeriodLimit, WithdrawVestedFundsMsg, WithdrawableResponse,
+    WithdrawalHistoryEntry, WithdrawalHistoryResponse, WithdrawalRecord,
 };
 use cosmwasm_std::testing::{mock_info, MockApi, MockQuerier, MockStorage};
 use cosmwasm_std::{
-    coin, from_binary, Addr, BankMsg, BlockInfo, Coin, ContractInfo, CosmosMsg, Empty, Env,
-    MessageInfo, OwnedDeps, ReplyOn, StakingMsg, SubMsg, Timestamp, Uint128, Uint64,
+    coin, from_binary, from_slice, to_binary, Addr, BankMsg, Binary, BlockInfo, Coin, ContractInfo,
+    ContractResult, CosmosMsg, Decimal, DistributionMsg, Empty, Env, Event, FullDelegation,
+    MessageInfo, OwnedDeps, Querier, QuerierResult, QueryRequest, Reply, ReplyOn, Response,
+    StakingMsg, StdError, SubMsg, SubMsgResponse, SubMsgResult, SystemError, SystemResult,
+    Timestamp, Uint128, Uint64, WasmMsg, WasmQuery,
+};
+use cw20::{Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg, TokenInfoResponse};
+use cw721::{
+    AllNftInfoResponse, Approval, ApprovalsResponse, Cw721ReceiveMsg, Expiration,
+    OperatorsResponse, OwnerOfResponse, TokensResponse,
 };
+use cw_asset::{AssetInfo, AssetInfoUnchecked};
+use std::collections::HashMap;
 use std::marker::PhantomData;
 
 const CONTRACT_ADDR: &str = "community_pool_vesting_contract";
@@ -19,20 +43,24 @@ const VESTING_END_TIME: u64 = 1861937999; //dec 31, 2028, 23:59:59
 const UNLOCKED_AMOUNT: u128 = 25_000_000_000_000; //25m u_units
 const CLIFF_AMOUNT: u128 = 25_000_000_000_000; //25m u_units
 const VESTING_AMOUNT: u128 = 100_000_000_000_000; //100m u_units
+const LUNA_SUPPLY: u128 = 1_000_000_000_000_000; //1b u_units held outside the contract
 
-const VESTED_PER_DAY: u128 = 68_446_270_221; //leap year and 3 years (incl. rounding errors from inner calculation) rounding error is a fraction of a luna, so it is within tolerance (actual value == 68446269678 == VESTING_AMOUNT/(365*3+366)
+const VESTED_PER_DAY: u128 = 68_446_270_220; //vested amount of the first day, rounded down. the schedule ends one second before the end of 2028, so this is slightly more than VESTING_AMOUNT/(365*3+366) == 68446269678
 const DAY_IN_SECONDS: u64 = 86400;
 
 fn mock_dependencies_with_contract_balance(
     amount: Uint128,
 ) -> OwnedDeps<MockStorage, MockApi, MockQuerier, Empty> {
-    let mock_querier = MockQuerier::new(&[(
-        CONTRACT_ADDR,
-        &[Coin {
-            denom: "uluna".to_string(),
-            amount,
-        }],
-    )]);
+    let mock_querier = MockQuerier::new(&[
+        (
+            CONTRACT_ADDR,
+            &[Coin {
+                denom: "uluna".to_string(),
+                amount,
+            }],
+        ),
+        ("luna_holder", &[coin(LUNA_SUPPLY, "uluna")]),
+    ]);
     OwnedDeps {
         storage: MockStorage::default(),
         api: MockApi::default(),
@@ -64,12 +92,20 @@ fn instantiate_contract() -> (
 
     let instantiate_msg = InstantiateMsg {
         owner: owner.sender.to_string(),
-        recipient: recipient.clone().sender.to_string(),
+        guardian: None,
+        recipients: vec![(recipient.sender.to_string(), 10_000)],
+        vesting_asset: None,
+        withdrawal_delay: None,
+        max_withdraw_per_period: None,
+        ibc_payout: None,
         unlocked_amount: Uint128::new(UNLOCKED_AMOUNT),
         cliff_amount: Uint128::new(CLIFF_AMOUNT),
         vesting_amount: Uint128::new(VESTING_AMOUNT),
         start_time: Some(Uint64::new(VESTING_START_TIME)),
         end_time: Uint64::new(VESTING_END_TIME),
+        require_full_funding: None,
+        governance_owner: None,
+        liquidity_guard: None,
     };
 
     deps.querier.update_balance(
@@ -82,6 +118,60 @@ fn instantiate_contract() -> (
     (deps, env, owner, recipient)
 }
 
+// answers the feegrant allowance query with the unspent uusd of each grantee, as the chain would
+struct FeeGrantQuerier {
+    base: MockQuerier,
+    allowances: HashMap<String, u128>,
+}
+
+impl Querier for FeeGrantQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        match from_slice::<QueryRequest<Empty>>(bin_request) {
+            Ok(QueryRequest::Stargate { path, data }) if path == QUERY_ALLOWANCE => {
+                let granter =
+                    String::from_utf8(decode_bytes(&data, 1).unwrap()[0].to_vec()).unwrap();
+                let grantee =
+                    String::from_utf8(decode_bytes(&data, 2).unwrap()[0].to_vec()).unwrap();
+                let remaining = match self.allowances.get(&grantee) {
+                    Some(remaining) => *remaining,
+                    None => {
+                        return SystemResult::Ok(ContractResult::Err(
+                            "fee-grant not found".to_string(),
+                        ))
+                    }
+                };
+                // a Grant is encoded like the MsgGrantAllowance creating it
+                let grant = match grant_allowance_msg(
+                    &Addr::unchecked(granter),
+                    &Addr::unchecked(grantee),
+                    &coin(remaining, "uusd"),
+                ) {
+                    CosmosMsg::Stargate { value, .. } => value,
+                    _ => unreachable!(),
+                };
+                let mut response = vec![];
+                encode_bytes(1, &grant, &mut response);
+                SystemResult::Ok(ContractResult::Ok(Binary::from(response)))
+            }
+            _ => self.base.raw_query(bin_request),
+        }
+    }
+}
+
+fn with_fee_grants(
+    deps: OwnedDeps<MockStorage, MockApi, MockQuerier>,
+) -> OwnedDeps<MockStorage, MockApi, FeeGrantQuerier> {
+    OwnedDeps {
+        storage: deps.storage,
+        api: deps.api,
+        querier: FeeGrantQuerier {
+            base: deps.querier,
+            allowances: HashMap::new(),
+        },
+        custom_query_type: PhantomData,
+    }
+}
+
 #[test]
 fn test_withdraw_vested_funds_owner() {
     let (mut deps, mut env, mut owner, recipient) = instantiate_contract();
@@ -93,7 +183,9 @@ fn test_withdraw_vested_funds_owner() {
         env.clone(),
         owner.clone(),
         ExecuteMsg::WithdrawUnlockedFunds(WithdrawVestedFundsMsg {
-            denom: "uluna".to_string(),
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
         }),
     )
     .unwrap();
@@ -103,7 +195,9 @@ fn test_withdraw_vested_funds_owner() {
         env.clone(),
         owner.clone(),
         ExecuteMsg::WithdrawCliffVestedFunds(WithdrawVestedFundsMsg {
-            denom: "uluna".to_string(),
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
         }),
     )
     .unwrap();
@@ -125,7 +219,9 @@ fn test_withdraw_vested_funds_owner() {
         env,
         owner,
         ExecuteMsg::WithdrawVestedFunds(WithdrawVestedFundsMsg {
-            denom: "uluna".to_string(),
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
         }),
     )
     .unwrap();
@@ -154,7 +250,9 @@ fn test_withdraw_vested_funds_whitelist() {
         env.clone(),
         recipient.clone(),
         ExecuteMsg::WithdrawUnlockedFunds(WithdrawVestedFundsMsg {
-            denom: "uluna".to_string(),
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
         }),
     )
     .unwrap();
@@ -164,7 +262,9 @@ fn test_withdraw_vested_funds_whitelist() {
         env.clone(),
         recipient.clone(),
         ExecuteMsg::WithdrawCliffVestedFunds(WithdrawVestedFundsMsg {
-            denom: "uluna".to_string(),
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
         }),
     )
     .unwrap();
@@ -188,7 +288,9 @@ fn test_withdraw_vested_funds_whitelist() {
         env,
         recipient.clone(),
         ExecuteMsg::WithdrawVestedFunds(WithdrawVestedFundsMsg {
-            denom: "uluna".to_string(),
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
         }),
     )
     .unwrap();
@@ -218,7 +320,9 @@ fn test_withdraw_unlocked() {
         env.clone(),
         owner.clone(),
         ExecuteMsg::WithdrawUnlockedFunds(WithdrawVestedFundsMsg {
-            denom: "uluna".to_string(),
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
         }),
     )
     .unwrap();
@@ -234,6 +338,16 @@ fn test_withdraw_unlocked() {
             reply_on: ReplyOn::Never,
         }
     );
+    assert_eq!(
+        res.events,
+        vec![Event::new("vesting-withdraw")
+            .add_attribute("action", "withdraw_unlocked_funds")
+            .add_attribute("sender", "vlad")
+            .add_attribute("unlocked_amount_withdrawn", UNLOCKED_AMOUNT.to_string())
+            .add_attribute("tranche", "unlocked")
+            .add_attribute("asset", "native:uluna")
+            .add_attribute("amount", UNLOCKED_AMOUNT.to_string())]
+    );
 }
 
 #[test]
@@ -249,6 +363,7 @@ fn test_withdraw_vested_funds_before_withdrawing_cliff_vested() {
                 cliff_amount_withdrawn: Uint128::new(0),
                 last_withdrawn_time: Uint64::new(VESTING_START_TIME),
                 unlocked_amount_withdrawn: Uint128::new(UNLOCKED_AMOUNT),
+                recent_withdrawals: vec![],
             },
         )
         .unwrap();
@@ -258,7 +373,9 @@ fn test_withdraw_vested_funds_before_withdrawing_cliff_vested() {
         env,
         owner,
         ExecuteMsg::WithdrawVestedFunds(WithdrawVestedFundsMsg {
-            denom: "uluna".to_string(),
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
         }),
     )
     .unwrap_err();
@@ -279,6 +396,7 @@ fn test_withdraw_vested_funds_before_withdrawing_unlocked() {
                 cliff_amount_withdrawn: Uint128::new(CLIFF_AMOUNT),
                 last_withdrawn_time: Uint64::new(VESTING_START_TIME),
                 unlocked_amount_withdrawn: Uint128::new(0),
+                recent_withdrawals: vec![],
             },
         )
         .unwrap();
@@ -288,7 +406,9 @@ fn test_withdraw_vested_funds_before_withdrawing_unlocked() {
         env,
         owner,
         ExecuteMsg::WithdrawVestedFunds(WithdrawVestedFundsMsg {
-            denom: "uluna".to_string(),
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
         }),
     )
     .unwrap_err();
@@ -307,7 +427,9 @@ fn test_withdraw_cliff_vested_funds_with_not_enough_balance() {
         env.clone(),
         owner.clone(),
         ExecuteMsg::WithdrawCliffVestedFunds(WithdrawVestedFundsMsg {
-            denom: "uluna".to_string(),
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
         }),
     )
     .unwrap();
@@ -331,6 +453,7 @@ fn test_withdraw_cliff_vested_funds_with_not_enough_balance() {
             cliff_amount_withdrawn: Uint128::new(CLIFF_AMOUNT),
             last_withdrawn_time: Uint64::new(VESTING_START_TIME),
             unlocked_amount_withdrawn: Uint128::new(0),
+            recent_withdrawals: vec![],
         }
     );
 
@@ -340,7 +463,9 @@ fn test_withdraw_cliff_vested_funds_with_not_enough_balance() {
         env.clone(),
         owner.clone(),
         ExecuteMsg::WithdrawVestedFunds(WithdrawVestedFundsMsg {
-            denom: "uluna".to_string(),
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
         }),
     )
     .unwrap_err();
@@ -357,7 +482,9 @@ fn test_withdraw_vested_funds_before_vesting_started() {
         env.clone(),
         owner.clone(),
         ExecuteMsg::WithdrawCliffVestedFunds(WithdrawVestedFundsMsg {
-            denom: "uluna".to_string(),
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
         }),
     )
     .unwrap_err();
@@ -375,7 +502,9 @@ fn test_withdraw_vested_funds_zero_balance() {
         env.clone(),
         owner.clone(),
         ExecuteMsg::WithdrawUnlockedFunds(WithdrawVestedFundsMsg {
-            denom: "uluna".to_string(),
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
         }),
     )
     .unwrap_err();
@@ -386,7 +515,9 @@ fn test_withdraw_vested_funds_zero_balance() {
         env.clone(),
         owner.clone(),
         ExecuteMsg::WithdrawCliffVestedFunds(WithdrawVestedFundsMsg {
-            denom: "uluna".to_string(),
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
         }),
     )
     .unwrap_err();
@@ -399,6 +530,7 @@ fn test_withdraw_vested_funds_zero_balance() {
                 cliff_amount_withdrawn: Uint128::new(CLIFF_AMOUNT),
                 last_withdrawn_time: Uint64::new(VESTING_START_TIME),
                 unlocked_amount_withdrawn: Uint128::new(UNLOCKED_AMOUNT),
+                recent_withdrawals: vec![],
             },
         )
         .unwrap();
@@ -408,7 +540,9 @@ fn test_withdraw_vested_funds_zero_balance() {
         env,
         owner,
         ExecuteMsg::WithdrawVestedFunds(WithdrawVestedFundsMsg {
-            denom: "uluna".to_string(),
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
         }),
     )
     .unwrap_err();
@@ -429,6 +563,7 @@ fn test_withdraw_vested_funds_balance_smaller_than_withdrawable() {
                 last_withdrawn_time: Uint64::new(VESTING_START_TIME),
                 cliff_amount_withdrawn: Uint128::new(CLIFF_AMOUNT),
                 unlocked_amount_withdrawn: Uint128::new(UNLOCKED_AMOUNT),
+                recent_withdrawals: vec![],
             },
         )
         .unwrap(); //cliff and unlocked withdrawn
@@ -441,7 +576,9 @@ fn test_withdraw_vested_funds_balance_smaller_than_withdrawable() {
         env.clone(),
         owner.clone(),
         ExecuteMsg::WithdrawVestedFunds(WithdrawVestedFundsMsg {
-            denom: "uluna".to_string(),
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
         }),
     )
     .unwrap();
@@ -466,6 +603,7 @@ fn test_withdraw_vested_funds_balance_smaller_than_withdrawable() {
             last_withdrawn_time: Uint64::new(VESTING_START_TIME + DAY_IN_SECONDS), //1 second worth withdraw, move the needle by 1 second
             cliff_amount_withdrawn: Uint128::new(25000000000000),
             unlocked_amount_withdrawn: Uint128::new(UNLOCKED_AMOUNT),
+            recent_withdrawals: vec![],
         }
     );
 
@@ -476,7 +614,9 @@ fn test_withdraw_vested_funds_balance_smaller_than_withdrawable() {
         env.clone(),
         owner.clone(),
         ExecuteMsg::WithdrawVestedFunds(WithdrawVestedFundsMsg {
-            denom: "uluna".to_string(),
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
         }),
     )
     .unwrap();
@@ -487,7 +627,7 @@ fn test_withdraw_vested_funds_balance_smaller_than_withdrawable() {
             id: 0,
             msg: CosmosMsg::Bank(BankMsg::Send {
                 to_address: recipient.sender.to_string(),
-                amount: vec![Coin::new(VESTED_PER_DAY + 1, "uluna")], //withdraws daily amount minus already withdrawn second worth of tokens in the day (+1 uluna needed for the rounding error)
+                amount: vec![Coin::new(VESTED_PER_DAY + 1, "uluna")], //withdraws the second day, which rounds up where the first day rounded down
             }),
             gas_limit: None,
             reply_on: ReplyOn::Never,
@@ -501,6 +641,7 @@ fn test_withdraw_vested_funds_balance_smaller_than_withdrawable() {
             last_withdrawn_time: Uint64::new(VESTING_START_TIME + DAY_IN_SECONDS * 2),
             cliff_amount_withdrawn: Uint128::new(CLIFF_AMOUNT),
             unlocked_amount_withdrawn: Uint128::new(UNLOCKED_AMOUNT),
+            recent_withdrawals: vec![],
         }
     );
 }
@@ -518,6 +659,7 @@ fn test_withdraw_vested_funds_balance_vesting_ended() {
                 last_withdrawn_time: Uint64::new(VESTING_START_TIME),
                 cliff_amount_withdrawn: Uint128::new(CLIFF_AMOUNT),
                 unlocked_amount_withdrawn: Uint128::new(UNLOCKED_AMOUNT),
+                recent_withdrawals: vec![],
             },
         )
         .unwrap();
@@ -530,7 +672,9 @@ fn test_withdraw_vested_funds_balance_vesting_ended() {
         env.clone(),
         owner.clone(),
         ExecuteMsg::WithdrawVestedFunds(WithdrawVestedFundsMsg {
-            denom: "uluna".to_string(),
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
         }),
     )
     .unwrap();
@@ -549,6 +693,82 @@ fn test_withdraw_vested_funds_balance_vesting_ended() {
     );
 }
 
+#[test]
+fn test_withdraw_vested_funds_sweeps_surplus() {
+    let (mut deps, mut env, owner, recipient) = instantiate_contract();
+    let surplus = 1_000_000;
+    STATE
+        .save(
+            deps.as_mut().storage,
+            &State {
+                last_withdrawn_time: Uint64::new(VESTING_START_TIME),
+                cliff_amount_withdrawn: Uint128::new(CLIFF_AMOUNT),
+                unlocked_amount_withdrawn: Uint128::new(UNLOCKED_AMOUNT),
+                recent_withdrawals: vec![],
+            },
+        )
+        .unwrap();
+    deps.querier
+        .update_balance(CONTRACT_ADDR, vec![coin(VESTING_AMOUNT + surplus, "uluna")]);
+    let withdraw_vested = ExecuteMsg::WithdrawVestedFunds(WithdrawVestedFundsMsg {
+        asset_info: AssetInfoUnchecked::native("uluna"),
+        destination: None,
+        msg: None,
+    });
+
+    // the surplus stays in the contract while the schedule is still vesting
+    env.block.time = Timestamp::from_seconds(VESTING_START_TIME + DAY_IN_SECONDS);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        withdraw_vested.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: recipient.sender.to_string(),
+            amount: vec![coin(VESTED_PER_DAY, "uluna")],
+        })
+    );
+
+    // past the end, the rest of the schedule is paid out together with the surplus
+    env.block.time = Timestamp::from_seconds(VESTING_END_TIME + 1);
+    deps.querier.update_balance(
+        CONTRACT_ADDR,
+        vec![coin(VESTING_AMOUNT - VESTED_PER_DAY + surplus, "uluna")],
+    );
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        withdraw_vested.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: recipient.sender.to_string(),
+            amount: vec![coin(VESTING_AMOUNT - VESTED_PER_DAY + surplus, "uluna")],
+        })
+    );
+
+    // funds sent after the schedule was fully withdrawn can be swept too
+    deps.querier
+        .update_balance(CONTRACT_ADDR, vec![coin(surplus, "uluna")]);
+    let res = execute(deps.as_mut(), env, owner, withdraw_vested).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: recipient.sender.to_string(),
+            amount: vec![coin(surplus, "uluna")],
+        })
+    );
+    let state = STATE.load(deps.as_ref().storage).unwrap();
+    assert_eq!(state.last_withdrawn_time, Uint64::new(VESTING_END_TIME));
+}
+
 #[test]
 fn test_withdraw_vested_funds_balance_non_luna() {
     let (mut deps, mut env, mut owner, recipient) = instantiate_contract();
@@ -563,7 +783,9 @@ fn test_withdraw_vested_funds_balance_non_luna() {
         env.clone(),
         owner.clone(),
         ExecuteMsg::WithdrawCliffVestedFunds(WithdrawVestedFundsMsg {
-            denom: "uusd".to_string(),
+            asset_info: AssetInfoUnchecked::native("uusd"),
+            destination: None,
+            msg: None,
         }),
     )
     .unwrap();
@@ -588,6 +810,7 @@ fn test_withdraw_vested_funds_balance_non_luna() {
                 last_withdrawn_time: Uint64::new(VESTING_START_TIME),
                 cliff_amount_withdrawn: Uint128::new(CLIFF_AMOUNT),
                 unlocked_amount_withdrawn: Uint128::new(UNLOCKED_AMOUNT),
+                recent_withdrawals: vec![],
             },
         )
         .unwrap();
@@ -597,7 +820,9 @@ fn test_withdraw_vested_funds_balance_non_luna() {
         env.clone(),
         owner.clone(),
         ExecuteMsg::WithdrawVestedFunds(WithdrawVestedFundsMsg {
-            denom: "uusd".to_string(),
+            asset_info: AssetInfoUnchecked::native("uusd"),
+            destination: None,
+            msg: None,
         }),
     )
     .unwrap();
@@ -633,7 +858,9 @@ fn test_withdraw_vested_funds_unauthorized() {
         env.clone(),
         info.clone(),
         ExecuteMsg::WithdrawCliffVestedFunds(WithdrawVestedFundsMsg {
-            denom: "uluna".to_string(),
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
         }),
     )
     .unwrap_err();
@@ -647,6 +874,7 @@ fn test_withdraw_vested_funds_unauthorized() {
                 last_withdrawn_time: Uint64::new(10),
                 cliff_amount_withdrawn: Uint128::new(100_000),
                 unlocked_amount_withdrawn: Uint128::new(0),
+                recent_withdrawals: vec![],
             },
         )
         .unwrap();
@@ -656,7 +884,9 @@ fn test_withdraw_vested_funds_unauthorized() {
         env.clone(),
         info.clone(),
         ExecuteMsg::WithdrawVestedFunds(WithdrawVestedFundsMsg {
-            denom: "uluna".to_string(),
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
         }),
     )
     .unwrap_err();
@@ -665,447 +895,553 @@ fn test_withdraw_vested_funds_unauthorized() {
 }
 
 #[test]
-fn test_add_to_whitelist_successful() {
-    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
-    owner.funds = vec![];
-    env.block.time = env.block.time.plus_seconds(200);
+fn test_receive_cw20_native_vesting_asset() {
+    let (mut deps, env, _owner, _recipient) = instantiate_contract();
 
-    execute(
+    let res = execute(
         deps.as_mut(),
-        env.clone(),
-        owner.clone(),
-        ExecuteMsg::AddToWhitelist(AddToWhitelistMsg {
-            addresses: vec![Addr::unchecked("warp")],
+        env,
+        mock_info("cw20_token", &[]),
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "vlad".to_string(),
+            amount: Uint128::new(1_000_000),
+            msg: to_binary(&Cw20HookMsg::Fund {}).unwrap(),
         }),
     )
-    .unwrap();
-
-    let config = CONFIG.load(deps.as_ref().storage).unwrap();
-    assert_eq!(
-        config.whitelisted_addresses,
-        vec![
-            Addr::unchecked("vlad"),
-            Addr::unchecked("javier"),
-            Addr::unchecked("warp")
-        ]
-    );
+    .unwrap_err();
+    assert_eq!(res, ContractError::UnsupportedAsset {});
 }
 
 #[test]
-fn test_add_to_whitelist_not_owner() {
-    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
-    owner.funds = vec![];
-    env.block.time = env.block.time.plus_seconds(200);
-
-    let info = mock_info("random", &[]);
+fn test_instantiate_require_full_funding() {
+    let (mut deps, env, owner, recipient) = instantiate_contract();
+    let total_amount = UNLOCKED_AMOUNT + CLIFF_AMOUNT + VESTING_AMOUNT;
+    let mut instantiate_msg = InstantiateMsg {
+        owner: owner.sender.to_string(),
+        guardian: None,
+        recipients: vec![(recipient.sender.to_string(), 10_000)],
+        vesting_asset: None,
+        withdrawal_delay: None,
+        max_withdraw_per_period: None,
+        ibc_payout: None,
+        unlocked_amount: Uint128::new(UNLOCKED_AMOUNT),
+        cliff_amount: Uint128::new(CLIFF_AMOUNT),
+        vesting_amount: Uint128::new(VESTING_AMOUNT),
+        start_time: Some(Uint64::new(VESTING_START_TIME)),
+        end_time: Uint64::new(VESTING_END_TIME),
+        require_full_funding: Some(true),
+        governance_owner: None,
+        liquidity_guard: None,
+    };
 
-    execute(
+    let res = instantiate(
         deps.as_mut(),
         env.clone(),
-        info.clone(),
-        ExecuteMsg::AddToWhitelist(AddToWhitelistMsg {
-            addresses: vec![Addr::unchecked("warp")],
-        }),
+        mock_info("vlad", &[coin(total_amount - 1, "uluna")]),
+        instantiate_msg.clone(),
     )
     .unwrap_err();
-}
-
-#[test]
-fn test_add_to_whitelist_already_included() {
-    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
-    owner.funds = vec![];
-    env.block.time = env.block.time.plus_seconds(200);
+    assert_eq!(
+        res,
+        ContractError::ValidationError("Funds sent do not cover the vesting schedule".to_string())
+    );
 
-    execute(
+    instantiate(
         deps.as_mut(),
         env.clone(),
-        owner.clone(),
-        ExecuteMsg::AddToWhitelist(AddToWhitelistMsg {
-            addresses: vec![Addr::unchecked("javier")],
-        }),
+        mock_info("vlad", &[coin(total_amount, "uluna")]),
+        instantiate_msg.clone(),
     )
     .unwrap();
-
-    let config = CONFIG.load(deps.as_ref().storage).unwrap();
     assert_eq!(
-        config.whitelisted_addresses,
-        vec![Addr::unchecked("vlad"), Addr::unchecked("javier")]
+        FUNDED_AMOUNT.load(deps.as_ref().storage).unwrap(),
+        Uint128::new(total_amount)
     );
-}
-
-#[test]
-fn test_remove_from_whitelist_successful() {
-    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
-    owner.funds = vec![];
-    env.block.time = env.block.time.plus_seconds(200);
 
-    execute(
+    // a cw20 asset cannot be attached to the instantiation
+    let res = instantiate(
         deps.as_mut(),
         env.clone(),
-        owner.clone(),
-        ExecuteMsg::AddToWhitelist(AddToWhitelistMsg {
-            addresses: vec![Addr::unchecked("warp")],
-        }),
+        mock_info("vlad", &[]),
+        InstantiateMsg {
+            vesting_asset: Some(AssetInfoUnchecked::cw20("token")),
+            ..instantiate_msg.clone()
+        },
     )
-    .unwrap();
-
-    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    .unwrap_err();
     assert_eq!(
-        config.whitelisted_addresses,
-        vec![
-            Addr::unchecked("vlad"),
-            Addr::unchecked("javier"),
-            Addr::unchecked("warp")
-        ]
+        res,
+        ContractError::ValidationError(
+            "Full funding can only be required for a native vesting asset".to_string()
+        )
     );
 
-    execute(
+    // without the flag, underfunded instantiation is still accepted
+    instantiate_msg.require_full_funding = None;
+    instantiate(
         deps.as_mut(),
-        env.clone(),
-        owner.clone(),
-        ExecuteMsg::RemoveFromWhitelist(RemoveFromWhitelistMsg {
-            addresses: vec![Addr::unchecked("warp")],
-        }),
+        env,
+        mock_info("vlad", &[coin(1, "uluna")]),
+        instantiate_msg,
     )
     .unwrap();
-
-    let config = CONFIG.load(deps.as_ref().storage).unwrap();
     assert_eq!(
-        config.whitelisted_addresses,
-        vec![Addr::unchecked("vlad"), Addr::unchecked("javier")]
+        FUNDED_AMOUNT.load(deps.as_ref().storage).unwrap(),
+        Uint128::new(1)
     );
 }
 
 #[test]
-fn test_remove_from_whitelist_not_owner() {
-    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
-    owner.funds = vec![];
-    env.block.time = env.block.time.plus_seconds(200);
-
-    let info = mock_info("random", &[]);
+fn test_fund_successful() {
+    let (mut deps, env, _owner, _recipient) = instantiate_contract();
 
     execute(
         deps.as_mut(),
         env.clone(),
-        info.clone(),
-        ExecuteMsg::RemoveFromWhitelist(RemoveFromWhitelistMsg {
-            addresses: vec![Addr::unchecked("warp")],
-        }),
+        mock_info("community_pool", &[coin(1_000_000, "uluna")]),
+        ExecuteMsg::Fund {},
     )
-    .unwrap_err();
-}
-
-#[test]
-fn test_remove_recipient_from_whitelist() {
-    let (mut deps, mut env, mut owner, recipient) = instantiate_contract();
-    owner.funds = vec![];
-    env.block.time = env.block.time.plus_seconds(200);
-
+    .unwrap();
     execute(
         deps.as_mut(),
-        env.clone(),
-        owner.clone(),
-        ExecuteMsg::RemoveFromWhitelist(RemoveFromWhitelistMsg {
-            addresses: vec![recipient.sender.clone()],
-        }),
+        env,
+        mock_info("community_pool", &[coin(500_000, "uluna")]),
+        ExecuteMsg::Fund {},
     )
     .unwrap();
 
-    let config = CONFIG.load(deps.as_ref().storage).unwrap();
     assert_eq!(
-        config.whitelisted_addresses,
-        vec![owner.sender, recipient.sender]
+        FUNDED_AMOUNT.load(deps.as_ref().storage).unwrap(),
+        Uint128::new(1_500_000)
     );
 }
 
 #[test]
-fn test_remove_owner_from_whitelist() {
-    let (mut deps, mut env, mut owner, recipient) = instantiate_contract();
-    owner.funds = vec![];
-    env.block.time = env.block.time.plus_seconds(200);
+fn test_fund_invalid_funds() {
+    let (mut deps, env, _owner, _recipient) = instantiate_contract();
 
-    execute(
+    let res = execute(
         deps.as_mut(),
         env.clone(),
-        owner.clone(),
-        ExecuteMsg::RemoveFromWhitelist(RemoveFromWhitelistMsg {
-            addresses: vec![owner.sender.clone()],
-        }),
+        mock_info("community_pool", &[]),
+        ExecuteMsg::Fund {},
     )
-    .unwrap();
+    .unwrap_err();
+    assert_eq!(res, ContractError::NoFunds {});
 
-    let config = CONFIG.load(deps.as_ref().storage).unwrap();
-    assert_eq!(
-        config.whitelisted_addresses,
-        vec![owner.sender, recipient.sender]
-    );
+    let res = execute(
+        deps.as_mut(),
+        env,
+        mock_info(
+            "community_pool",
+            &[coin(1_000_000, "uluna"), coin(1_000_000, "uusd")],
+        ),
+        ExecuteMsg::Fund {},
+    )
+    .unwrap_err();
+    assert_eq!(res, ContractError::UnsupportedAsset {});
 }
 
 #[test]
-fn test_remove_owner_from_whitelist_with_accs() {
-    let (mut deps, mut env, mut owner, recipient) = instantiate_contract();
-    owner.funds = vec![];
-    env.block.time = env.block.time.plus_seconds(200);
-
-    execute(
-        deps.as_mut(),
-        env.clone(),
-        owner.clone(),
-        ExecuteMsg::AddToWhitelist(AddToWhitelistMsg {
-            addresses: vec![Addr::unchecked("warp")],
-        }),
-    )
-    .unwrap();
+fn test_query_funding() {
+    let (mut deps, mut env, owner, _recipient) = instantiate_contract();
+    let total_amount = UNLOCKED_AMOUNT + CLIFF_AMOUNT + VESTING_AMOUNT;
 
+    deps.querier
+        .update_balance(CONTRACT_ADDR, vec![coin(total_amount / 2, "uluna")]);
+    deps.querier.update_staking(
+        "uluna",
+        &[],
+        &[FullDelegation {
+            delegator: Addr::unchecked(CONTRACT_ADDR),
+            validator: "validator".to_string(),
+            amount: coin(total_amount / 4, "uluna"),
+            can_redelegate: coin(total_amount / 4, "uluna"),
+            accumulated_rewards: vec![],
+        }],
+    );
     execute(
         deps.as_mut(),
         env.clone(),
-        owner.clone(),
-        ExecuteMsg::RemoveFromWhitelist(RemoveFromWhitelistMsg {
-            addresses: vec![owner.sender.clone()],
+        owner,
+        ExecuteMsg::UndelegateFunds(UndelegateFundsMsg {
+            validator: "validator".to_string(),
+            amount: coin(total_amount / 4, "uluna"),
         }),
     )
     .unwrap();
 
-    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::QueryFunding {}).unwrap();
+    let value: FundingResponse = from_binary(&res).unwrap();
     assert_eq!(
-        config.whitelisted_addresses,
-        vec![owner.sender, recipient.sender, Addr::unchecked("warp")]
+        value,
+        FundingResponse {
+            total_obligations: Uint128::new(total_amount),
+            total_funded: Uint128::zero(),
+            liquid_balance: Uint128::new(total_amount / 2),
+            delegated_balance: Uint128::new(total_amount / 4),
+            unbonding_balance: Uint128::new(total_amount / 4),
+            total_holdings: Uint128::new(total_amount),
+            shortfall: Uint128::zero(),
+        }
     );
+
+    // once unbonding completes the funds are expected back in the liquid balance
+    env.block.time = env.block.time.plus_seconds(DAY_IN_SECONDS * 21 + 1);
+    let res = query(deps.as_ref(), env, QueryMsg::QueryFunding {}).unwrap();
+    let value: FundingResponse = from_binary(&res).unwrap();
+    assert_eq!(value.unbonding_balance, Uint128::zero());
+    assert_eq!(value.shortfall, Uint128::new(total_amount / 4));
 }
 
 #[test]
-fn test_update_owner_successful() {
-    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
-    owner.funds = vec![];
-    env.block.time = env.block.time.plus_seconds(200);
-
-    execute(
-        deps.as_mut(),
-        env.clone(),
-        owner.clone(),
-        ExecuteMsg::UpdateOwner(UpdateOwnerMsg {
-            owner: "rando".to_string(),
-        }),
-    )
-    .unwrap();
+fn test_query_summary() {
+    let (mut deps, mut env, _owner, _recipient) = instantiate_contract();
+    env.block.time = Timestamp::from_seconds(VESTING_START_TIME + DAY_IN_SECONDS);
+    STATE
+        .save(
+            deps.as_mut().storage,
+            &State {
+                last_withdrawn_time: Uint64::new(VESTING_START_TIME),
+                cliff_amount_withdrawn: Uint128::new(CLIFF_AMOUNT),
+                unlocked_amount_withdrawn: Uint128::zero(),
+                recent_withdrawals: vec![],
+            },
+        )
+        .unwrap();
+    deps.querier
+        .update_balance(CONTRACT_ADDR, vec![coin(VESTING_AMOUNT / 2, "uluna")]);
+    let delegation = |validator: &str, rewards: Vec<Coin>| FullDelegation {
+        delegator: Addr::unchecked(CONTRACT_ADDR),
+        validator: validator.to_string(),
+        amount: coin(VESTING_AMOUNT / 4, "uluna"),
+        can_redelegate: coin(VESTING_AMOUNT / 4, "uluna"),
+        accumulated_rewards: rewards,
+    };
+    deps.querier.update_staking(
+        "uluna",
+        &[],
+        &[
+            delegation("validator", vec![coin(100, "uluna")]),
+            delegation("other_validator", vec![coin(50, "uluna"), coin(7, "uusd")]),
+        ],
+    );
 
-    let config = CONFIG.load(deps.as_ref().storage).unwrap();
-    assert_eq!(config.owner, Addr::unchecked("rando"));
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::QuerySummary).unwrap();
+    let value: SummaryResponse = from_binary(&res).unwrap();
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::QueryWithdrawable {}).unwrap();
+    let withdrawable: WithdrawableResponse = from_binary(&res).unwrap();
     assert_eq!(
-        config.whitelisted_addresses,
-        vec![Addr::unchecked("rando"), Addr::unchecked("javier")]
+        value,
+        SummaryResponse {
+            vesting_asset: AssetInfo::native("uluna"),
+            unlocked_amount: Uint128::new(UNLOCKED_AMOUNT),
+            cliff_amount: Uint128::new(CLIFF_AMOUNT),
+            vesting_amount: Uint128::new(VESTING_AMOUNT),
+            start_time: Uint64::new(VESTING_START_TIME),
+            end_time: Uint64::new(VESTING_END_TIME),
+            percent_vested: Decimal::from_ratio(VESTED_PER_DAY, VESTING_AMOUNT / 100),
+            unlocked_amount_withdrawn: Uint128::zero(),
+            cliff_amount_withdrawn: Uint128::new(CLIFF_AMOUNT),
+            vesting_amount_withdrawn: Uint128::zero(),
+            withdrawable,
+            paused: false,
+            liquid_balance: Uint128::new(VESTING_AMOUNT / 2),
+            delegated_balance: Uint128::new(VESTING_AMOUNT / 2),
+            pending_rewards: vec![coin(150, "uluna"), coin(7, "uusd")],
+            unbonding_balance: Uint128::zero(),
+        }
+    );
+    assert_eq!(
+        value.withdrawable.vesting_amount,
+        Uint128::new(VESTED_PER_DAY)
     );
+
+    // without a vesting amount there is nothing left to vest
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    CONFIG
+        .save(
+            deps.as_mut().storage,
+            &Config {
+                vesting_amount: Uint128::zero(),
+                ..config
+            },
+        )
+        .unwrap();
+    let res = query(deps.as_ref(), env, QueryMsg::QuerySummary).unwrap();
+    let value: SummaryResponse = from_binary(&res).unwrap();
+    assert_eq!(value.percent_vested, Decimal::percent(10_000));
 }
 
 #[test]
-fn test_update_owner_unauthorized() {
-    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
-    owner.funds = vec![];
-    env.block.time = env.block.time.plus_seconds(200);
-
-    let info = mock_info("random", &[]);
+fn test_query_solvency() {
+    let (mut deps, mut env, owner, _recipient) = instantiate_contract();
+    let total_amount = UNLOCKED_AMOUNT + CLIFF_AMOUNT + VESTING_AMOUNT;
+    env.block.time = Timestamp::from_seconds(VESTING_START_TIME + DAY_IN_SECONDS);
 
+    deps.querier
+        .update_balance(CONTRACT_ADDR, vec![coin(VESTING_AMOUNT / 4, "uluna")]);
+    deps.querier.update_staking(
+        "uluna",
+        &[],
+        &[FullDelegation {
+            delegator: Addr::unchecked(CONTRACT_ADDR),
+            validator: "validator".to_string(),
+            amount: coin(VESTING_AMOUNT / 2, "uluna"),
+            can_redelegate: coin(VESTING_AMOUNT / 2, "uluna"),
+            accumulated_rewards: vec![],
+        }],
+    );
     execute(
         deps.as_mut(),
         env.clone(),
-        info.clone(),
-        ExecuteMsg::UpdateOwner(UpdateOwnerMsg {
-            owner: "rando".to_string(),
+        owner,
+        ExecuteMsg::UndelegateFunds(UndelegateFundsMsg {
+            validator: "validator".to_string(),
+            amount: coin(VESTING_AMOUNT / 10, "uluna"),
         }),
     )
-    .unwrap_err();
+    .unwrap();
+
+    let res = query(deps.as_ref(), env, QueryMsg::QuerySolvency).unwrap();
+    let value: SolvencyResponse = from_binary(&res).unwrap();
+    let withdrawable_obligations = UNLOCKED_AMOUNT + CLIFF_AMOUNT + VESTED_PER_DAY;
+    assert_eq!(
+        value,
+        SolvencyResponse {
+            total_obligations: Uint128::new(total_amount),
+            withdrawable_obligations: Uint128::new(withdrawable_obligations),
+            liquid_balance: Uint128::new(VESTING_AMOUNT / 4),
+            delegated_balance: Uint128::new(VESTING_AMOUNT / 2),
+            unbonding_balance: Uint128::new(VESTING_AMOUNT / 10),
+            liquid_shortfall: Uint128::new(withdrawable_obligations - VESTING_AMOUNT / 4),
+            shortfall: Uint128::new(
+                total_amount - VESTING_AMOUNT / 4 - VESTING_AMOUNT / 2 - VESTING_AMOUNT / 10
+            ),
+        }
+    );
 }
 
 #[test]
-fn test_update_recipient_successful() {
-    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
-    owner.funds = vec![];
-    env.block.time = env.block.time.plus_seconds(200);
+fn test_amend_schedule_successful() {
+    let (mut deps, mut env, owner, _recipient) = instantiate_contract();
+    env.block.time = Timestamp::from_seconds(VESTING_START_TIME + DAY_IN_SECONDS * 2);
+    STATE
+        .save(
+            deps.as_mut().storage,
+            &State {
+                last_withdrawn_time: Uint64::new(VESTING_START_TIME + DAY_IN_SECONDS),
+                cliff_amount_withdrawn: Uint128::new(CLIFF_AMOUNT),
+                unlocked_amount_withdrawn: Uint128::new(UNLOCKED_AMOUNT),
+                recent_withdrawals: vec![],
+            },
+        )
+        .unwrap();
 
+    // doubling both the amount and the duration keeps the vesting rate
+    let new_end_time = VESTING_END_TIME * 2 - VESTING_START_TIME;
     execute(
         deps.as_mut(),
-        env.clone(),
-        owner.clone(),
-        ExecuteMsg::UpdateRecipient(UpdateRecipientMsg {
-            recipient: "rando".to_string(),
+        env,
+        owner,
+        ExecuteMsg::AmendSchedule(AmendScheduleMsg {
+            unlocked_amount: None,
+            cliff_amount: Some(Uint128::new(CLIFF_AMOUNT * 2)),
+            vesting_amount: Some(Uint128::new(VESTING_AMOUNT * 2)),
+            end_time: Some(Uint64::new(new_end_time)),
         }),
     )
     .unwrap();
 
     let config = CONFIG.load(deps.as_ref().storage).unwrap();
-    assert_eq!(config.recipient, Addr::unchecked("rando"));
+    assert_eq!(config.unlocked_amount, Uint128::new(UNLOCKED_AMOUNT));
+    assert_eq!(config.cliff_amount, Uint128::new(CLIFF_AMOUNT * 2));
+    assert_eq!(config.vesting_amount, Uint128::new(VESTING_AMOUNT * 2));
+    assert_eq!(config.end_time, Uint64::new(new_end_time));
+
+    // one day worth of vesting was already withdrawn under the previous schedule
+    let state = STATE.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        state,
+        State {
+            last_withdrawn_time: Uint64::new(VESTING_START_TIME + DAY_IN_SECONDS),
+            cliff_amount_withdrawn: Uint128::new(CLIFF_AMOUNT),
+            unlocked_amount_withdrawn: Uint128::new(UNLOCKED_AMOUNT),
+            recent_withdrawals: vec![],
+        }
+    );
 }
 
 #[test]
-fn test_update_recipient_unauthorized() {
-    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
-    owner.funds = vec![];
-    env.block.time = env.block.time.plus_seconds(200);
+fn test_amend_schedule_invalid() {
+    let (mut deps, mut env, owner, _recipient) = instantiate_contract();
+    env.block.time = Timestamp::from_seconds(VESTING_START_TIME + DAY_IN_SECONDS * 2);
 
-    let info = mock_info("random", &[]);
-
-    execute(
+    let res = execute(
         deps.as_mut(),
         env.clone(),
-        info.clone(),
-        ExecuteMsg::UpdateRecipient(UpdateRecipientMsg {
-            recipient: "rando".to_string(),
+        owner.clone(),
+        ExecuteMsg::AmendSchedule(AmendScheduleMsg {
+            unlocked_amount: None,
+            cliff_amount: Some(Uint128::new(CLIFF_AMOUNT - 1)),
+            vesting_amount: None,
+            end_time: None,
         }),
     )
     .unwrap_err();
-}
-
-#[test]
-fn test_delegate_funds_successful() {
-    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
-    owner.funds = vec![];
-    env.block.time = env.block.time.plus_seconds(200);
+    assert_eq!(
+        res,
+        ContractError::ValidationError("Amounts cannot be decreased".to_string())
+    );
 
     let res = execute(
         deps.as_mut(),
         env.clone(),
         owner.clone(),
-        ExecuteMsg::DelegateFunds(DelegateFundsMsg {
-            validator: "random".to_string(),
-            amount: Coin::new(100_000, "uluna"),
+        ExecuteMsg::AmendSchedule(AmendScheduleMsg {
+            unlocked_amount: None,
+            cliff_amount: None,
+            vesting_amount: None,
+            end_time: Some(Uint64::new(VESTING_END_TIME - 1)),
         }),
     )
-    .unwrap();
-
-    assert_eq!(res.messages.len(), 1);
+    .unwrap_err();
     assert_eq!(
-        res.messages[0],
-        SubMsg::new(CosmosMsg::Staking(StakingMsg::Delegate {
-            validator: "random".to_string(),
-            amount: Coin {
-                denom: "uluna".to_string(),
-                amount: Uint128::new(100_000),
-            },
-        }))
+        res,
+        ContractError::ValidationError("End time cannot be moved earlier".to_string())
     );
-}
-
-#[test]
-fn test_delegate_funds_unauthorized() {
-    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
-    owner.funds = vec![];
-    env.block.time = env.block.time.plus_seconds(200);
-
-    let info = mock_info("random", &[]);
 
+    // extending the end time alone slows down vesting
     let res = execute(
         deps.as_mut(),
-        env.clone(),
-        info.clone(),
-        ExecuteMsg::DelegateFunds(DelegateFundsMsg {
-            validator: "random".to_string(),
-            amount: Coin::new(100_000, "uluna"),
+        env,
+        owner,
+        ExecuteMsg::AmendSchedule(AmendScheduleMsg {
+            unlocked_amount: None,
+            cliff_amount: None,
+            vesting_amount: None,
+            end_time: Some(Uint64::new(VESTING_END_TIME + DAY_IN_SECONDS)),
         }),
     )
     .unwrap_err();
-    assert_eq!(res, ContractError::Unauthorized {});
+    assert_eq!(
+        res,
+        ContractError::ValidationError(
+            "Amendment cannot reduce the already vested amount".to_string()
+        )
+    );
 }
 
 #[test]
-fn test_undelegate_funds_successful() {
-    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
-    owner.funds = vec![];
-    env.block.time = env.block.time.plus_seconds(200);
+fn test_amend_schedule_validation() {
+    let (mut deps, mut env, owner, recipient) = instantiate_contract();
+    env.block.time = Timestamp::from_seconds(VESTING_START_TIME + DAY_IN_SECONDS * 2);
 
     let res = execute(
         deps.as_mut(),
         env.clone(),
         owner.clone(),
-        ExecuteMsg::UndelegateFunds(UndelegateFundsMsg {
-            validator: "random".to_string(),
-            amount: Coin::new(100_000, "uluna"),
+        ExecuteMsg::AmendSchedule(AmendScheduleMsg {
+            unlocked_amount: None,
+            cliff_amount: None,
+            vesting_amount: Some(Uint128::new(VESTING_AMOUNT * 100)),
+            end_time: Some(Uint64::new(VESTING_START_TIME + MAX_VESTING_DURATION + 1)),
         }),
     )
-    .unwrap();
-
-    assert_eq!(res.messages.len(), 1);
+    .unwrap_err();
     assert_eq!(
-        res.messages[0],
-        SubMsg::new(CosmosMsg::Staking(StakingMsg::Undelegate {
-            validator: "random".to_string(),
-            amount: Coin {
-                denom: "uluna".to_string(),
-                amount: Uint128::new(100_000),
-            },
-        }))
+        res,
+        ContractError::ValidationError("Vesting duration cannot exceed 100 years".to_string())
     );
-}
-
-#[test]
-fn test_undelegate_funds_unauthorized() {
-    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
-    owner.funds = vec![];
-    env.block.time = env.block.time.plus_seconds(200);
-
-    let info = mock_info("random", &[]);
 
     let res = execute(
         deps.as_mut(),
         env.clone(),
-        info.clone(),
-        ExecuteMsg::UndelegateFunds(UndelegateFundsMsg {
-            validator: "random".to_string(),
-            amount: Coin::new(100_000, "uluna"),
+        owner.clone(),
+        ExecuteMsg::AmendSchedule(AmendScheduleMsg {
+            unlocked_amount: Some(Uint128::MAX),
+            cliff_amount: None,
+            vesting_amount: None,
+            end_time: None,
         }),
     )
     .unwrap_err();
-    assert_eq!(res, ContractError::Unauthorized {});
-}
-
-#[test]
-fn test_redelegate_funds_successful() {
-    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
-    owner.funds = vec![];
-    env.block.time = env.block.time.plus_seconds(200);
+    assert_eq!(
+        res,
+        ContractError::ValidationError("Total amount overflows a Uint128".to_string())
+    );
 
+    // once everything is withdrawn, added vesting funds would vest at once without a later end time
+    env.block.time = Timestamp::from_seconds(VESTING_END_TIME + 1);
+    deps.querier.update_balance(
+        CONTRACT_ADDR,
+        vec![coin(
+            UNLOCKED_AMOUNT + CLIFF_AMOUNT + VESTING_AMOUNT,
+            "uluna",
+        )],
+    );
+    for msg in [
+        ExecuteMsg::WithdrawUnlockedFunds,
+        ExecuteMsg::WithdrawCliffVestedFunds,
+        ExecuteMsg::WithdrawVestedFunds,
+    ] {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            recipient.clone(),
+            msg(WithdrawVestedFundsMsg {
+                asset_info: AssetInfoUnchecked::native("uluna"),
+                destination: None,
+                msg: None,
+            }),
+        )
+        .unwrap();
+    }
     let res = execute(
         deps.as_mut(),
         env.clone(),
         owner.clone(),
-        ExecuteMsg::RedelegateFunds(RedelegateFundsMsg {
-            src_validator: "random".to_string(),
-            dst_validator: "another".to_string(),
-            amount: Coin::new(100_000, "uluna"),
+        ExecuteMsg::AmendSchedule(AmendScheduleMsg {
+            unlocked_amount: None,
+            cliff_amount: None,
+            vesting_amount: Some(Uint128::new(VESTING_AMOUNT * 2)),
+            end_time: None,
         }),
     )
-    .unwrap();
-
-    assert_eq!(res.messages.len(), 1);
+    .unwrap_err();
     assert_eq!(
-        res.messages[0],
-        SubMsg::new(CosmosMsg::Staking(StakingMsg::Redelegate {
-            src_validator: "random".to_string(),
-            dst_validator: "another".to_string(),
-            amount: Coin {
-                denom: "uluna".to_string(),
-                amount: Uint128::new(100_000),
-            },
-        }))
+        res,
+        ContractError::ValidationError(
+            "End time must be after the last withdrawal to add vesting funds".to_string()
+        )
     );
+    execute(
+        deps.as_mut(),
+        env,
+        owner,
+        ExecuteMsg::AmendSchedule(AmendScheduleMsg {
+            unlocked_amount: None,
+            cliff_amount: None,
+            vesting_amount: Some(Uint128::new(VESTING_AMOUNT * 2)),
+            end_time: Some(Uint64::new(VESTING_END_TIME + DAY_IN_SECONDS)),
+        }),
+    )
+    .unwrap();
 }
 
 #[test]
-fn test_redelegate_funds_unauthorized() {
-    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
-    owner.funds = vec![];
-    env.block.time = env.block.time.plus_seconds(200);
-
-    let info = mock_info("random", &[]);
+fn test_amend_schedule_unauthorized() {
+    let (mut deps, env, _owner, recipient) = instantiate_contract();
 
     let res = execute(
         deps.as_mut(),
-        env.clone(),
-        info.clone(),
-        ExecuteMsg::RedelegateFunds(RedelegateFundsMsg {
-            src_validator: "random".to_string(),
-            dst_validator: "another".to_string(),
-            amount: Coin::new(100_000, "uluna"),
+        env,
+        recipient,
+        ExecuteMsg::AmendSchedule(AmendScheduleMsg {
+            unlocked_amount: None,
+            cliff_amount: None,
+            vesting_amount: Some(Uint128::new(VESTING_AMOUNT * 2)),
+            end_time: None,
         }),
     )
     .unwrap_err();
@@ -1113,82 +1449,3392 @@ fn test_redelegate_funds_unauthorized() {
 }
 
 #[test]
-fn test_withdraw_delegator_reward_successful() {
-    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
-    owner.funds = vec![];
-    env.block.time = env.block.time.plus_seconds(200);
+fn test_pause_blocks_withdrawals() {
+    let (mut deps, mut env, owner, recipient) = instantiate_contract();
+    env.block.time = env.block.time.plus_seconds(DAY_IN_SECONDS);
 
-    let res = execute(
+    execute(
         deps.as_mut(),
         env.clone(),
         owner.clone(),
-        ExecuteMsg::WithdrawDelegatorReward(WithdrawDelegatorRewardMsg {
-            validator: "random".to_string(),
+        ExecuteMsg::UpdateGuardian(UpdateGuardianMsg {
+            guardian: Some("guardian".to_string()),
         }),
     )
     .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("guardian", &[]),
+        ExecuteMsg::Pause {},
+    )
+    .unwrap();
 
-    assert_eq!(res.messages.len(), 0);
-}
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::QueryPaused {}).unwrap();
+    let value: PausedResponse = from_binary(&res).unwrap();
+    assert!(value.paused);
 
-#[test]
-fn test_withdraw_delegator_reward_unauthorized() {
-    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
-    owner.funds = vec![];
-    env.block.time = env.block.time.plus_seconds(200);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        recipient.clone(),
+        ExecuteMsg::WithdrawUnlockedFunds(WithdrawVestedFundsMsg {
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(res, ContractError::Paused {});
 
-    let info = mock_info("random", &[]);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        recipient.clone(),
+        ExecuteMsg::WithdrawCliffVestedFunds(WithdrawVestedFundsMsg {
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(res, ContractError::Paused {});
 
     let res = execute(
         deps.as_mut(),
         env.clone(),
-        info.clone(),
+        recipient.clone(),
+        ExecuteMsg::WithdrawVestedFunds(WithdrawVestedFundsMsg {
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(res, ContractError::Paused {});
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
         ExecuteMsg::WithdrawDelegatorReward(WithdrawDelegatorRewardMsg {
             validator: "random".to_string(),
         }),
     )
     .unwrap_err();
+    assert_eq!(res, ContractError::Paused {});
+
+    // undelegating and recovery actions remain available
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::UndelegateFunds(UndelegateFundsMsg {
+            validator: "random".to_string(),
+            amount: Coin::new(100_000, "uluna"),
+        }),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::UpdateRecipient(UpdateRecipientMsg {
+            recipients: vec![("rando".to_string(), 10_000)],
+        }),
+    )
+    .unwrap();
+
+    execute(deps.as_mut(), env.clone(), owner, ExecuteMsg::Unpause {}).unwrap();
+    execute(
+        deps.as_mut(),
+        env,
+        recipient,
+        ExecuteMsg::WithdrawUnlockedFunds(WithdrawVestedFundsMsg {
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
+        }),
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_pause_unauthorized() {
+    let (mut deps, env, _owner, recipient) = instantiate_contract();
+
+    let res = execute(deps.as_mut(), env.clone(), recipient, ExecuteMsg::Pause {}).unwrap_err();
+    assert_eq!(res, ContractError::Unauthorized {});
+
+    let res = execute(
+        deps.as_mut(),
+        env,
+        mock_info("guardian", &[]),
+        ExecuteMsg::UpdateGuardian(UpdateGuardianMsg {
+            guardian: Some("guardian".to_string()),
+        }),
+    )
+    .unwrap_err();
     assert_eq!(res, ContractError::Unauthorized {});
 }
 
+fn set_withdrawal_delay(deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>, env: &Env) {
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("vlad", &[]),
+        ExecuteMsg::UpdateWithdrawalDelay(UpdateWithdrawalDelayMsg {
+            withdrawal_delay: Some(Uint64::new(DAY_IN_SECONDS)),
+        }),
+    )
+    .unwrap();
+}
+
 #[test]
-fn test_query_config() {
-    let (deps, mut env, mut owner, recipient) = instantiate_contract();
-    owner.funds = vec![];
-    env.block.time = env.block.time.plus_seconds(200);
+fn test_withdrawal_delay_finalize() {
+    let (mut deps, mut env, _owner, recipient) = instantiate_contract();
+    set_withdrawal_delay(&mut deps, &env);
 
-    let res = query(deps.as_ref(), env.clone(), QueryMsg::QueryConfig {}).unwrap();
-    let value: Config = from_binary(&res).unwrap();
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        recipient.clone(),
+        ExecuteMsg::WithdrawUnlockedFunds(WithdrawVestedFundsMsg {
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
+        }),
+    )
+    .unwrap();
+    assert_eq!(res.messages.len(), 0);
+
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::QueryPendingWithdrawals {
+            start_after: None,
+            limit: None,
+        },
+    )
+    .unwrap();
+    let value: PendingWithdrawalsResponse = from_binary(&res).unwrap();
     assert_eq!(
-        value,
-        Config {
-            owner: owner.sender.clone(),
-            recipient: recipient.sender.clone(),
-            unlocked_amount: Uint128::new(UNLOCKED_AMOUNT),
-            cliff_amount: Uint128::new(CLIFF_AMOUNT),
-            vesting_amount: Uint128::new(VESTING_AMOUNT),
-            start_time: Uint64::new(VESTING_START_TIME),
-            end_time: Uint64::new(VESTING_END_TIME),
-            whitelisted_addresses: vec![owner.sender, recipient.sender],
-        }
+        value.withdrawals,
+        vec![PendingWithdrawal {
+            id: 1,
+            tranche: Tranche::Unlocked,
+            asset_info: AssetInfo::native("uluna"),
+            amount: Uint128::new(UNLOCKED_AMOUNT),
+            release_time: Uint64::new(VESTING_START_TIME - 1 + DAY_IN_SECONDS),
+            destination: None,
+            withdrawn_at: Uint64::new(VESTING_START_TIME - 1),
+        }]
+    );
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        recipient.clone(),
+        ExecuteMsg::FinalizeWithdrawal(PendingWithdrawalMsg { id: 1 }),
+    )
+    .unwrap_err();
+    assert_eq!(res, ContractError::WithdrawalLocked {});
+
+    env.block.time = env.block.time.plus_seconds(DAY_IN_SECONDS);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        recipient.clone(),
+        ExecuteMsg::FinalizeWithdrawal(PendingWithdrawalMsg { id: 1 }),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            to_address: recipient.sender.to_string(),
+            amount: vec![Coin::new(UNLOCKED_AMOUNT, "uluna")],
+        }))
     );
+
+    // a finalized withdrawal cannot be finalized twice
+    execute(
+        deps.as_mut(),
+        env,
+        recipient,
+        ExecuteMsg::FinalizeWithdrawal(PendingWithdrawalMsg { id: 1 }),
+    )
+    .unwrap_err();
 }
 
 #[test]
-fn test_query_state() {
-    let (deps, mut env, mut owner, _recipient) = instantiate_contract();
-    owner.funds = vec![];
-    env.block.time = env.block.time.plus_seconds(200);
+fn test_withdrawal_delay_cancel() {
+    let (mut deps, mut env, owner, recipient) = instantiate_contract();
+    env.block.time = Timestamp::from_seconds(VESTING_START_TIME);
+    set_withdrawal_delay(&mut deps, &env);
+    deps.querier
+        .update_balance(CONTRACT_ADDR, vec![Coin::new(CLIFF_AMOUNT, "uluna")]);
 
-    let res = query(deps.as_ref(), env.clone(), QueryMsg::QueryState {}).unwrap();
-    let value: State = from_binary(&res).unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        recipient.clone(),
+        ExecuteMsg::WithdrawCliffVestedFunds(WithdrawVestedFundsMsg {
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
+        }),
+    )
+    .unwrap();
+
+    // the queued amount is reserved and cannot be withdrawn again
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        recipient.clone(),
+        ExecuteMsg::WithdrawUnlockedFunds(WithdrawVestedFundsMsg {
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(res, ContractError::NothingToWithdraw {});
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        recipient.clone(),
+        ExecuteMsg::CancelWithdrawal(PendingWithdrawalMsg { id: 1 }),
+    )
+    .unwrap_err();
+    assert_eq!(res, ContractError::Unauthorized {});
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner,
+        ExecuteMsg::CancelWithdrawal(PendingWithdrawalMsg { id: 1 }),
+    )
+    .unwrap();
+
+    let state = STATE.load(deps.as_ref().storage).unwrap();
+    assert_eq!(state.cliff_amount_withdrawn, Uint128::zero());
+
+    // the cliff entitlement can be queued again
+    execute(
+        deps.as_mut(),
+        env,
+        recipient,
+        ExecuteMsg::WithdrawCliffVestedFunds(WithdrawVestedFundsMsg {
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
+        }),
+    )
+    .unwrap();
+    let state = STATE.load(deps.as_ref().storage).unwrap();
+    assert_eq!(state.cliff_amount_withdrawn, Uint128::new(CLIFF_AMOUNT));
+}
+
+fn set_withdraw_limit(deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>, amount: u128) {
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    CONFIG
+        .save(
+            deps.as_mut().storage,
+            &Config {
+                max_withdraw_per_period: Some(WithdrawPeriodLimit {
+                    amount: Uint128::new(amount),
+                    period_seconds: Uint64::new(DAY_IN_SECONDS),
+                }),
+                ..config
+            },
+        )
+        .unwrap();
+}
+
+#[test]
+fn test_withdraw_limit_caps_withdrawal() {
+    let (mut deps, mut env, _owner, recipient) = instantiate_contract();
+    set_withdraw_limit(&mut deps, UNLOCKED_AMOUNT / 4);
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        recipient.clone(),
+        ExecuteMsg::WithdrawUnlockedFunds(WithdrawVestedFundsMsg {
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
+        }),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: recipient.sender.to_string(),
+            amount: vec![Coin::new(UNLOCKED_AMOUNT / 4, "uluna")],
+        })
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        recipient.clone(),
+        ExecuteMsg::WithdrawUnlockedFunds(WithdrawVestedFundsMsg {
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::WithdrawLimitReached {});
+
+    // the allowance is restored once the period has passed
+    env.block.time = env.block.time.plus_seconds(DAY_IN_SECONDS);
+    let res = execute(
+        deps.as_mut(),
+        env,
+        recipient.clone(),
+        ExecuteMsg::WithdrawUnlockedFunds(WithdrawVestedFundsMsg {
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
+        }),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: recipient.sender.to_string(),
+            amount: vec![Coin::new(UNLOCKED_AMOUNT / 4, "uluna")],
+        })
+    );
 
+    let state = STATE.load(deps.as_ref().storage).unwrap();
     assert_eq!(
-        value,
-        State {
-            last_withdrawn_time: Uint64::new(1735707600),
-            cliff_amount_withdrawn: Uint128::new(0),
-            unlocked_amount_withdrawn: Uint128::new(0),
+        state.unlocked_amount_withdrawn,
+        Uint128::new(UNLOCKED_AMOUNT / 2)
+    );
+    assert_eq!(state.recent_withdrawals.len(), 1);
+}
+
+#[test]
+fn test_withdraw_limit_restored_on_cancel() {
+    let (mut deps, env, owner, recipient) = instantiate_contract();
+    set_withdraw_limit(&mut deps, UNLOCKED_AMOUNT / 4);
+    set_withdrawal_delay(&mut deps, &env);
+    let withdraw_unlocked = ExecuteMsg::WithdrawUnlockedFunds(WithdrawVestedFundsMsg {
+        asset_info: AssetInfoUnchecked::native("uluna"),
+        destination: None,
+        msg: None,
+    });
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        recipient.clone(),
+        withdraw_unlocked.clone(),
+    )
+    .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        recipient.clone(),
+        withdraw_unlocked.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::WithdrawLimitReached {});
+
+    // the cancelled amount no longer counts against the allowance of the period
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner,
+        ExecuteMsg::CancelWithdrawal(PendingWithdrawalMsg { id: 1 }),
+    )
+    .unwrap();
+    let state = STATE.load(deps.as_ref().storage).unwrap();
+    assert_eq!(state.recent_withdrawals, vec![]);
+
+    let res = execute(deps.as_mut(), env, recipient, withdraw_unlocked).unwrap();
+    assert!(res
+        .events
+        .iter()
+        .flat_map(|event| &event.attributes)
+        .any(|attr| attr.key == "amount" && attr.value == (UNLOCKED_AMOUNT / 4).to_string()));
+    let state = STATE.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        state.unlocked_amount_withdrawn,
+        Uint128::new(UNLOCKED_AMOUNT / 4)
+    );
+}
+
+#[test]
+fn test_query_withdrawable() {
+    let (mut deps, env, _owner, recipient) = instantiate_contract();
+    set_withdraw_limit(&mut deps, UNLOCKED_AMOUNT / 4);
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        recipient,
+        ExecuteMsg::WithdrawUnlockedFunds(WithdrawVestedFundsMsg {
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
+        }),
+    )
+    .unwrap();
+
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::QueryWithdrawable {}).unwrap();
+    let value: WithdrawableResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        value,
+        WithdrawableResponse {
+            unlocked_amount: Uint128::new(UNLOCKED_AMOUNT / 4 * 3),
+            cliff_amount: Uint128::zero(),
+            vesting_amount: Uint128::zero(),
+            // the first uluna vests in the first second of the schedule
+            next_unlock_time: Some(Uint64::new(VESTING_START_TIME + 1)),
+            period_allowance: Some(Uint128::zero()),
+        }
+    );
+
+    let res = query(
+        deps.as_ref(),
+        Env {
+            block: BlockInfo {
+                time: env.block.time.plus_seconds(DAY_IN_SECONDS + 1),
+                ..env.block
+            },
+            ..env
+        },
+        QueryMsg::QueryWithdrawable {},
+    )
+    .unwrap();
+    let value: WithdrawableResponse = from_binary(&res).unwrap();
+    assert_eq!(value.cliff_amount, Uint128::new(CLIFF_AMOUNT));
+    assert_eq!(
+        value.period_allowance,
+        Some(Uint128::new(UNLOCKED_AMOUNT / 4))
+    );
+}
+
+#[test]
+fn test_add_to_whitelist_successful() {
+    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
+    owner.funds = vec![];
+    env.block.time = env.block.time.plus_seconds(200);
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::AddToWhitelist(AddToWhitelistMsg {
+            addresses: vec![Addr::unchecked("warp")],
+        }),
+    )
+    .unwrap();
+    assert_eq!(
+        res.events,
+        vec![Event::new("vesting-admin")
+            .add_attribute("action", "add_to_whitelist")
+            .add_attribute("addresses", "warp")
+            .add_attribute("whitelisted_addresses", "vlad,javier,warp")]
+    );
+
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        config.whitelisted_addresses,
+        vec![
+            Addr::unchecked("vlad"),
+            Addr::unchecked("javier"),
+            Addr::unchecked("warp")
+        ]
+    );
+}
+
+#[test]
+fn test_add_to_whitelist_not_owner() {
+    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
+    owner.funds = vec![];
+    env.block.time = env.block.time.plus_seconds(200);
+
+    let info = mock_info("random", &[]);
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::AddToWhitelist(AddToWhitelistMsg {
+            addresses: vec![Addr::unchecked("warp")],
+        }),
+    )
+    .unwrap_err();
+}
+
+#[test]
+fn test_add_to_whitelist_already_included() {
+    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
+    owner.funds = vec![];
+    env.block.time = env.block.time.plus_seconds(200);
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::AddToWhitelist(AddToWhitelistMsg {
+            addresses: vec![Addr::unchecked("javier")],
+        }),
+    )
+    .unwrap();
+
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        config.whitelisted_addresses,
+        vec![Addr::unchecked("vlad"), Addr::unchecked("javier")]
+    );
+}
+
+#[test]
+fn test_remove_from_whitelist_successful() {
+    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
+    owner.funds = vec![];
+    env.block.time = env.block.time.plus_seconds(200);
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::AddToWhitelist(AddToWhitelistMsg {
+            addresses: vec![Addr::unchecked("warp")],
+        }),
+    )
+    .unwrap();
+
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        config.whitelisted_addresses,
+        vec![
+            Addr::unchecked("vlad"),
+            Addr::unchecked("javier"),
+            Addr::unchecked("warp")
+        ]
+    );
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::RemoveFromWhitelist(RemoveFromWhitelistMsg {
+            addresses: vec![Addr::unchecked("warp")],
+        }),
+    )
+    .unwrap();
+
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        config.whitelisted_addresses,
+        vec![Addr::unchecked("vlad"), Addr::unchecked("javier")]
+    );
+}
+
+#[test]
+fn test_remove_from_whitelist_not_owner() {
+    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
+    owner.funds = vec![];
+    env.block.time = env.block.time.plus_seconds(200);
+
+    let info = mock_info("random", &[]);
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::RemoveFromWhitelist(RemoveFromWhitelistMsg {
+            addresses: vec![Addr::unchecked("warp")],
+        }),
+    )
+    .unwrap_err();
+}
+
+#[test]
+fn test_remove_recipient_from_whitelist() {
+    let (mut deps, mut env, mut owner, recipient) = instantiate_contract();
+    owner.funds = vec![];
+    env.block.time = env.block.time.plus_seconds(200);
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::RemoveFromWhitelist(RemoveFromWhitelistMsg {
+            addresses: vec![recipient.sender.clone()],
+        }),
+    )
+    .unwrap();
+
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        config.whitelisted_addresses,
+        vec![owner.sender, recipient.sender]
+    );
+}
+
+#[test]
+fn test_remove_owner_from_whitelist() {
+    let (mut deps, mut env, mut owner, recipient) = instantiate_contract();
+    owner.funds = vec![];
+    env.block.time = env.block.time.plus_seconds(200);
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::RemoveFromWhitelist(RemoveFromWhitelistMsg {
+            addresses: vec![owner.sender.clone()],
+        }),
+    )
+    .unwrap();
+
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        config.whitelisted_addresses,
+        vec![owner.sender, recipient.sender]
+    );
+}
+
+#[test]
+fn test_remove_owner_from_whitelist_with_accs() {
+    let (mut deps, mut env, mut owner, recipient) = instantiate_contract();
+    owner.funds = vec![];
+    env.block.time = env.block.time.plus_seconds(200);
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::AddToWhitelist(AddToWhitelistMsg {
+            addresses: vec![Addr::unchecked("warp")],
+        }),
+    )
+    .unwrap();
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::RemoveFromWhitelist(RemoveFromWhitelistMsg {
+            addresses: vec![owner.sender.clone()],
+        }),
+    )
+    .unwrap();
+
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        config.whitelisted_addresses,
+        vec![owner.sender, recipient.sender, Addr::unchecked("warp")]
+    );
+}
+
+#[test]
+fn test_update_owner_successful() {
+    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
+    owner.funds = vec![];
+    env.block.time = env.block.time.plus_seconds(200);
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::UpdateOwner(UpdateOwnerMsg {
+            owner: "rando".to_string(),
+        }),
+    )
+    .unwrap();
+
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    assert_eq!(config.owner, Addr::unchecked("rando"));
+    assert_eq!(
+        config.whitelisted_addresses,
+        vec![Addr::unchecked("rando"), Addr::unchecked("javier")]
+    );
+}
+
+#[test]
+fn test_update_owner_unauthorized() {
+    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
+    owner.funds = vec![];
+    env.block.time = env.block.time.plus_seconds(200);
+
+    let info = mock_info("random", &[]);
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::UpdateOwner(UpdateOwnerMsg {
+            owner: "rando".to_string(),
+        }),
+    )
+    .unwrap_err();
+}
+
+#[test]
+fn test_update_recipient_successful() {
+    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
+    owner.funds = vec![];
+    env.block.time = env.block.time.plus_seconds(200);
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::UpdateRecipient(UpdateRecipientMsg {
+            recipients: vec![("rando".to_string(), 7_000), ("javier".to_string(), 3_000)],
+        }),
+    )
+    .unwrap();
+    assert_eq!(
+        res.events,
+        vec![Event::new("vesting-admin")
+            .add_attribute("action", "update_recipient")
+            .add_attribute("recipients", "rando:7000,javier:3000")]
+    );
+
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        config.recipients,
+        vec![
+            (Addr::unchecked("rando"), 7_000),
+            (Addr::unchecked("javier"), 3_000)
+        ]
+    );
+}
+
+#[test]
+fn test_update_recipient_unauthorized() {
+    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
+    owner.funds = vec![];
+    env.block.time = env.block.time.plus_seconds(200);
+
+    let info = mock_info("random", &[]);
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::UpdateRecipient(UpdateRecipientMsg {
+            recipients: vec![("rando".to_string(), 10_000)],
+        }),
+    )
+    .unwrap_err();
+}
+
+#[test]
+fn test_update_recipient_invalid_shares() {
+    let (mut deps, env, owner, _recipient) = instantiate_contract();
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::UpdateRecipient(UpdateRecipientMsg {
+            recipients: vec![("rando".to_string(), 5_000), ("javier".to_string(), 4_000)],
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::ValidationError(
+            "Recipient shares must sum to 10000 basis points".to_string()
+        )
+    );
+
+    let err = execute(
+        deps.as_mut(),
+        env,
+        owner,
+        ExecuteMsg::UpdateRecipient(UpdateRecipientMsg {
+            recipients: vec![("rando".to_string(), 5_000), ("rando".to_string(), 5_000)],
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::ValidationError("Duplicate recipient".to_string())
+    );
+}
+
+#[test]
+fn test_withdraw_split_between_recipients() {
+    let (mut deps, env, owner, recipient) = instantiate_contract();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::UpdateRecipient(UpdateRecipientMsg {
+            recipients: vec![
+                (recipient.sender.to_string(), 5_000),
+                ("rando".to_string(), 5_000),
+            ],
+        }),
+    )
+    .unwrap();
+    // an odd amount leaves one unit of dust for the first recipient
+    set_withdraw_limit(&mut deps, 1_000_001);
+
+    let res = execute(
+        deps.as_mut(),
+        env,
+        owner,
+        ExecuteMsg::WithdrawUnlockedFunds(WithdrawVestedFundsMsg {
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
+        }),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages
+            .into_iter()
+            .map(|msg| msg.msg)
+            .collect::<Vec<CosmosMsg>>(),
+        vec![
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: recipient.sender.to_string(),
+                amount: vec![Coin::new(500_001, "uluna")],
+            }),
+            CosmosMsg::Bank(BankMsg::Send {
+                to_address: "rando".to_string(),
+                amount: vec![Coin::new(500_000, "uluna")],
+            }),
+        ]
+    );
+}
+
+fn query_withdrawal_history(
+    deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>,
+    env: &Env,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+    tranche: Option<Tranche>,
+    sender: Option<&str>,
+) -> Vec<WithdrawalHistoryEntry> {
+    let res: WithdrawalHistoryResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::QueryWithdrawalHistory {
+                start_after,
+                limit,
+                tranche,
+                sender: sender.map(|sender| sender.to_string()),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    res.withdrawals
+}
+
+#[test]
+fn test_withdrawal_history() {
+    let (mut deps, mut env, owner, recipient) = instantiate_contract();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::UpdateRecipient(UpdateRecipientMsg {
+            recipients: vec![
+                (recipient.sender.to_string(), 5_000),
+                ("rando".to_string(), 5_000),
+            ],
+        }),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::WithdrawUnlockedFunds(WithdrawVestedFundsMsg {
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
+        }),
+    )
+    .unwrap();
+    env.block.time = Timestamp::from_seconds(VESTING_START_TIME);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        recipient.clone(),
+        ExecuteMsg::WithdrawCliffVestedFunds(WithdrawVestedFundsMsg {
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
+        }),
+    )
+    .unwrap();
+
+    // every recipient's share is recorded separately
+    let history = query_withdrawal_history(&deps, &env, None, None, None, None);
+    assert_eq!(history.len(), 4);
+    assert_eq!(
+        history[1],
+        WithdrawalHistoryEntry {
+            id: 2,
+            tranche: Tranche::Unlocked,
+            denom: "uluna".to_string(),
+            amount: Uint128::new(UNLOCKED_AMOUNT / 2),
+            sender: owner.sender.clone(),
+            recipient: "rando".to_string(),
+            time: Uint64::new(VESTING_START_TIME - 1),
+        }
+    );
+
+    let cliff = query_withdrawal_history(&deps, &env, None, None, Some(Tranche::Cliff), None);
+    assert_eq!(
+        cliff.iter().map(|entry| entry.id).collect::<Vec<u64>>(),
+        vec![3, 4]
+    );
+    assert!(cliff.iter().all(|entry| entry.sender == recipient.sender));
+
+    let page = query_withdrawal_history(&deps, &env, Some(1), Some(2), None, None);
+    assert_eq!(
+        page.iter().map(|entry| entry.id).collect::<Vec<u64>>(),
+        vec![2, 3]
+    );
+    let page = query_withdrawal_history(&deps, &env, Some(3), None, Some(Tranche::Cliff), None);
+    assert_eq!(
+        page.iter().map(|entry| entry.id).collect::<Vec<u64>>(),
+        vec![4]
+    );
+
+    // filtered by the sender that executed the withdrawal, also within a tranche
+    let by_owner = query_withdrawal_history(&deps, &env, None, None, None, Some("vlad"));
+    assert_eq!(
+        by_owner.iter().map(|entry| entry.id).collect::<Vec<u64>>(),
+        vec![1, 2]
+    );
+    let by_recipient = query_withdrawal_history(
+        &deps,
+        &env,
+        None,
+        None,
+        Some(Tranche::Unlocked),
+        Some("javier"),
+    );
+    assert!(by_recipient.is_empty());
+}
+
+#[test]
+fn test_migrate_single_recipient_config() {
+    let (deps, env, _owner, recipient) = instantiate_contract();
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+
+    // config and state exactly as stored by the single recipient contract
+    let mut deps = mock_dependencies_with_contract_balance(Uint128::new(UNLOCKED_AMOUNT));
+    deps.as_mut().storage.set(
+        b"config",
+        format!(
+            r#"{{"owner":"vlad","recipient":"javier","unlocked_amount":"{}","cliff_amount":"{}","vesting_amount":"{}","start_time":"{}","end_time":"{}","whitelisted_addresses":["vlad","javier"]}}"#,
+            UNLOCKED_AMOUNT, CLIFF_AMOUNT, VESTING_AMOUNT, VESTING_START_TIME, VESTING_END_TIME
+        )
+        .as_bytes(),
+    );
+    deps.as_mut().storage.set(
+        b"state",
+        format!(
+            r#"{{"last_withdrawn_time":"{}","cliff_amount_withdrawn":"0","unlocked_amount_withdrawn":"0"}}"#,
+            VESTING_START_TIME
+        )
+        .as_bytes(),
+    );
+
+    migrate(
+        deps.as_mut(),
+        env.clone(),
+        MigrateMsg {
+            governance_owner: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(CONFIG.load(deps.as_ref().storage).unwrap(), config);
+    // the position keeps the token id of its recipient index
+    let res: OwnerOfResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::OwnerOf {
+                token_id: "0".to_string(),
+                include_expired: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.owner, recipient.sender.to_string());
+
+    // the migrated contract keeps paying out the schedule
+    let res = execute(
+        deps.as_mut(),
+        env,
+        recipient.clone(),
+        ExecuteMsg::WithdrawUnlockedFunds(WithdrawVestedFundsMsg {
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
+        }),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: recipient.sender.to_string(),
+            amount: vec![coin(UNLOCKED_AMOUNT, "uluna")],
+        })
+    );
+}
+
+fn withdraw_unlocked_to(
+    deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>,
+    env: &Env,
+    info: &MessageInfo,
+    destination: Option<&str>,
+    msg: Option<Binary>,
+) -> Result<Response, ContractError> {
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::WithdrawUnlockedFunds(WithdrawVestedFundsMsg {
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: destination.map(|destination| destination.to_string()),
+            msg,
+        }),
+    )
+}
+
+#[test]
+fn test_withdraw_to_approved_destination() {
+    let (mut deps, env, owner, recipient) = instantiate_contract();
+    let hook = to_binary(&"deposit").unwrap();
+
+    let err = withdraw_unlocked_to(
+        &mut deps,
+        &env,
+        &owner,
+        Some("treasury"),
+        Some(hook.clone()),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::DestinationNotApproved {});
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        recipient.clone(),
+        ExecuteMsg::ApproveDestination(DestinationMsg {
+            destination: "treasury".to_string(),
+            msg: Some(hook.clone()),
+        }),
+    )
+    .unwrap();
+    let res = query(
+        deps.as_ref(),
+        env.clone(),
+        QueryMsg::QueryApprovedDestinations {
+            recipient: recipient.sender.to_string(),
+        },
+    )
+    .unwrap();
+    let value: ApprovedDestinationsResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        value.destinations,
+        vec![PayoutDestination {
+            address: Addr::unchecked("treasury"),
+            msg: Some(hook.clone()),
+        }]
+    );
+
+    let res = withdraw_unlocked_to(
+        &mut deps,
+        &env,
+        &owner,
+        Some("treasury"),
+        Some(hook.clone()),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: "treasury".to_string(),
+            msg: hook,
+            funds: vec![Coin::new(UNLOCKED_AMOUNT, "uluna")],
+        })
+    );
+}
+
+#[test]
+fn test_withdraw_destination_hook_not_approved() {
+    let (mut deps, env, owner, recipient) = instantiate_contract();
+    let keeper = mock_info("warp", &[]);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::AddToWhitelist(AddToWhitelistMsg {
+            addresses: vec![keeper.sender.clone()],
+        }),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        recipient,
+        ExecuteMsg::ApproveDestination(DestinationMsg {
+            destination: "treasury".to_string(),
+            msg: Some(to_binary(&"deposit").unwrap()),
+        }),
+    )
+    .unwrap();
+
+    // neither the owner nor a keeper can pick another hook, or drop the approved one
+    let drain = Some(to_binary(&"withdraw_to_owner").unwrap());
+    for sender in [&owner, &keeper] {
+        let err = withdraw_unlocked_to(&mut deps, &env, sender, Some("treasury"), drain.clone())
+            .unwrap_err();
+        assert_eq!(err, ContractError::DestinationNotApproved {});
+        let err =
+            withdraw_unlocked_to(&mut deps, &env, sender, Some("treasury"), None).unwrap_err();
+        assert_eq!(err, ContractError::DestinationNotApproved {});
+    }
+}
+
+#[test]
+fn test_withdraw_destination_invalid() {
+    let (mut deps, env, owner, recipient) = instantiate_contract();
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::ApproveDestination(DestinationMsg {
+            destination: "treasury".to_string(),
+            msg: None,
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    let err = withdraw_unlocked_to(
+        &mut deps,
+        &env,
+        &owner,
+        None,
+        Some(to_binary(&"deposit").unwrap()),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::ValidationError("A hook message requires a destination".to_string())
+    );
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        recipient.clone(),
+        ExecuteMsg::ApproveDestination(DestinationMsg {
+            destination: "treasury".to_string(),
+            msg: None,
+        }),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        recipient,
+        ExecuteMsg::RevokeDestination(DestinationMsg {
+            destination: "treasury".to_string(),
+            msg: None,
+        }),
+    )
+    .unwrap();
+    let err = withdraw_unlocked_to(&mut deps, &env, &owner, Some("treasury"), None).unwrap_err();
+    assert_eq!(err, ContractError::DestinationNotApproved {});
+}
+
+fn withdraw_unlocked_over_ibc(
+    deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>,
+    env: &Env,
+    owner: &MessageInfo,
+) {
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::UpdateIbcPayout(UpdateIbcPayoutMsg {
+            ibc_payout: Some(IbcPayout {
+                recipient: "javier".to_string(),
+                channel_id: "channel-1".to_string(),
+                remote_address: "osmo1recipient".to_string(),
+                timeout_seconds: Uint64::new(600),
+            }),
+        }),
+    )
+    .unwrap();
+
+    let res = withdraw_unlocked_to(deps, env, owner, None, None).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::reply_on_success(
+            transfer_msg(
+                "channel-1",
+                &Coin::new(UNLOCKED_AMOUNT, "uluna"),
+                &env.contract.address,
+                "osmo1recipient",
+                env.block.time.plus_seconds(600),
+            ),
+            1,
+        )]
+    );
+
+    // MsgTransferResponse { sequence: 7 }
+    reply(
+        deps.as_mut(),
+        env.clone(),
+        Reply {
+            id: 1,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(Binary::from(vec![0x08, 0x07])),
+            }),
+        },
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_ibc_transfer_msg() {
+    let CosmosMsg::Stargate { type_url, value } = transfer_msg(
+        "channel-1",
+        &Coin::new(5, "uluna"),
+        &Addr::unchecked("cosmos2contract"),
+        "osmo1recipient",
+        Timestamp::from_nanos(300),
+    ) else {
+        panic!("expected a stargate message");
+    };
+    assert_eq!(type_url, "/ibc.applications.transfer.v1.MsgTransfer");
+
+    // port, channel, token, sender, receiver, timeout timestamp and the ibc-hooks callback memo
+    let memo = br#"{"ibc_callback":"cosmos2contract"}"#;
+    let mut expected = vec![0x0a, 8];
+    expected.extend_from_slice(b"transfer");
+    expected.extend_from_slice(&[0x12, 9]);
+    expected.extend_from_slice(b"channel-1");
+    expected.extend_from_slice(&[0x1a, 10, 0x0a, 5]);
+    expected.extend_from_slice(b"uluna");
+    expected.extend_from_slice(&[0x12, 1, b'5', 0x22, 15]);
+    expected.extend_from_slice(b"cosmos2contract");
+    expected.extend_from_slice(&[0x2a, 14]);
+    expected.extend_from_slice(b"osmo1recipient");
+    expected.extend_from_slice(&[0x38, 0xac, 0x02, 0x42, memo.len() as u8]);
+    expected.extend_from_slice(memo);
+    assert_eq!(value, Binary::from(expected));
+}
+
+#[test]
+fn test_ibc_payout_acknowledged() {
+    let (mut deps, env, owner, _recipient) = instantiate_contract();
+    withdraw_unlocked_over_ibc(&mut deps, &env, &owner);
+    assert!(IBC_TRANSFERS.has(deps.as_ref().storage, ("channel-1", 7)));
+
+    let res = sudo(
+        deps.as_mut(),
+        env.clone(),
+        SudoMsg::IbcLifecycleComplete(IbcLifecycleComplete::IbcAck {
+            channel: "channel-1".to_string(),
+            sequence: 7,
+            ack: "AQ==".to_string(),
+            success: true,
+        }),
+    )
+    .unwrap();
+    assert_eq!(
+        res.events,
+        vec![Event::new("vesting-withdraw")
+            .add_attribute("action", "ibc_transfer_complete")
+            .add_attribute("channel_id", "channel-1")
+            .add_attribute("sequence", "7")
+            .add_attribute("tranche", "unlocked")
+            .add_attribute("amount", UNLOCKED_AMOUNT.to_string())
+            .add_attribute("success", "true")]
+    );
+
+    // the delivered transfer stays withdrawn and in the history
+    assert!(!IBC_TRANSFERS.has(deps.as_ref().storage, ("channel-1", 7)));
+    let state = STATE.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        state.unlocked_amount_withdrawn,
+        Uint128::new(UNLOCKED_AMOUNT)
+    );
+    let history = query_withdrawal_history(&deps, &env, None, None, None, None);
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].recipient, "osmo1recipient");
+}
+
+#[test]
+fn test_ibc_payout_splits_by_share() {
+    let (mut deps, env, owner, recipient) = instantiate_contract();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::UpdateRecipient(UpdateRecipientMsg {
+            recipients: vec![
+                (recipient.sender.to_string(), 6_000),
+                ("alice".to_string(), 4_000),
+            ],
+        }),
+    )
+    .unwrap();
+
+    // the ibc payout must go to one of the recipients
+    let update_ibc_payout = |recipient: &str| {
+        ExecuteMsg::UpdateIbcPayout(UpdateIbcPayoutMsg {
+            ibc_payout: Some(IbcPayout {
+                recipient: recipient.to_string(),
+                channel_id: "channel-1".to_string(),
+                remote_address: "osmo1recipient".to_string(),
+                timeout_seconds: Uint64::new(600),
+            }),
+        })
+    };
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        update_ibc_payout("rando"),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::ValidationError("IBC payout recipient must be a recipient".to_string())
+    );
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        update_ibc_payout("javier"),
+    )
+    .unwrap();
+
+    // only the share of the ibc payout recipient leaves over IBC
+    let res = withdraw_unlocked_to(&mut deps, &env, &owner, None, None).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::reply_on_success(
+                transfer_msg(
+                    "channel-1",
+                    &Coin::new(UNLOCKED_AMOUNT * 6 / 10, "uluna"),
+                    &env.contract.address,
+                    "osmo1recipient",
+                    env.block.time.plus_seconds(600),
+                ),
+                1,
+            ),
+            SubMsg::new(BankMsg::Send {
+                to_address: "alice".to_string(),
+                amount: vec![Coin::new(UNLOCKED_AMOUNT * 4 / 10, "uluna")],
+            }),
+        ]
+    );
+    reply(
+        deps.as_mut(),
+        env.clone(),
+        Reply {
+            id: 1,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(Binary::from(vec![0x08, 0x07])),
+            }),
+        },
+    )
+    .unwrap();
+
+    // a timeout refunds the share sent over IBC to its recipient alone
+    let res = sudo(
+        deps.as_mut(),
+        env.clone(),
+        SudoMsg::IbcLifecycleComplete(IbcLifecycleComplete::IbcTimeout {
+            channel: "channel-1".to_string(),
+            sequence: 7,
+        }),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "javier".to_string(),
+            amount: vec![Coin::new(UNLOCKED_AMOUNT * 6 / 10, "uluna")],
+        })]
+    );
+    let history = query_withdrawal_history(&deps, &env, None, None, None, None);
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[0].recipient, "javier");
+    assert_eq!(history[0].amount, Uint128::new(UNLOCKED_AMOUNT * 6 / 10));
+    assert_eq!(history[1].recipient, "alice");
+    assert_eq!(history[1].amount, Uint128::new(UNLOCKED_AMOUNT * 4 / 10));
+
+    // both shares are paid, so nothing is owed to either recipient anymore
+    let state = STATE.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        state.unlocked_amount_withdrawn,
+        Uint128::new(UNLOCKED_AMOUNT)
+    );
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::QueryWithdrawable {}).unwrap();
+    let withdrawable: WithdrawableResponse = from_binary(&res).unwrap();
+    assert_eq!(withdrawable.unlocked_amount, Uint128::zero());
+    let err = withdraw_unlocked_to(&mut deps, &env, &owner, None, None).unwrap_err();
+    assert_eq!(err, ContractError::NothingToWithdraw {});
+}
+
+#[test]
+fn test_ibc_payout_timeout_refunds_recipient() {
+    let (mut deps, env, owner, _recipient) = instantiate_contract();
+    withdraw_unlocked_over_ibc(&mut deps, &env, &owner);
+
+    let res = sudo(
+        deps.as_mut(),
+        env.clone(),
+        SudoMsg::IbcLifecycleComplete(IbcLifecycleComplete::IbcTimeout {
+            channel: "channel-1".to_string(),
+            sequence: 7,
+        }),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "javier".to_string(),
+            amount: vec![Coin::new(UNLOCKED_AMOUNT, "uluna")],
+        })]
+    );
+    assert_eq!(
+        res.events,
+        vec![Event::new("vesting-withdraw")
+            .add_attribute("action", "ibc_transfer_complete")
+            .add_attribute("channel_id", "channel-1")
+            .add_attribute("sequence", "7")
+            .add_attribute("tranche", "unlocked")
+            .add_attribute("amount", UNLOCKED_AMOUNT.to_string())
+            .add_attribute("success", "false")
+            .add_attribute("refund_recipient", "javier")]
+    );
+
+    // the refund is paid locally, so the tranche stays withdrawn and the history points at the recipient
+    let state = STATE.load(deps.as_ref().storage).unwrap();
+    assert_eq!(
+        state.unlocked_amount_withdrawn,
+        Uint128::new(UNLOCKED_AMOUNT)
+    );
+    let history = query_withdrawal_history(&deps, &env, None, None, None, None);
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].recipient, "javier");
+
+    // a repeated callback for the same packet is ignored
+    let res = sudo(
+        deps.as_mut(),
+        env,
+        SudoMsg::IbcLifecycleComplete(IbcLifecycleComplete::IbcTimeout {
+            channel: "channel-1".to_string(),
+            sequence: 7,
+        }),
+    )
+    .unwrap();
+    assert!(res.events.is_empty());
+}
+
+#[test]
+fn test_delegate_funds_successful() {
+    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
+    owner.funds = vec![];
+    env.block.time = env.block.time.plus_seconds(200);
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::DelegateFunds(DelegateFundsMsg {
+            validator: "random".to_string(),
+            amount: Coin::new(100_000, "uluna"),
+        }),
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Staking(StakingMsg::Delegate {
+            validator: "random".to_string(),
+            amount: Coin {
+                denom: "uluna".to_string(),
+                amount: Uint128::new(100_000),
+            },
+        }))
+    );
+}
+
+#[test]
+fn test_delegate_funds_unauthorized() {
+    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
+    owner.funds = vec![];
+    env.block.time = env.block.time.plus_seconds(200);
+
+    let info = mock_info("random", &[]);
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::DelegateFunds(DelegateFundsMsg {
+            validator: "random".to_string(),
+            amount: Coin::new(100_000, "uluna"),
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(res, ContractError::Unauthorized {});
+}
+
+#[test]
+fn test_liquidity_guard() {
+    let (mut deps, mut env, owner, _recipient) = instantiate_contract();
+    env.block.time = Timestamp::from_seconds(VESTING_START_TIME + DAY_IN_SECONDS);
+    let delegate = |amount: u128, denom: &str| {
+        ExecuteMsg::DelegateFunds(DelegateFundsMsg {
+            validator: "validator".to_string(),
+            amount: coin(amount, denom),
+        })
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("javier", &[]),
+        ExecuteMsg::UpdateLiquidityGuard(UpdateLiquidityGuardMsg {
+            liquidity_guard: true,
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::UpdateLiquidityGuard(UpdateLiquidityGuardMsg {
+            liquidity_guard: true,
+        }),
+    )
+    .unwrap();
+    assert_eq!(
+        res.events,
+        vec![Event::new("vesting-admin")
+            .add_attribute("action", "update_liquidity_guard")
+            .add_attribute("liquidity_guard", "true")]
+    );
+    assert!(CONFIG.load(deps.as_ref().storage).unwrap().liquidity_guard);
+
+    // the unlocked and cliff amounts and the first day of vesting have to stay liquid
+    let withdrawable = UNLOCKED_AMOUNT + CLIFF_AMOUNT + VESTED_PER_DAY;
+    let balance = VESTING_AMOUNT + CLIFF_AMOUNT;
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        delegate(balance - withdrawable + 1, "uluna"),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::InsufficientLiquidity {});
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        delegate(balance + 1, "uluna"),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::InsufficientLiquidity {});
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        delegate(balance - withdrawable, "uluna"),
+    )
+    .unwrap();
+
+    // other denoms are not vested, delegating them is not guarded
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        delegate(balance, "uusd"),
+    )
+    .unwrap();
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::UpdateLiquidityGuard(UpdateLiquidityGuardMsg {
+            liquidity_guard: false,
+        }),
+    )
+    .unwrap();
+    execute(deps.as_mut(), env, owner, delegate(balance, "uluna")).unwrap();
+}
+
+#[test]
+fn test_undelegate_funds_successful() {
+    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
+    owner.funds = vec![];
+    env.block.time = env.block.time.plus_seconds(200);
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::UndelegateFunds(UndelegateFundsMsg {
+            validator: "random".to_string(),
+            amount: Coin::new(100_000, "uluna"),
+        }),
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Staking(StakingMsg::Undelegate {
+            validator: "random".to_string(),
+            amount: Coin {
+                denom: "uluna".to_string(),
+                amount: Uint128::new(100_000),
+            },
+        }))
+    );
+}
+
+#[test]
+fn test_undelegate_funds_withdraws_rewards_first() {
+    let (mut deps, env, owner, _recipient) = instantiate_contract();
+    deps.querier.update_staking(
+        "uluna",
+        &[],
+        &[FullDelegation {
+            delegator: Addr::unchecked(CONTRACT_ADDR),
+            validator: "validator".to_string(),
+            amount: coin(100_000, "uluna"),
+            can_redelegate: coin(100_000, "uluna"),
+            accumulated_rewards: vec![coin(100, "uluna")],
+        }],
+    );
+
+    // undelegating the whole stake removes the delegation, its rewards cannot be withdrawn after
+    let res = execute(
+        deps.as_mut(),
+        env,
+        owner,
+        ExecuteMsg::UndelegateFunds(UndelegateFundsMsg {
+            validator: "validator".to_string(),
+            amount: coin(100_000, "uluna"),
+        }),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(DistributionMsg::WithdrawDelegatorReward {
+                validator: "validator".to_string(),
+            }),
+            SubMsg::new(BankMsg::Send {
+                to_address: "javier".to_string(),
+                amount: vec![coin(100, "uluna")],
+            }),
+            SubMsg::new(StakingMsg::Undelegate {
+                validator: "validator".to_string(),
+                amount: coin(100_000, "uluna"),
+            }),
+        ]
+    );
+}
+
+#[test]
+fn test_undelegate_funds_unauthorized() {
+    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
+    owner.funds = vec![];
+    env.block.time = env.block.time.plus_seconds(200);
+
+    let info = mock_info("random", &[]);
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::UndelegateFunds(UndelegateFundsMsg {
+            validator: "random".to_string(),
+            amount: Coin::new(100_000, "uluna"),
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(res, ContractError::Unauthorized {});
+}
+
+#[test]
+fn test_prepare_liquidity() {
+    let (mut deps, mut env, owner, _recipient) = instantiate_contract();
+    env.block.time = Timestamp::from_seconds(VESTING_START_TIME + DAY_IN_SECONDS);
+    let million = VESTING_AMOUNT / 100;
+    let delegation = |validator: &str, amount: u128| FullDelegation {
+        delegator: Addr::unchecked(CONTRACT_ADDR),
+        validator: validator.to_string(),
+        amount: coin(amount, "uluna"),
+        can_redelegate: coin(amount, "uluna"),
+        accumulated_rewards: vec![coin(100, "uluna")],
+    };
+    // rewards are withdrawn and forwarded before each undelegation
+    let undelegate = |validator: &str, amount: u128| {
+        vec![
+            SubMsg::new(DistributionMsg::WithdrawDelegatorReward {
+                validator: validator.to_string(),
+            }),
+            SubMsg::new(BankMsg::Send {
+                to_address: "javier".to_string(),
+                amount: vec![coin(100, "uluna")],
+            }),
+            SubMsg::new(StakingMsg::Undelegate {
+                validator: validator.to_string(),
+                amount: coin(amount, "uluna"),
+            }),
+        ]
+    };
+    deps.querier
+        .update_balance(CONTRACT_ADDR, vec![coin(20 * million, "uluna")]);
+    deps.querier.update_staking(
+        "uluna",
+        &[],
+        &[
+            delegation("validator_a", 50 * million),
+            delegation("validator_b", 60 * million),
+            delegation("validator_c", 30 * million),
+        ],
+    );
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::UndelegateFunds(UndelegateFundsMsg {
+            validator: "validator_c".to_string(),
+            amount: coin(10 * million, "uluna"),
+        }),
+    )
+    .unwrap();
+    let prepare = |horizon_seconds: u64| {
+        ExecuteMsg::PrepareLiquidity(PrepareLiquidityMsg {
+            horizon_seconds: Uint64::new(horizon_seconds),
+        })
+    };
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("javier", &[]),
+        prepare(0),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    // what is withdrawable now is only partly covered by the liquid and unbonding balances
+    let required = UNLOCKED_AMOUNT + CLIFF_AMOUNT + VESTED_PER_DAY;
+    let gap = required - 30 * million;
+    let res = execute(deps.as_mut(), env.clone(), owner.clone(), prepare(0)).unwrap();
+    assert_eq!(res.messages, undelegate("validator_b", gap));
+    assert_eq!(
+        res.events,
+        vec![Event::new("vesting-stake")
+            .add_attribute("action", "prepare_liquidity")
+            .add_attribute("horizon_seconds", "0")
+            .add_attribute("required", required.to_string())
+            .add_attribute("liquid_balance", (20 * million).to_string())
+            .add_attribute("unbonding_balance", (10 * million).to_string())
+            .add_attribute("denom", "uluna")
+            .add_attribute("amount", gap.to_string())
+            .add_attribute("shortfall", "0")]
+    );
+
+    // nothing left to prepare for the same horizon
+    let res = execute(deps.as_mut(), env.clone(), owner.clone(), prepare(0)).unwrap();
+    assert!(res.messages.is_empty());
+
+    // by the end of the schedule everything is withdrawable, delegations that cannot cover the gap
+    // are fully undelegated and the rest is reported
+    let full_horizon = VESTING_END_TIME - VESTING_START_TIME;
+    let total_amount = UNLOCKED_AMOUNT + CLIFF_AMOUNT + VESTING_AMOUNT;
+    let gap = total_amount - 20 * million - 10 * million - gap;
+    deps.querier
+        .update_staking("uluna", &[], &[delegation("validator_a", million)]);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        prepare(full_horizon),
+    )
+    .unwrap();
+    assert_eq!(res.messages, undelegate("validator_a", million));
+    assert_eq!(
+        res.events[0].attributes.last().unwrap().value,
+        (gap - million).to_string()
+    );
+
+    // largest delegations are undelegated first
+    let gap = gap - million;
+    deps.querier.update_staking(
+        "uluna",
+        &[],
+        &[
+            delegation("validator_a", 50 * million),
+            delegation("validator_b", 60 * million),
+            delegation("validator_c", 30 * million),
+        ],
+    );
+    let res = execute(deps.as_mut(), env.clone(), owner, prepare(full_horizon)).unwrap();
+    assert_eq!(
+        res.messages,
+        [
+            undelegate("validator_b", 60 * million),
+            undelegate("validator_a", gap - 60 * million),
+        ]
+        .concat()
+    );
+
+    let res = query(deps.as_ref(), env, QueryMsg::QuerySolvency).unwrap();
+    let value: SolvencyResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        value.unbonding_balance,
+        Uint128::new(total_amount - 20 * million)
+    );
+    assert_eq!(
+        value.liquid_shortfall,
+        Uint128::new(required - 20 * million)
+    );
+}
+
+#[test]
+fn test_redelegate_funds_successful() {
+    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
+    owner.funds = vec![];
+    env.block.time = env.block.time.plus_seconds(200);
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::RedelegateFunds(RedelegateFundsMsg {
+            src_validator: "random".to_string(),
+            dst_validator: "another".to_string(),
+            amount: Coin::new(100_000, "uluna"),
+        }),
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 1);
+    assert_eq!(
+        res.messages[0],
+        SubMsg::new(CosmosMsg::Staking(StakingMsg::Redelegate {
+            src_validator: "random".to_string(),
+            dst_validator: "another".to_string(),
+            amount: Coin {
+                denom: "uluna".to_string(),
+                amount: Uint128::new(100_000),
+            },
+        }))
+    );
+}
+
+#[test]
+fn test_redelegate_funds_while_paused() {
+    let (mut deps, env, owner, _recipient) = instantiate_contract();
+    deps.querier.update_staking(
+        "uluna",
+        &[],
+        &[FullDelegation {
+            delegator: Addr::unchecked(CONTRACT_ADDR),
+            validator: "jailed".to_string(),
+            amount: coin(100_000, "uluna"),
+            can_redelegate: coin(100_000, "uluna"),
+            accumulated_rewards: vec![coin(100, "uluna")],
+        }],
+    );
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::Pause {},
+    )
+    .unwrap();
+
+    // the stake moves off the validator, its rewards stay in the contract
+    let res = execute(
+        deps.as_mut(),
+        env,
+        owner,
+        ExecuteMsg::RedelegateFunds(RedelegateFundsMsg {
+            src_validator: "jailed".to_string(),
+            dst_validator: "another".to_string(),
+            amount: coin(100_000, "uluna"),
+        }),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(StakingMsg::Redelegate {
+            src_validator: "jailed".to_string(),
+            dst_validator: "another".to_string(),
+            amount: coin(100_000, "uluna"),
+        })]
+    );
+    assert!(res.events[0]
+        .attributes
+        .iter()
+        .any(|attr| attr.key == "rewards_forwarded" && attr.value == "false"));
+}
+
+#[test]
+fn test_redelegate_funds_unauthorized() {
+    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
+    owner.funds = vec![];
+    env.block.time = env.block.time.plus_seconds(200);
+
+    let info = mock_info("random", &[]);
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::RedelegateFunds(RedelegateFundsMsg {
+            src_validator: "random".to_string(),
+            dst_validator: "another".to_string(),
+            amount: Coin::new(100_000, "uluna"),
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(res, ContractError::Unauthorized {});
+}
+
+#[test]
+fn test_withdraw_delegator_reward_successful() {
+    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
+    owner.funds = vec![];
+    env.block.time = env.block.time.plus_seconds(200);
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::WithdrawDelegatorReward(WithdrawDelegatorRewardMsg {
+            validator: "random".to_string(),
+        }),
+    )
+    .unwrap();
+
+    assert_eq!(res.messages.len(), 0);
+}
+
+#[test]
+fn test_withdraw_delegator_reward_unauthorized() {
+    let (mut deps, mut env, mut owner, _recipient) = instantiate_contract();
+    owner.funds = vec![];
+    env.block.time = env.block.time.plus_seconds(200);
+
+    let info = mock_info("random", &[]);
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::WithdrawDelegatorReward(WithdrawDelegatorRewardMsg {
+            validator: "random".to_string(),
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(res, ContractError::Unauthorized {});
+}
+
+#[test]
+fn test_query_config() {
+    let (deps, mut env, mut owner, recipient) = instantiate_contract();
+    owner.funds = vec![];
+    env.block.time = env.block.time.plus_seconds(200);
+
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::QueryConfig {}).unwrap();
+    let value: ConfigResponse = from_binary(&res).unwrap();
+    assert_eq!(
+        value,
+        ConfigResponse {
+            owner: owner.sender.clone(),
+            guardian: None,
+            recipients: vec![(recipient.sender.clone(), 10_000)],
+            vesting_asset: AssetInfo::native("uluna"),
+            withdrawal_delay: None,
+            max_withdraw_per_period: None,
+            ibc_payout: None,
+            unlocked_amount: Uint128::new(UNLOCKED_AMOUNT),
+            cliff_amount: Uint128::new(CLIFF_AMOUNT),
+            vesting_amount: Uint128::new(VESTING_AMOUNT),
+            start_time: Uint64::new(VESTING_START_TIME),
+            end_time: Uint64::new(VESTING_END_TIME),
+            whitelisted_addresses: vec![owner.sender, recipient.sender],
+            governance_owner: false,
+            liquidity_guard: false,
+        }
+    );
+}
+
+#[test]
+fn test_query_state() {
+    let (deps, mut env, mut owner, _recipient) = instantiate_contract();
+    owner.funds = vec![];
+    env.block.time = env.block.time.plus_seconds(200);
+
+    let res = query(deps.as_ref(), env.clone(), QueryMsg::QueryState {}).unwrap();
+    let value: StateResponse = from_binary(&res).unwrap();
+
+    assert_eq!(
+        value,
+        StateResponse {
+            last_withdrawn_time: Uint64::new(1735707600),
+            cliff_amount_withdrawn: Uint128::new(0),
+            unlocked_amount_withdrawn: Uint128::new(0),
+            recent_withdrawals: vec![],
+        }
+    );
+}
+
+#[test]
+fn test_transfer_nft_moves_position() {
+    let (mut deps, env, owner, recipient) = instantiate_contract();
+
+    // only the holder can transfer the position
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::TransferNft(TransferNftMsg {
+            recipient: "multisig".to_string(),
+            token_id: "0".to_string(),
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        recipient.clone(),
+        ExecuteMsg::TransferNft(TransferNftMsg {
+            recipient: "multisig".to_string(),
+            token_id: "0".to_string(),
+        }),
+    )
+    .unwrap();
+
+    let res: OwnerOfResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::OwnerOf {
+                token_id: "0".to_string(),
+                include_expired: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.owner, "multisig");
+    let res: TokensResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Tokens {
+                owner: "multisig".to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.tokens, vec!["0".to_string()]);
+
+    // the previous holder can no longer withdraw, the new holder is paid
+    let withdraw = ExecuteMsg::WithdrawUnlockedFunds(WithdrawVestedFundsMsg {
+        asset_info: AssetInfoUnchecked::native("uluna"),
+        destination: None,
+        msg: None,
+    });
+    let err = execute(deps.as_mut(), env.clone(), recipient, withdraw.clone()).unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let res = execute(deps.as_mut(), env, mock_info("multisig", &[]), withdraw).unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "multisig".to_string(),
+            amount: vec![Coin::new(UNLOCKED_AMOUNT, "uluna")],
+        })
+    );
+}
+
+#[test]
+fn test_send_nft() {
+    let (mut deps, env, _owner, recipient) = instantiate_contract();
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        recipient.clone(),
+        ExecuteMsg::SendNft(SendNftMsg {
+            contract: "escrow".to_string(),
+            token_id: "0".to_string(),
+            msg: Binary::from(b"{}".to_vec()),
+        }),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        Cw721ReceiveMsg {
+            sender: recipient.sender.to_string(),
+            token_id: "0".to_string(),
+            msg: Binary::from(b"{}".to_vec()),
+        }
+        .into_cosmos_msg("escrow")
+        .unwrap()
+    );
+
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    assert_eq!(config.recipients, vec![(Addr::unchecked("escrow"), 10_000)]);
+
+    // unknown positions are rejected
+    let err = execute(
+        deps.as_mut(),
+        env,
+        mock_info("escrow", &[]),
+        ExecuteMsg::TransferNft(TransferNftMsg {
+            recipient: "rando".to_string(),
+            token_id: "1".to_string(),
+        }),
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::Std(StdError::NotFound { .. })));
+}
+
+#[test]
+fn test_transfer_nft_keeps_token_ids_and_whitelist() {
+    let (mut deps, env, owner, recipient) = instantiate_contract();
+    let update_recipients = |deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>,
+                             recipients: Vec<(&str, u16)>| {
+        execute(
+            deps.as_mut(),
+            env.clone(),
+            owner.clone(),
+            ExecuteMsg::UpdateRecipient(UpdateRecipientMsg {
+                recipients: recipients
+                    .into_iter()
+                    .map(|(addr, bps)| (addr.to_string(), bps))
+                    .collect(),
+            }),
+        )
+        .unwrap();
+    };
+    let owner_of = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>, token_id: &str| {
+        query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::OwnerOf {
+                token_id: token_id.to_string(),
+                include_expired: None,
+            },
+        )
+        .and_then(|res| from_binary::<OwnerOfResponse>(&res))
+        .map(|res| res.owner)
+    };
+
+    // remaining recipients keep their token, new ones are minted a fresh id
+    update_recipients(&mut deps, vec![("alice", 4_000), ("javier", 6_000)]);
+    assert_eq!(owner_of(&deps, "0").unwrap(), "javier");
+    assert_eq!(owner_of(&deps, "1").unwrap(), "alice");
+
+    // a holder cannot take over a second position
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        recipient.clone(),
+        ExecuteMsg::TransferNft(TransferNftMsg {
+            recipient: "alice".to_string(),
+            token_id: "0".to_string(),
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::ValidationError("Recipient already holds a position".to_string())
+    );
+
+    // whitelisted by the owner, the previous holder stays on the whitelist
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::AddToWhitelist(AddToWhitelistMsg {
+            addresses: vec![recipient.sender.clone()],
+        }),
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        recipient.clone(),
+        ExecuteMsg::TransferNft(TransferNftMsg {
+            recipient: "multisig".to_string(),
+            token_id: "0".to_string(),
+        }),
+    )
+    .unwrap();
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    assert!(config.whitelisted_addresses.contains(&recipient.sender));
+    assert!(config
+        .whitelisted_addresses
+        .contains(&Addr::unchecked("multisig")));
+
+    // otherwise it is dropped with the position
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("alice", &[]),
+        ExecuteMsg::TransferNft(TransferNftMsg {
+            recipient: "bob".to_string(),
+            token_id: "1".to_string(),
+        }),
+    )
+    .unwrap();
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    assert!(!config
+        .whitelisted_addresses
+        .contains(&Addr::unchecked("alice")));
+
+    // removed recipients burn their token, ids are never reissued
+    update_recipients(&mut deps, vec![("bob", 5_000), ("carol", 5_000)]);
+    assert!(matches!(
+        owner_of(&deps, "0").unwrap_err(),
+        StdError::NotFound { .. }
+    ));
+    assert_eq!(owner_of(&deps, "1").unwrap(), "bob");
+    assert_eq!(owner_of(&deps, "2").unwrap(), "carol");
+    let res: TokensResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::AllTokens {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.tokens, vec!["1".to_string(), "2".to_string()]);
+}
+
+#[test]
+fn test_transfer_nft_revokes_fee_allowance() {
+    let (deps, env, owner, recipient) = instantiate_contract();
+    let mut deps = with_fee_grants(deps);
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("vlad", &[Coin::new(100_000, "uusd")]),
+        ExecuteMsg::TopUpFeeBudget {},
+    )
+    .unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner,
+        ExecuteMsg::GrantFeeAllowance(FeeAllowanceMsg {
+            grantee: recipient.sender.to_string(),
+            spend_limit: Uint128::new(40_000),
+        }),
+    )
+    .unwrap();
+    deps.querier
+        .allowances
+        .insert(recipient.sender.to_string(), 40_000);
+
+    // the previous holder leaves the whitelist, so its allowance goes with it
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        recipient.clone(),
+        ExecuteMsg::TransferNft(TransferNftMsg {
+            recipient: "multisig".to_string(),
+            token_id: "0".to_string(),
+        }),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(revoke_allowance_msg(
+            &env.contract.address,
+            &recipient.sender
+        ))]
+    );
+    let res: FeeGrantsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env,
+            QueryMsg::QueryFeeGrants {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert!(res.grants.is_empty());
+}
+
+#[test]
+fn test_nft_approvals() {
+    let (mut deps, mut env, _owner, recipient) = instantiate_contract();
+    let transfer = |recipient: &str| {
+        ExecuteMsg::TransferNft(TransferNftMsg {
+            recipient: recipient.to_string(),
+            token_id: "0".to_string(),
+        })
+    };
+
+    // only the holder or its operators manage the approvals of a position
+    let approve = ExecuteMsg::Approve(ApproveMsg {
+        spender: "market".to_string(),
+        token_id: "0".to_string(),
+        expires: None,
+    });
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("market", &[]),
+        approve.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    execute(deps.as_mut(), env.clone(), recipient.clone(), approve).unwrap();
+
+    let res: AllNftInfoResponse<VestingPosition> = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::AllNftInfo {
+                token_id: "0".to_string(),
+                include_expired: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.access.approvals,
+        vec![Approval {
+            spender: "market".to_string(),
+            expires: Expiration::Never {},
+        }]
+    );
+    assert_eq!(res.info.extension, VestingPosition { share_bps: 10_000 });
+
+    // the approved spender moves the position, which clears its approvals
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("market", &[]),
+        transfer("buyer"),
+    )
+    .unwrap();
+    let res: ApprovalsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Approvals {
+                token_id: "0".to_string(),
+                include_expired: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.approvals, vec![]);
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("market", &[]),
+        transfer("market"),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    // operators act on all positions of the holder until they expire
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("buyer", &[]),
+        ExecuteMsg::ApproveAll(ApproveAllMsg {
+            operator: "desk".to_string(),
+            expires: Some(Expiration::AtHeight(env.block.height)),
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::ValidationError("Approval is already expired".to_string())
+    );
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("buyer", &[]),
+        ExecuteMsg::ApproveAll(ApproveAllMsg {
+            operator: "desk".to_string(),
+            expires: Some(Expiration::AtHeight(env.block.height + 1)),
+        }),
+    )
+    .unwrap();
+    let res: OperatorsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::AllOperators {
+                owner: "buyer".to_string(),
+                include_expired: None,
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.operators,
+        vec![Approval {
+            spender: "desk".to_string(),
+            expires: Expiration::AtHeight(env.block.height + 1),
+        }]
+    );
+
+    env.block.height += 1;
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("desk", &[]),
+        transfer("desk"),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+
+    env.block.height -= 1;
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("desk", &[]),
+        transfer("vault"),
+    )
+    .unwrap();
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    assert_eq!(config.recipients, vec![(Addr::unchecked("vault"), 10_000)]);
+}
+
+#[test]
+fn test_governance_owner_mode() {
+    let (mut deps, env, owner, recipient) = instantiate_contract();
+    let instantiate_msg = InstantiateMsg {
+        owner: owner.sender.to_string(),
+        guardian: None,
+        recipients: vec![(recipient.sender.to_string(), 10_000)],
+        vesting_asset: None,
+        withdrawal_delay: None,
+        max_withdraw_per_period: None,
+        ibc_payout: None,
+        unlocked_amount: Uint128::new(UNLOCKED_AMOUNT),
+        cliff_amount: Uint128::new(CLIFF_AMOUNT),
+        vesting_amount: Uint128::new(VESTING_AMOUNT),
+        start_time: Some(Uint64::new(VESTING_START_TIME)),
+        end_time: Uint64::new(VESTING_END_TIME),
+        require_full_funding: None,
+        governance_owner: Some(true),
+        liquidity_guard: None,
+    };
+    let err = instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        instantiate_msg.clone(),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::ValidationError("Owner must be the governance module account".to_string())
+    );
+
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        InstantiateMsg {
+            owner: GOVERNANCE_MODULE.to_string(),
+            ..instantiate_msg
+        },
+    )
+    .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        env,
+        mock_info(GOVERNANCE_MODULE, &[]),
+        ExecuteMsg::UpdateOwner(UpdateOwnerMsg {
+            owner: owner.sender.to_string(),
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::ValidationError(
+            "Owner cannot be changed in governance owner mode".to_string()
+        )
+    );
+}
+
+#[test]
+fn test_migrate_to_governance_owner() {
+    let (mut deps, env, owner, recipient) = instantiate_contract();
+    migrate(
+        deps.as_mut(),
+        env.clone(),
+        MigrateMsg {
+            governance_owner: Some(true),
+        },
+    )
+    .unwrap();
+
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    assert!(config.governance_owner);
+    assert_eq!(config.owner, Addr::unchecked(GOVERNANCE_MODULE));
+    assert_eq!(
+        config.whitelisted_addresses,
+        vec![Addr::unchecked(GOVERNANCE_MODULE), recipient.sender]
+    );
+
+    // the previous owner can no longer perform owner actions
+    let err = execute(
+        deps.as_mut(),
+        env,
+        owner,
+        ExecuteMsg::AddToWhitelist(AddToWhitelistMsg {
+            addresses: vec![Addr::unchecked("warp")],
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+}
+
+#[test]
+fn test_sudo_pause_and_update_recipient() {
+    let (mut deps, env, owner, _recipient) = instantiate_contract();
+
+    sudo(deps.as_mut(), env.clone(), SudoMsg::Pause {}).unwrap();
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner,
+        ExecuteMsg::WithdrawUnlockedFunds(WithdrawVestedFundsMsg {
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Paused {});
+    sudo(deps.as_mut(), env.clone(), SudoMsg::Unpause {}).unwrap();
+    assert!(!PAUSED.load(deps.as_ref().storage).unwrap());
+
+    sudo(
+        deps.as_mut(),
+        env,
+        SudoMsg::UpdateRecipient(UpdateRecipientMsg {
+            recipients: vec![("rando".to_string(), 10_000)],
+        }),
+    )
+    .unwrap();
+    let config = CONFIG.load(deps.as_ref().storage).unwrap();
+    assert_eq!(config.recipients, vec![(Addr::unchecked("rando"), 10_000)]);
+}
+
+#[test]
+fn test_sudo_clawback() {
+    let (mut deps, mut env, _owner, _recipient) = instantiate_contract();
+    let half_time = (VESTING_START_TIME + VESTING_END_TIME) / 2;
+    env.block.time = Timestamp::from_seconds(half_time);
+    let vested = Uint128::new(VESTING_AMOUNT).multiply_ratio(
+        half_time - VESTING_START_TIME,
+        VESTING_END_TIME - VESTING_START_TIME,
+    );
+
+    let res = sudo(
+        deps.as_mut(),
+        env.clone(),
+        SudoMsg::Clawback(ClawbackMsg {
+            destination: "community_pool".to_string(),
+        }),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages[0].msg,
+        CosmosMsg::Bank(BankMsg::Send {
+            to_address: "community_pool".to_string(),
+            amount: vec![Coin::new(VESTING_AMOUNT - vested.u128(), "uluna")],
+        })
+    );
+
+    // nothing vests after the clawback
+    env.block.time = Timestamp::from_seconds(VESTING_END_TIME);
+    let res: WithdrawableResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), QueryMsg::QueryWithdrawable {}).unwrap())
+            .unwrap();
+    assert_eq!(res.vesting_amount, vested);
+    assert_eq!(res.cliff_amount, Uint128::new(CLIFF_AMOUNT));
+
+    let err = sudo(
+        deps.as_mut(),
+        env,
+        SudoMsg::Clawback(ClawbackMsg {
+            destination: "community_pool".to_string(),
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::ValidationError("Nothing to claw back".to_string())
+    );
+}
+
+#[test]
+fn test_fee_grants() {
+    let (deps, mut env, _owner, _recipient) = instantiate_contract();
+    let mut deps = with_fee_grants(deps);
+    env.block.time = env.block.time.plus_seconds(200);
+    deps.querier
+        .base
+        .update_balance(CONTRACT_ADDR, vec![Coin::new(1_000_000, "uusd")]);
+
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("javier", &[Coin::new(100_000, "uusd")]),
+        ExecuteMsg::TopUpFeeBudget {},
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::Unauthorized {});
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("vlad", &[Coin::new(100_000, "uusd")]),
+        ExecuteMsg::TopUpFeeBudget {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.events,
+        vec![Event::new("vesting-admin")
+            .add_attribute("action", "top_up_fee_budget")
+            .add_attribute("amount", "100000uusd")
+            .add_attribute("fee_budget", "100000uusd")]
+    );
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("vlad", &[Coin::new(100_000, "uluna")]),
+        ExecuteMsg::TopUpFeeBudget {},
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::ValidationError("Fee budget is held in uusd".to_string())
+    );
+
+    // only whitelisted addresses get an allowance
+    let grant = |spend_limit: u128| {
+        ExecuteMsg::GrantFeeAllowance(FeeAllowanceMsg {
+            grantee: "keeper".to_string(),
+            spend_limit: Uint128::new(spend_limit),
+        })
+    };
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("vlad", &[]),
+        grant(40_000),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::ValidationError(
+            "Fee allowances can only be granted to whitelisted addresses".to_string()
+        )
+    );
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("vlad", &[]),
+        ExecuteMsg::AddToWhitelist(AddToWhitelistMsg {
+            addresses: vec![Addr::unchecked("keeper")],
+        }),
+    )
+    .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("vlad", &[]),
+        grant(100_001),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::ValidationError("Fee budget exceeded".to_string())
+    );
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("vlad", &[]),
+        grant(40_000),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(CosmosMsg::Stargate {
+            type_url: "/cosmos.feegrant.v1beta1.MsgGrantAllowance".to_string(),
+            value: Binary::from_base64("Ch9jb21tdW5pdHlfcG9vbF92ZXN0aW5nX2NvbnRyYWN0EgZrZWVwZXIakgEKLC9jb3Ntb3MuZmVlZ3JhbnQudjFiZXRhMS5BbGxvd2VkTXNnQWxsb3dhbmNlEmIKOgonL2Nvc21vcy5mZWVncmFudC52MWJldGExLkJhc2ljQWxsb3dhbmNlEg8KDQoEdXVzZBIFNDAwMDASJC9jb3Ntd2FzbS53YXNtLnYxLk1zZ0V4ZWN1dGVDb250cmFjdA==").unwrap(),
+        })]
+    );
+    deps.querier.allowances.insert("keeper".to_string(), 40_000);
+    let revoke = CosmosMsg::Stargate {
+        type_url: "/cosmos.feegrant.v1beta1.MsgRevokeAllowance".to_string(),
+        value: Binary::from_base64("Ch9jb21tdW5pdHlfcG9vbF92ZXN0aW5nX2NvbnRyYWN0EgZrZWVwZXI=")
+            .unwrap(),
+    };
+
+    // granted and remaining fee funds are kept out of payouts in the fee denom
+    let withdraw_uusd = |deps: &mut OwnedDeps<MockStorage, MockApi, FeeGrantQuerier>| {
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("vlad", &[]),
+            ExecuteMsg::WithdrawCliffVestedFunds(WithdrawVestedFundsMsg {
+                asset_info: AssetInfoUnchecked::native("uusd"),
+                destination: None,
+                msg: None,
+            }),
+        )
+        .unwrap();
+        match &res.messages[0].msg {
+            CosmosMsg::Bank(BankMsg::Send { to_address, amount }) => {
+                assert_eq!(to_address, "javier");
+                amount[0].amount.u128()
+            }
+            msg => panic!("unexpected payout {:?}", msg),
+        }
+    };
+    assert_eq!(withdraw_uusd(&mut deps), 900_000);
+
+    // a new grant replaces the current allowance, its unspent part goes back to the budget
+    let set_uusd_balance = |deps: &mut OwnedDeps<MockStorage, MockApi, FeeGrantQuerier>,
+                            amount: u128| {
+        deps.querier
+            .base
+            .update_balance(CONTRACT_ADDR, vec![Coin::new(amount, "uusd")]);
+    };
+    deps.querier.allowances.insert("keeper".to_string(), 15_000);
+    set_uusd_balance(&mut deps, 75_000);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("vlad", &[]),
+        grant(10_000),
+    )
+    .unwrap();
+    assert_eq!(res.messages[0].msg, revoke);
+    deps.querier.allowances.insert("keeper".to_string(), 10_000);
+    let res: FeeGrantsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::QueryFeeGrants {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        FeeGrantsResponse {
+            budget: Some(Coin::new(65_000, "uusd")),
+            grants: vec![FeeGrant {
+                grantee: Addr::unchecked("keeper"),
+                spend_limit: Uint128::new(10_000),
+            }],
+        }
+    );
+    // the reserve is the budget and the new spend limit, the spent fees already left the contract
+    set_uusd_balance(&mut deps, 80_000);
+    assert_eq!(withdraw_uusd(&mut deps), 5_000);
+
+    // leaving the whitelist revokes the allowance, crediting what is left of it
+    deps.querier.allowances.insert("keeper".to_string(), 4_000);
+    set_uusd_balance(&mut deps, 70_000);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("vlad", &[]),
+        ExecuteMsg::RemoveFromWhitelist(RemoveFromWhitelistMsg {
+            addresses: vec![Addr::unchecked("keeper")],
+        }),
+    )
+    .unwrap();
+    assert_eq!(res.messages, vec![SubMsg::new(revoke.clone())]);
+    assert_eq!(withdraw_uusd(&mut deps), 1_000);
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("vlad", &[]),
+        ExecuteMsg::RevokeFeeAllowance(RevokeFeeAllowanceMsg {
+            grantee: "keeper".to_string(),
+        }),
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::ValidationError("No fee allowance granted".to_string())
+    );
+
+    // revoking credits the unspent part, an allowance used up is already gone from the chain
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("vlad", &[]),
+        ExecuteMsg::AddToWhitelist(AddToWhitelistMsg {
+            addresses: vec![Addr::unchecked("keeper")],
+        }),
+    )
+    .unwrap();
+    let revoke_keeper = ExecuteMsg::RevokeFeeAllowance(RevokeFeeAllowanceMsg {
+        grantee: "keeper".to_string(),
+    });
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("vlad", &[]),
+        grant(9_000),
+    )
+    .unwrap();
+    deps.querier.allowances.insert("keeper".to_string(), 3_000);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("vlad", &[]),
+        revoke_keeper.clone(),
+    )
+    .unwrap();
+    assert_eq!(res.messages, vec![SubMsg::new(revoke)]);
+    assert_eq!(
+        res.events,
+        vec![Event::new("vesting-admin")
+            .add_attribute("action", "revoke_fee_allowance")
+            .add_attribute("grantee", "keeper")
+            .add_attribute("fee_budget", "63000uusd")]
+    );
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("vlad", &[]),
+        grant(9_000),
+    )
+    .unwrap();
+    deps.querier.allowances.remove("keeper");
+    let res = execute(deps.as_mut(), env, mock_info("vlad", &[]), revoke_keeper).unwrap();
+    assert!(res.messages.is_empty());
+    assert_eq!(res.events[0].attributes.last().unwrap().value, "54000uusd");
+}
+
+#[test]
+fn test_instantiate_invalid_schedule() {
+    let (mut deps, env, owner, recipient) = instantiate_contract();
+    let instantiate_msg = InstantiateMsg {
+        owner: owner.sender.to_string(),
+        guardian: None,
+        recipients: vec![(recipient.sender.to_string(), 10_000)],
+        vesting_asset: None,
+        withdrawal_delay: None,
+        max_withdraw_per_period: None,
+        ibc_payout: None,
+        unlocked_amount: Uint128::new(UNLOCKED_AMOUNT),
+        cliff_amount: Uint128::new(CLIFF_AMOUNT),
+        vesting_amount: Uint128::new(VESTING_AMOUNT),
+        start_time: Some(Uint64::new(VESTING_START_TIME)),
+        end_time: Uint64::new(VESTING_END_TIME),
+        require_full_funding: None,
+        governance_owner: None,
+        liquidity_guard: None,
+    };
+
+    let err = instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        InstantiateMsg {
+            end_time: Uint64::new(VESTING_START_TIME),
+            ..instantiate_msg.clone()
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::ValidationError("End time must be after start time".to_string())
+    );
+
+    // without a start time the schedule starts now
+    let err = instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        InstantiateMsg {
+            start_time: None,
+            end_time: Uint64::new(env.block.time.seconds() - 1),
+            ..instantiate_msg.clone()
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::ValidationError("End time must be after start time".to_string())
+    );
+
+    let err = instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        InstantiateMsg {
+            start_time: Some(Uint64::new(env.block.time.seconds() - 1)),
+            ..instantiate_msg.clone()
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::ValidationError("Start time cannot be in the past".to_string())
+    );
+
+    let err = instantiate(
+        deps.as_mut(),
+        env,
+        owner,
+        InstantiateMsg {
+            unlocked_amount: Uint128::MAX,
+            require_full_funding: Some(true),
+            ..instantiate_msg
+        },
+    )
+    .unwrap_err();
+    assert_eq!(
+        err,
+        ContractError::ValidationError("Total amount overflows a Uint128".to_string())
+    );
+}
+
+#[test]
+fn test_instantiate_supply_cap() {
+    let (mut deps, env, owner, recipient) = instantiate_contract();
+    let instantiate_msg = InstantiateMsg {
+        owner: owner.sender.to_string(),
+        guardian: None,
+        recipients: vec![(recipient.sender.to_string(), 10_000)],
+        vesting_asset: None,
+        withdrawal_delay: None,
+        max_withdraw_per_period: None,
+        ibc_payout: None,
+        unlocked_amount: Uint128::new(UNLOCKED_AMOUNT),
+        cliff_amount: Uint128::new(CLIFF_AMOUNT),
+        vesting_amount: Uint128::new(VESTING_AMOUNT),
+        start_time: Some(Uint64::new(VESTING_START_TIME)),
+        end_time: Uint64::new(VESTING_END_TIME),
+        require_full_funding: None,
+        governance_owner: None,
+        liquidity_guard: None,
+    };
+    let supply_exceeded = ContractError::ValidationError(
+        "Total amount exceeds the supply of the vesting asset".to_string(),
+    );
+
+    // with an empty contract the uluna supply is what luna_holder holds
+    deps.querier.update_balance(CONTRACT_ADDR, vec![]);
+    let err = instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        InstantiateMsg {
+            vesting_amount: Uint128::new(LUNA_SUPPLY - UNLOCKED_AMOUNT - CLIFF_AMOUNT + 1),
+            ..instantiate_msg.clone()
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, supply_exceeded);
+    instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        InstantiateMsg {
+            vesting_amount: Uint128::new(LUNA_SUPPLY - UNLOCKED_AMOUNT - CLIFF_AMOUNT),
+            ..instantiate_msg.clone()
+        },
+    )
+    .unwrap();
+
+    // a cw20 asset is capped by the total supply of the token
+    deps.querier.update_wasm(|query| match query {
+        WasmQuery::Smart { contract_addr, msg } if contract_addr == "token" => {
+            assert_eq!(
+                from_binary::<Cw20QueryMsg>(msg).unwrap(),
+                Cw20QueryMsg::TokenInfo {}
+            );
+            SystemResult::Ok(ContractResult::Ok(
+                to_binary(&TokenInfoResponse {
+                    name: "token".to_string(),
+                    symbol: "TKN".to_string(),
+                    decimals: 6,
+                    total_supply: Uint128::new(UNLOCKED_AMOUNT + CLIFF_AMOUNT + VESTING_AMOUNT),
+                })
+                .unwrap(),
+            ))
+        }
+        _ => SystemResult::Err(SystemError::UnsupportedRequest {
+            kind: "wasm".to_string(),
+        }),
+    });
+    let err = instantiate(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        InstantiateMsg {
+            vesting_asset: Some(AssetInfoUnchecked::cw20("token")),
+            vesting_amount: Uint128::new(VESTING_AMOUNT + 1),
+            ..instantiate_msg.clone()
+        },
+    )
+    .unwrap_err();
+    assert_eq!(err, supply_exceeded);
+    instantiate(
+        deps.as_mut(),
+        env,
+        owner,
+        InstantiateMsg {
+            vesting_asset: Some(AssetInfoUnchecked::cw20("token")),
+            ..instantiate_msg
+        },
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_instantiate_validate() {
+    let now = VESTING_START_TIME - 1;
+    let msg = InstantiateMsg {
+        owner: "vlad".to_string(),
+        guardian: None,
+        recipients: vec![("javier".to_string(), 10_000)],
+        vesting_asset: None,
+        withdrawal_delay: None,
+        max_withdraw_per_period: None,
+        ibc_payout: None,
+        unlocked_amount: Uint128::new(UNLOCKED_AMOUNT),
+        cliff_amount: Uint128::new(CLIFF_AMOUNT),
+        vesting_amount: Uint128::new(VESTING_AMOUNT),
+        start_time: Some(Uint64::new(VESTING_START_TIME)),
+        end_time: Uint64::new(VESTING_END_TIME),
+        require_full_funding: None,
+        governance_owner: None,
+        liquidity_guard: None,
+    };
+    msg.validate(now).unwrap();
+
+    let cases = vec![
+        (
+            InstantiateMsg {
+                start_time: Some(Uint64::new(now - 1)),
+                ..msg.clone()
+            },
+            ContractError::ValidationError("Start time cannot be in the past".to_string()),
+        ),
+        (
+            InstantiateMsg {
+                end_time: Uint64::new(VESTING_START_TIME - 1),
+                ..msg.clone()
+            },
+            ContractError::ValidationError("End time must be after start time".to_string()),
+        ),
+        // the default start time is checked against the end time too
+        (
+            InstantiateMsg {
+                start_time: None,
+                end_time: Uint64::new(now),
+                ..msg.clone()
+            },
+            ContractError::ValidationError("End time must be after start time".to_string()),
+        ),
+        (
+            InstantiateMsg {
+                end_time: Uint64::new(VESTING_START_TIME + 101 * 365 * DAY_IN_SECONDS),
+                ..msg.clone()
+            },
+            ContractError::ValidationError("Vesting duration cannot exceed 100 years".to_string()),
+        ),
+        (
+            InstantiateMsg {
+                unlocked_amount: Uint128::zero(),
+                cliff_amount: Uint128::zero(),
+                vesting_amount: Uint128::zero(),
+                ..msg.clone()
+            },
+            ContractError::ValidationError("Schedule amounts cannot all be zero".to_string()),
+        ),
+        (
+            InstantiateMsg {
+                cliff_amount: Uint128::MAX - Uint128::new(UNLOCKED_AMOUNT),
+                ..msg.clone()
+            },
+            ContractError::ValidationError("Total amount overflows a Uint128".to_string()),
+        ),
+        (
+            InstantiateMsg {
+                max_withdraw_per_period: Some(WithdrawPeriodLimit {
+                    amount: Uint128::new(1_000),
+                    period_seconds: Uint64::zero(),
+                }),
+                ..msg.clone()
+            },
+            ContractError::ValidationError(
+                "Withdraw limit amount and period must be positive".to_string(),
+            ),
+        ),
+        (
+            InstantiateMsg {
+                recipients: vec![("javier".to_string(), 5_000), ("vlad".to_string(), 5_000)],
+                ..msg.clone()
+            },
+            ContractError::ValidationError("Owner cannot be a recipient".to_string()),
+        ),
+        (
+            InstantiateMsg {
+                governance_owner: Some(true),
+                liquidity_guard: None,
+                ..msg.clone()
+            },
+            ContractError::ValidationError(
+                "Owner must be the governance module account".to_string(),
+            ),
+        ),
+    ];
+    for (msg, err) in cases {
+        assert_eq!(msg.validate(now).unwrap_err(), err);
+    }
+
+    // a single non-zero tranche and the longest duration are accepted
+    InstantiateMsg {
+        unlocked_amount: Uint128::zero(),
+        cliff_amount: Uint128::zero(),
+        end_time: Uint64::new(VESTING_START_TIME + 100 * 365 * DAY_IN_SECONDS),
+        ..msg
+    }
+    .validate(now)
+    .unwrap();
+}
+
+#[test]
+fn test_checked_arithmetic() {
+    let (mut deps, env, owner, _recipient) = instantiate_contract();
+    let withdraw_unlocked = || {
+        ExecuteMsg::WithdrawUnlockedFunds(WithdrawVestedFundsMsg {
+            asset_info: AssetInfoUnchecked::native("uluna"),
+            destination: None,
+            msg: None,
+        })
+    };
+
+    FUNDED_AMOUNT
+        .save(deps.as_mut().storage, &Uint128::MAX)
+        .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("vlad", &[coin(1, "uluna")]),
+        ExecuteMsg::Fund {},
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::Overflow(_)));
+
+    // a state beyond the schedule fails instead of panicking
+    let state = STATE.load(deps.as_ref().storage).unwrap();
+    STATE
+        .save(
+            deps.as_mut().storage,
+            &State {
+                unlocked_amount_withdrawn: Uint128::new(UNLOCKED_AMOUNT + 1),
+                ..state.clone()
+            },
+        )
+        .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        withdraw_unlocked(),
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::Overflow(_)));
+    STATE.save(deps.as_mut().storage, &state).unwrap();
+
+    // release time past the largest timestamp
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::UpdateWithdrawalDelay(UpdateWithdrawalDelayMsg {
+            withdrawal_delay: Some(Uint64::MAX),
+        }),
+    )
+    .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        withdraw_unlocked(),
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::Overflow(_)));
+    // the mock storage is not reverted on errors
+    STATE.save(deps.as_mut().storage, &state).unwrap();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::UpdateWithdrawalDelay(UpdateWithdrawalDelayMsg {
+            withdrawal_delay: None,
+        }),
+    )
+    .unwrap();
+
+    // IBC timeout past the largest timestamp
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::UpdateIbcPayout(UpdateIbcPayoutMsg {
+            ibc_payout: Some(IbcPayout {
+                recipient: "javier".to_string(),
+                channel_id: "channel-1".to_string(),
+                remote_address: "osmo1recipient".to_string(),
+                timeout_seconds: Uint64::MAX,
+            }),
+        }),
+    )
+    .unwrap();
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        withdraw_unlocked(),
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::Overflow(_)));
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        owner.clone(),
+        ExecuteMsg::UpdateIbcPayout(UpdateIbcPayoutMsg { ibc_payout: None }),
+    )
+    .unwrap();
+
+    // withdrawals of the period summing past the largest amount
+    set_withdraw_limit(&mut deps, UNLOCKED_AMOUNT);
+    let record = WithdrawalRecord {
+        time: Uint64::new(env.block.time.seconds()),
+        amount: Uint128::MAX,
+    };
+    STATE
+        .save(
+            deps.as_mut().storage,
+            &State {
+                recent_withdrawals: vec![record.clone(), record],
+                ..state
+            },
+        )
+        .unwrap();
+    let err = execute(deps.as_mut(), env.clone(), owner, withdraw_unlocked()).unwrap_err();
+    assert!(matches!(err, ContractError::Overflow(_)));
+    let err = query(deps.as_ref(), env, QueryMsg::QueryWithdrawable {}).unwrap_err();
+    assert!(matches!(err, StdError::Overflow { .. }));
+}
+
+#[test]
+fn test_helpers_msgs() {
+    let vesting = CommunityPoolVestingContract(Addr::unchecked(CONTRACT_ADDR));
+    let msg = DelegateFundsMsg {
+        validator: "validator".to_string(),
+        amount: coin(1_000, "uluna"),
+    };
+    assert_eq!(
+        vesting.delegate_funds(msg.clone()).unwrap(),
+        WasmMsg::Execute {
+            contract_addr: CONTRACT_ADDR.to_string(),
+            msg: to_binary(&ExecuteMsg::DelegateFunds(msg)).unwrap(),
+            funds: vec![],
+        }
+    );
+    assert_eq!(
+        vesting.fund(vec![coin(1_000, "uluna")]).unwrap(),
+        WasmMsg::Execute {
+            contract_addr: CONTRACT_ADDR.to_string(),
+            msg: to_binary(&ExecuteMsg::Fund {}).unwrap(),
+            funds: vec![coin(1_000, "uluna")],
+        }
+    );
+
+    // cw20 funding goes through the token contract
+    assert_eq!(
+        vesting
+            .fund_cw20(&Addr::unchecked("token"), Uint128::new(1_000))
+            .unwrap(),
+        WasmMsg::Execute {
+            contract_addr: "token".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Send {
+                contract: CONTRACT_ADDR.to_string(),
+                amount: Uint128::new(1_000),
+                msg: to_binary(&Cw20HookMsg::Fund {}).unwrap(),
+            })
+            .unwrap(),
+            funds: vec![],
         }
     );
 }
@@ -0,0 +1,39 @@
+// protobuf encoding of the ICS-20 transfer message, sent as a stargate message to carry a memo
+use crate::proto::{encode_bytes, encode_uint64};
+use cosmwasm_std::{Addr, Binary, Coin, CosmosMsg, Timestamp};
+
+pub const MSG_TRANSFER: &str = "/ibc.applications.transfer.v1.MsgTransfer";
+const TRANSFER_PORT: &str = "transfer";
+
+/// Transfers `token` over `channel_id` to `receiver`, asking the ibc-hooks middleware to report
+/// the acknowledgement or timeout of the packet to the `sender` contract
+pub fn transfer_msg(
+    channel_id: &str,
+    token: &Coin,
+    sender: &Addr,
+    receiver: &str,
+    timeout: Timestamp,
+) -> CosmosMsg {
+    let mut coin = vec![];
+    encode_bytes(1, token.denom.as_bytes(), &mut coin);
+    encode_bytes(2, token.amount.to_string().as_bytes(), &mut coin);
+
+    // MsgTransfer without a timeout height, which is optional when a timestamp is set
+    let mut msg = vec![];
+    encode_bytes(1, TRANSFER_PORT.as_bytes(), &mut msg);
+    encode_bytes(2, channel_id.as_bytes(), &mut msg);
+    encode_bytes(3, &coin, &mut msg);
+    encode_bytes(4, sender.as_bytes(), &mut msg);
+    encode_bytes(5, receiver.as_bytes(), &mut msg);
+    encode_uint64(7, timeout.nanos(), &mut msg);
+    encode_bytes(8, callback_memo(sender).as_bytes(), &mut msg);
+    CosmosMsg::Stargate {
+        type_url: MSG_TRANSFER.to_string(),
+        value: Binary::from(msg),
+    }
+}
+
+/// Memo registering `contract` for the `ibc_lifecycle_complete` sudo callbacks of ibc-hooks
+pub fn callback_memo(contract: &Addr) -> String {
+    format!(r#"{{"ibc_callback":"{}"}}"#, contract)
+}
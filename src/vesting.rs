@@ -0,0 +1,112 @@
+// linear vesting math, free of storage so it can be reused and tested on its own
+//
+// every function rounds in favour of the contract: vested amounts are rounded down and times
+// derived from an amount are rounded so that no more than the amount is ever considered vested.
+// the amount withdrawn from the tranche is always `vested_at(last_withdrawn_time)`, so withdrawals
+// telescope and splitting a withdrawal never changes the total paid out.
+use crate::{Config, ContractError};
+use cosmwasm_std::{Uint128, Uint256, Uint64};
+
+/// Linear vesting of `amount` from `start_time` to `end_time`, in seconds
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Schedule {
+    pub amount: Uint128,
+    pub start_time: u64,
+    pub end_time: u64,
+}
+
+impl From<&Config> for Schedule {
+    fn from(config: &Config) -> Self {
+        Schedule {
+            amount: config.vesting_amount,
+            start_time: config.start_time.u64(),
+            end_time: config.end_time.u64(),
+        }
+    }
+}
+
+/// Amount vested at `time`, rounded down
+pub fn vested_at(schedule: &Schedule, time: u64) -> Result<Uint128, ContractError> {
+    if time >= schedule.end_time {
+        return Ok(schedule.amount);
+    }
+    if time <= schedule.start_time {
+        return Ok(Uint128::zero());
+    }
+    let vested = Uint256::from(schedule.amount)
+        .checked_mul(Uint256::from(time - schedule.start_time))?
+        .checked_div(Uint256::from(duration(schedule)?))?;
+    to_uint128(vested)
+}
+
+/// Amount vested since `last_withdrawn_time`, zero while `time` is before it
+pub fn withdrawable(
+    schedule: &Schedule,
+    last_withdrawn_time: u64,
+    time: u64,
+) -> Result<Uint128, ContractError> {
+    let vested = vested_at(schedule, time.max(last_withdrawn_time))?;
+    Ok(vested.checked_sub(vested_at(schedule, last_withdrawn_time)?)?)
+}
+
+/// Withdraws at most `limit` of the withdrawable amount, returns the amount withdrawn and the new
+/// last withdrawn time. A limited withdrawal moves the last withdrawn time to the latest second at
+/// which the amount is covered, so up to one second worth of vesting stays withdrawable.
+pub fn withdraw(
+    schedule: &Schedule,
+    last_withdrawn_time: u64,
+    time: u64,
+    limit: Uint128,
+) -> Result<(Uint128, u64), ContractError> {
+    let time = time.min(schedule.end_time).max(last_withdrawn_time);
+    let amount = withdrawable(schedule, last_withdrawn_time, time)?;
+    if amount <= limit {
+        return Ok((amount, time));
+    }
+
+    // latest time at which no more than `target` is vested, before the first unit above it vests
+    let target = vested_at(schedule, last_withdrawn_time)?.checked_add(limit)?;
+    let next_unit_time = vesting_time(schedule, target.checked_add(Uint128::new(1))?)?;
+    let new_last_withdrawn_time = Uint64::new(next_unit_time)
+        .checked_sub(Uint64::new(1))?
+        .u64();
+    let amount = withdrawable(schedule, last_withdrawn_time, new_last_withdrawn_time)?;
+    Ok((amount, new_last_withdrawn_time))
+}
+
+/// Earliest time at which `amount` is vested, rounded up, the end time for the whole amount or more
+pub fn vesting_time(schedule: &Schedule, amount: Uint128) -> Result<u64, ContractError> {
+    if amount.is_zero() {
+        return Ok(schedule.start_time);
+    }
+    if amount >= schedule.amount {
+        return Ok(schedule.end_time);
+    }
+    let seconds = Uint256::from(amount)
+        .checked_mul(Uint256::from(duration(schedule)?))?
+        .checked_add(Uint256::from(schedule.amount).checked_sub(Uint256::from(1u8))?)?
+        .checked_div(Uint256::from(schedule.amount))?;
+    // below the duration, as the amount is below the vesting amount
+    let seconds = Uint64::try_from(to_uint128(seconds)?)?;
+    Ok(Uint64::new(schedule.start_time).checked_add(seconds)?.u64())
+}
+
+/// Earliest time after `time` at which the vested amount increases, none once fully vested
+pub fn next_unlock_time(schedule: &Schedule, time: u64) -> Result<Option<u64>, ContractError> {
+    if time >= schedule.end_time || schedule.amount.is_zero() {
+        return Ok(None);
+    }
+    let next = vested_at(schedule, time)?.checked_add(Uint128::new(1))?;
+    vesting_time(schedule, next).map(Some)
+}
+
+fn duration(schedule: &Schedule) -> Result<u64, ContractError> {
+    schedule
+        .end_time
+        .checked_sub(schedule.start_time)
+        .ok_or_else(|| ContractError::InvalidSchedule("End time is before start time".to_string()))
+}
+
+fn to_uint128(value: Uint256) -> Result<Uint128, ContractError> {
+    Ok(Uint128::try_from(value)?)
+}
@@ -0,0 +1,239 @@
+use crate::vesting::{next_unlock_time, vested_at, vesting_time, withdraw, withdrawable, Schedule};
+use crate::ContractError;
+use cosmwasm_std::Uint128;
+use proptest::prelude::*;
+
+const VESTING_START_TIME: u64 = 1735707600; //jan 1, 2025, 00:00:00
+const VESTING_END_TIME: u64 = 1861937999; //dec 31, 2028, 23:59:59
+const VESTING_AMOUNT: u128 = 100_000_000_000_000; //100m u_units
+
+fn schedule() -> Schedule {
+    Schedule {
+        amount: Uint128::new(VESTING_AMOUNT),
+        start_time: VESTING_START_TIME,
+        end_time: VESTING_END_TIME,
+    }
+}
+
+prop_compose! {
+    fn arb_schedule()(
+        amount in any::<u128>(),
+        start_time in 0..u64::from(u32::MAX),
+        duration in 0..u64::from(u32::MAX),
+    ) -> Schedule {
+        Schedule {
+            amount: Uint128::new(amount),
+            start_time,
+            end_time: start_time + duration,
+        }
+    }
+}
+
+fn arb_time() -> impl Strategy<Value = u64> {
+    0..u64::from(u32::MAX) * 2
+}
+
+#[test]
+fn test_vested_at() {
+    let schedule = schedule();
+    assert_eq!(vested_at(&schedule, 0).unwrap(), Uint128::zero());
+    assert_eq!(
+        vested_at(&schedule, VESTING_START_TIME).unwrap(),
+        Uint128::zero()
+    );
+    // 100m over 126 230 399 seconds, rounded down
+    assert_eq!(
+        vested_at(&schedule, VESTING_START_TIME + 1).unwrap(),
+        Uint128::new(792_202)
+    );
+    assert_eq!(
+        vested_at(&schedule, VESTING_END_TIME - 1).unwrap(),
+        Uint128::new(VESTING_AMOUNT - 792_203)
+    );
+    assert_eq!(
+        vested_at(&schedule, VESTING_END_TIME).unwrap(),
+        Uint128::new(VESTING_AMOUNT)
+    );
+
+    // the full amount of the largest schedule is vested without overflow
+    let schedule = Schedule {
+        amount: Uint128::MAX,
+        ..schedule
+    };
+    assert_eq!(
+        vested_at(&schedule, VESTING_END_TIME - 1).unwrap(),
+        Uint128::MAX.multiply_ratio(
+            VESTING_END_TIME - 1 - VESTING_START_TIME,
+            VESTING_END_TIME - VESTING_START_TIME
+        )
+    );
+}
+
+#[test]
+fn test_withdraw_limited() {
+    let schedule = schedule();
+    let (amount, last_withdrawn_time) = withdraw(
+        &schedule,
+        VESTING_START_TIME,
+        VESTING_END_TIME,
+        Uint128::new(1_000_000),
+    )
+    .unwrap();
+    assert_eq!(amount, Uint128::new(792_202));
+    assert_eq!(last_withdrawn_time, VESTING_START_TIME + 1);
+
+    // less than a second worth of vesting withdraws nothing
+    let (amount, last_withdrawn_time) = withdraw(
+        &schedule,
+        last_withdrawn_time,
+        VESTING_END_TIME,
+        Uint128::new(1_000),
+    )
+    .unwrap();
+    assert_eq!(amount, Uint128::zero());
+    assert_eq!(last_withdrawn_time, VESTING_START_TIME + 1);
+}
+
+#[test]
+fn test_next_unlock_time() {
+    let schedule = schedule();
+    assert_eq!(
+        next_unlock_time(&schedule, 0).unwrap(),
+        Some(VESTING_START_TIME + 1)
+    );
+    assert_eq!(
+        next_unlock_time(&schedule, VESTING_END_TIME - 1).unwrap(),
+        Some(VESTING_END_TIME)
+    );
+    assert_eq!(next_unlock_time(&schedule, VESTING_END_TIME).unwrap(), None);
+
+    // with fewer units than seconds, units vest every few seconds
+    let schedule = Schedule {
+        amount: Uint128::new(3),
+        start_time: 0,
+        end_time: 10,
+    };
+    assert_eq!(next_unlock_time(&schedule, 0).unwrap(), Some(4));
+    assert_eq!(next_unlock_time(&schedule, 4).unwrap(), Some(7));
+    assert_eq!(next_unlock_time(&schedule, 7).unwrap(), Some(10));
+}
+
+proptest! {
+    #[test]
+    fn vested_at_is_monotonic(schedule in arb_schedule(), a in arb_time(), b in arb_time()) {
+        let (earlier, later) = (a.min(b), a.max(b));
+        prop_assert!(vested_at(&schedule, earlier).unwrap() <= vested_at(&schedule, later).unwrap());
+    }
+
+    #[test]
+    fn vested_at_never_exceeds_amount(schedule in arb_schedule(), time in arb_time()) {
+        prop_assert!(vested_at(&schedule, time).unwrap() <= schedule.amount);
+    }
+
+    #[test]
+    fn partial_withdrawals_sum_to_single_withdrawal(
+        schedule in arb_schedule(),
+        mut times in prop::collection::vec(arb_time(), 1..10),
+    ) {
+        times.sort_unstable();
+        let mut last_withdrawn_time = schedule.start_time;
+        let mut total = Uint128::zero();
+        for time in &times {
+            let (amount, time) =
+                withdraw(&schedule, last_withdrawn_time, *time, Uint128::MAX).unwrap();
+            total += amount;
+            last_withdrawn_time = time;
+        }
+        let single = withdrawable(&schedule, schedule.start_time, times[times.len() - 1]).unwrap();
+        prop_assert_eq!(total, single);
+    }
+
+    #[test]
+    fn limited_withdrawals_pay_what_was_vested(
+        schedule in arb_schedule(),
+        withdrawals in prop::collection::vec((arb_time(), any::<u128>()), 1..10),
+    ) {
+        let mut last_withdrawn_time = schedule.start_time;
+        let mut total = Uint128::zero();
+        for (time, limit) in withdrawals {
+            let limit = Uint128::new(limit);
+            let (amount, time) = withdraw(&schedule, last_withdrawn_time, time, limit).unwrap();
+            prop_assert!(amount <= limit);
+            prop_assert!(time >= last_withdrawn_time);
+            total += amount;
+            last_withdrawn_time = time;
+        }
+        // the last withdrawn time accounts for exactly the amount paid out
+        prop_assert_eq!(total, vested_at(&schedule, last_withdrawn_time).unwrap());
+    }
+
+    #[test]
+    fn vesting_time_is_earliest(schedule in arb_schedule(), amount in any::<u128>()) {
+        let amount = Uint128::new(amount).min(schedule.amount);
+        let time = vesting_time(&schedule, amount).unwrap();
+        prop_assert!(vested_at(&schedule, time).unwrap() >= amount);
+        if time > schedule.start_time {
+            prop_assert!(vested_at(&schedule, time - 1).unwrap() < amount);
+        }
+    }
+
+    #[test]
+    fn next_unlock_time_vests_more(schedule in arb_schedule(), time in arb_time()) {
+        let vested = vested_at(&schedule, time).unwrap();
+        match next_unlock_time(&schedule, time).unwrap() {
+            Some(next) => {
+                prop_assert!(next > time);
+                prop_assert!(vested_at(&schedule, next).unwrap() > vested);
+                prop_assert_eq!(vested_at(&schedule, next - 1).unwrap(), vested);
+            }
+            None => prop_assert_eq!(vested, schedule.amount),
+        }
+    }
+}
+
+#[test]
+fn test_schedule_edges() {
+    // a schedule without duration vests everything at its end
+    let schedule = Schedule {
+        amount: Uint128::new(VESTING_AMOUNT),
+        start_time: VESTING_START_TIME,
+        end_time: VESTING_START_TIME,
+    };
+    assert_eq!(
+        vested_at(&schedule, VESTING_START_TIME - 1).unwrap(),
+        Uint128::zero()
+    );
+    assert_eq!(
+        vested_at(&schedule, VESTING_START_TIME).unwrap(),
+        Uint128::new(VESTING_AMOUNT)
+    );
+    assert_eq!(
+        vesting_time(&schedule, Uint128::new(1)).unwrap(),
+        VESTING_START_TIME
+    );
+
+    // nothing vests in an empty schedule
+    let schedule = Schedule {
+        amount: Uint128::zero(),
+        ..self::schedule()
+    };
+    assert_eq!(
+        withdrawable(&schedule, VESTING_START_TIME, VESTING_END_TIME).unwrap(),
+        Uint128::zero()
+    );
+    assert_eq!(
+        next_unlock_time(&schedule, VESTING_START_TIME).unwrap(),
+        None
+    );
+
+    // a schedule ending before it starts is rejected instead of underflowing
+    let schedule = Schedule {
+        start_time: VESTING_END_TIME,
+        end_time: VESTING_START_TIME,
+        ..self::schedule()
+    };
+    assert_eq!(
+        vesting_time(&schedule, Uint128::new(1)).unwrap_err(),
+        ContractError::InvalidSchedule("End time is before start time".to_string())
+    );
+}